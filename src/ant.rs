@@ -0,0 +1,264 @@
+//! Langton's ant and its generalizations: multi-color ants written as a
+//! string of turns such as `RL` or `LLRR`, and turmites, ants with states of
+//! their own driven by a transition table.
+//!
+//! See <https://en.wikipedia.org/wiki/Langton%27s_ant> and
+//! <https://en.wikipedia.org/wiki/Turmite>.
+
+use crate::{Direction, Error};
+use std::convert::TryFrom;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// An ant walking over the grid, leaving the cells behind it recolored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Ant {
+    pub row: u32,
+    pub col: u32,
+    pub heading: Direction,
+    /// Internal state of a turmite, always 0 for plain ants.
+    pub state: u8,
+    /// The turmite's own rule, or `None` to follow the universe's ant rule.
+    pub rule: Option<Rc<AntRule>>,
+}
+
+/// What an ant does on each cell color in each of its states, color `n`
+/// being cell state `n`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct AntRule {
+    /// `table[state][color]`, every state having an entry for every color.
+    table: Vec<Vec<Transition>>,
+}
+
+/// The color an ant leaves behind, how it turns and the state it goes to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Transition {
+    pub color: u8,
+    turn: Turn,
+    pub state: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Turn {
+    Left,
+    Right,
+    Straight,
+    Reverse,
+}
+
+impl Turn {
+    /// Eighths of a clockwise turn.
+    fn eighths(self) -> u32 {
+        match self {
+            Turn::Straight => 0,
+            Turn::Right => 2,
+            Turn::Reverse => 4,
+            Turn::Left => 6,
+        }
+    }
+}
+
+impl Transition {
+    /// Heading after turning from `heading`.
+    pub fn turn(self, heading: Direction) -> Direction {
+        heading.turned(self.turn.eighths())
+    }
+}
+
+impl AntRule {
+    /// Number of cell colors the rule knows.
+    pub fn colors(&self) -> u8 {
+        self.table[0].len() as u8
+    }
+
+    /// What an ant in `state` does on a cell of `color`. Colors the rule does
+    /// not know are treated as color 0.
+    pub fn transition(&self, state: u8, color: u8) -> Transition {
+        let row = &self.table[usize::from(state)];
+        row.get(usize::from(color)).cloned().unwrap_or(row[0])
+    }
+
+    /// The turn letters of a single state rule cycling through its colors.
+    fn letters(&self) -> Option<String> {
+        if self.table.len() != 1 {
+            return None;
+        }
+        let colors = self.table[0].len();
+        let mut letters = String::with_capacity(colors);
+        for (color, transition) in self.table[0].iter().enumerate() {
+            if usize::from(transition.color) != (color + 1) % colors {
+                return None;
+            }
+            letters.push(match transition.turn {
+                Turn::Left => 'L',
+                Turn::Right => 'R',
+                Turn::Straight => 'N',
+                Turn::Reverse => 'U',
+            });
+        }
+        Some(letters)
+    }
+}
+
+impl Default for AntRule {
+    /// Langton's original ant, turning right on dead cells and left on live
+    /// ones.
+    fn default() -> AntRule {
+        "RL".parse().unwrap()
+    }
+}
+
+impl FromStr for AntRule {
+    type Err = Error;
+
+    /// Parse one letter per color in any case, where `L` and `R` turn left
+    /// and right, `N` keeps going and `U` turns around, and a cell an ant
+    /// leaves goes to the next color. Or parse a turmite's transition table
+    /// in JSON, `[[[1, 2, 0], [0, 8, 0]]]` being Langton's ant: for each state
+    /// and then each color the color to write, the turn and the next state.
+    /// Turns are coded as in Golly: 1 for none, 2 right, 4 around and 8 left.
+    /// Golly's own notation with braces is accepted too.
+    fn from_str(text: &str) -> Result<AntRule, Error> {
+        let invalid = || Error::InvalidAntRule(text.to_string());
+        let text = text.trim();
+        let table = if text.starts_with(['[', '{']) {
+            parse_table(text).ok_or_else(invalid)?
+        } else {
+            let turns = text
+                .chars()
+                .map(|letter| match letter.to_ascii_uppercase() {
+                    'L' => Some(Turn::Left),
+                    'R' => Some(Turn::Right),
+                    'N' => Some(Turn::Straight),
+                    'U' => Some(Turn::Reverse),
+                    _ => None,
+                })
+                .collect::<Option<Vec<Turn>>>()
+                .ok_or_else(invalid)?;
+            let colors = turns.len();
+            let row = turns
+                .into_iter()
+                .enumerate()
+                .map(|(color, turn)| Transition {
+                    color: ((color + 1) % colors) as u8,
+                    turn,
+                    state: 0,
+                })
+                .collect();
+            vec![row]
+        };
+
+        let colors = table.first().map_or(0, Vec::len);
+        let valid = (1..=usize::from(u8::MAX)).contains(&colors)
+            && table.len() <= usize::from(u8::MAX)
+            && table.iter().all(|row| {
+                row.len() == colors
+                    && row
+                        .iter()
+                        .all(|transition| usize::from(transition.color) < colors && usize::from(transition.state) < table.len())
+            });
+        if !valid {
+            return Err(invalid());
+        }
+        Ok(AntRule { table })
+    }
+}
+
+/// Parse a JSON array of states, each an array of colors, each a
+/// `[color, turn, state]` array.
+fn parse_table(text: &str) -> Option<Vec<Vec<Transition>>> {
+    let mut tokens = Tokens(text);
+    let table = tokens.list(|tokens| {
+        tokens.list(|tokens| {
+            let numbers = tokens.list(Tokens::number)?;
+            match numbers.as_slice() {
+                [color, turn, state] => Some(Transition {
+                    color: u8::try_from(*color).ok()?,
+                    turn: match turn {
+                        1 => Turn::Straight,
+                        2 => Turn::Right,
+                        4 => Turn::Reverse,
+                        8 => Turn::Left,
+                        _ => return None,
+                    },
+                    state: u8::try_from(*state).ok()?,
+                }),
+                _ => None,
+            }
+        })
+    })?;
+    tokens.0.trim().is_empty().then_some(table)
+}
+
+/// What is left of a JSON document being parsed.
+struct Tokens<'a>(&'a str);
+
+impl<'a> Tokens<'a> {
+    /// Consume `symbol` after any whitespace.
+    fn eat(&mut self, symbol: char) -> bool {
+        match self.0.trim_start().strip_prefix(symbol) {
+            Some(rest) => {
+                self.0 = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let text = self.0.trim_start();
+        let end = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+        let number = text[..end].parse().ok()?;
+        self.0 = &text[end..];
+        Some(number)
+    }
+
+    /// A bracketed, comma separated list of items.
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Option<T>) -> Option<Vec<T>> {
+        let close = if self.eat('[') {
+            ']'
+        } else if self.eat('{') {
+            '}'
+        } else {
+            return None;
+        };
+        let mut items = Vec::new();
+        if self.eat(close) {
+            return Some(items);
+        }
+        loop {
+            items.push(item(self)?);
+            if self.eat(close) {
+                return Some(items);
+            }
+            if !self.eat(',') {
+                return None;
+            }
+        }
+    }
+}
+
+impl fmt::Display for AntRule {
+    /// Turn letters when the rule has them, a JSON transition table otherwise.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(letters) = self.letters() {
+            return write!(f, "{}", letters);
+        }
+        let states: Vec<String> = self
+            .table
+            .iter()
+            .map(|row| {
+                let colors: Vec<String> = row
+                    .iter()
+                    .map(|transition| {
+                        let turn = 1 << (transition.turn.eighths() / 2);
+                        format!("[{}, {}, {}]", transition.color, turn, transition.state)
+                    })
+                    .collect();
+                format!("[{}]", colors.join(", "))
+            })
+            .collect();
+        write!(f, "[{}]", states.join(", "))
+    }
+}
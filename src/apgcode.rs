@@ -0,0 +1,218 @@
+//! Catagolue's apgcodes, which name an object by its period and the Extended
+//! Wechsler encoding of its canonical phase and orientation.
+//!
+//! See <https://conwaylife.com/wiki/Apgcode>.
+
+use crate::pattern::{Pattern, Transform};
+use crate::Error;
+use std::collections::{HashMap, HashSet};
+
+/// Objects that do not repeat within this many generations have no apgcode.
+const MAX_PERIOD: u32 = 1000;
+
+/// Objects with more live cells than this in any phase have no apgcode,
+/// which keeps growing patterns from stepping for long.
+const MAX_POPULATION: usize = 1024;
+
+/// Objects spanning more rows or columns than this in any phase have no
+/// apgcode, which keeps whatever they send off from being followed far.
+const MAX_EXTENT: i64 = 256;
+
+/// Symbols of 5 cell columns and of zero run lengths after a `y`.
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Live cells of an unbounded plane, as `(row, col)`.
+type Cells = HashSet<(i64, i64)>;
+
+/// Identify a still life, oscillator or spaceship under B3/S23 by running it
+/// on an unbounded plane until it repeats.
+pub(crate) fn encode(pattern: &Pattern) -> Result<String, Error> {
+    let mut cells: Cells = (0..pattern.height())
+        .flat_map(|row| (0..pattern.width()).map(move |col| (row, col)))
+        .filter(|&(row, col)| pattern.get(row, col))
+        .map(|(row, col)| (i64::from(row), i64::from(col)))
+        .collect();
+    if cells.is_empty() {
+        return Ok("xs0_0".to_string());
+    }
+
+    let (origin, first) = normalize(&cells);
+    check_size(&first)?;
+    let mut phases = vec![first.clone()];
+    for generation in 1..=MAX_PERIOD {
+        cells = step(&cells);
+        let (position, shape) = normalize(&cells);
+        check_size(&shape)?;
+        if shape == first {
+            let prefix = if position != origin {
+                format!("xq{}", generation)
+            } else if generation == 1 {
+                format!("xs{}", first.len())
+            } else {
+                format!("xp{}", generation)
+            };
+            return Ok(format!("{}_{}", prefix, canonical(&phases)));
+        }
+        if cells.is_empty() {
+            break;
+        }
+        phases.push(shape);
+    }
+    Err(Error::NotPeriodic { generations: MAX_PERIOD })
+}
+
+/// Fail when a phase `shape`, as `normalize` gives it, is too big to be
+/// followed until it repeats.
+fn check_size(shape: &[(i64, i64)]) -> Result<(), Error> {
+    let extent = shape.iter().map(|&(row, col)| row.max(col) + 1).max().unwrap_or(0);
+    if shape.len() > MAX_POPULATION || extent > MAX_EXTENT {
+        return Err(Error::ObjectSize {
+            population: MAX_POPULATION,
+            extent: MAX_EXTENT as u32,
+        });
+    }
+    Ok(())
+}
+
+/// Decode an `xs`, `xp` or `xq` apgcode, or a bare Extended Wechsler string,
+/// into the object's cells.
+pub(crate) fn decode(code: &str) -> Result<Pattern, Error> {
+    let code = code.trim();
+    let wechsler = match code.find('_') {
+        Some(idx) if ["xs", "xp", "xq"].iter().any(|prefix| code.starts_with(prefix)) => &code[idx + 1..],
+        Some(_) => return Err(parse_error("expected an xs, xp or xq apgcode")),
+        None => code,
+    };
+
+    let mut live = Vec::new();
+    let (mut top, mut col) = (0u32, 0u32);
+    let mut symbols = wechsler.chars();
+    while let Some(symbol) = symbols.next() {
+        match symbol {
+            'w' => col += 2,
+            'x' => col += 3,
+            'y' => {
+                let run = symbols
+                    .next()
+                    .filter(|run| run.is_ascii_digit() || run.is_ascii_lowercase())
+                    .and_then(|run| run.to_digit(36))
+                    .ok_or_else(|| parse_error("expected a run length after y"))?;
+                col += 4 + run;
+            }
+            'z' => {
+                top += 5;
+                col = 0;
+            }
+            '0'..='9' | 'a'..='v' => {
+                let bits = symbol.to_digit(32).unwrap();
+                for bit in 0..5 {
+                    if bits & (1 << bit) != 0 {
+                        live.push((top + bit, col));
+                    }
+                }
+                col += 1;
+            }
+            other => return Err(parse_error(&format!("unexpected symbol {:?}", other))),
+        }
+    }
+
+    let height = live.iter().map(|&(row, _)| row + 1).max().unwrap_or(0);
+    let width = live.iter().map(|&(_, col)| col + 1).max().unwrap_or(0);
+    let mut pattern = Pattern::new(width, height).map_err(|err| parse_error(&err.to_string()))?;
+    for (row, col) in live {
+        pattern.set(row, col, true);
+    }
+    Ok(pattern)
+}
+
+/// Advance one B3/S23 generation.
+fn step(cells: &Cells) -> Cells {
+    let mut neighbors: HashMap<(i64, i64), u8> = HashMap::new();
+    for &(row, col) in cells {
+        for dr in -1..=1 {
+            for dc in -1..=1 {
+                if dr != 0 || dc != 0 {
+                    *neighbors.entry((row + dr, col + dc)).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+    neighbors
+        .into_iter()
+        .filter(|&(cell, count)| count == 3 || (count == 2 && cells.contains(&cell)))
+        .map(|(cell, _)| cell)
+        .collect()
+}
+
+/// The top left corner of the bounding box of `cells`, and the cells sorted
+/// relative to it.
+fn normalize(cells: &Cells) -> ((i64, i64), Vec<(i64, i64)>) {
+    let top = cells.iter().map(|&(row, _)| row).min().unwrap_or(0);
+    let left = cells.iter().map(|&(_, col)| col).min().unwrap_or(0);
+    let mut shape: Vec<(i64, i64)> = cells.iter().map(|&(row, col)| (row - top, col - left)).collect();
+    shape.sort_unstable();
+    ((top, left), shape)
+}
+
+/// The shortest, then alphabetically first, encoding of every phase in every
+/// orientation.
+fn canonical(phases: &[Vec<(i64, i64)>]) -> String {
+    phases
+        .iter()
+        .flat_map(|shape| {
+            let height = shape.iter().map(|&(row, _)| row + 1).max().unwrap_or(0);
+            let width = shape.iter().map(|&(_, col)| col + 1).max().unwrap_or(0);
+            let mut pattern = Pattern::blank(width as u32, height as u32);
+            for &(row, col) in shape {
+                pattern.set(row as u32, col as u32, true);
+            }
+            Transform::ALL.iter().map(move |&transform| wechsler(&pattern.transformed(transform)))
+        })
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+        .unwrap_or_default()
+}
+
+/// Extended Wechsler encoding: strips of 5 rows separated by `z`, one symbol
+/// per column with the top row in the lowest bit, and runs of empty columns
+/// shortened to `w`, `x` or `y` followed by a length.
+fn wechsler(pattern: &Pattern) -> String {
+    let mut strips = Vec::new();
+    for top in (0..pattern.height()).step_by(5) {
+        let mut strip = String::new();
+        let mut zeros = 0;
+        for col in 0..pattern.width() {
+            let bits: usize = (0..5).filter(|&bit| pattern.get(top + bit, col)).map(|bit| 1 << bit).sum();
+            if bits == 0 {
+                zeros += 1;
+                continue;
+            }
+            push_zeros(&mut strip, zeros);
+            zeros = 0;
+            strip.push(DIGITS[bits] as char);
+        }
+        strips.push(strip);
+    }
+    strips.join("z")
+}
+
+fn push_zeros(strip: &mut String, mut zeros: usize) {
+    while zeros >= 4 {
+        let run = zeros.min(39);
+        strip.push('y');
+        strip.push(DIGITS[run - 4] as char);
+        zeros -= run;
+    }
+    match zeros {
+        3 => strip.push('x'),
+        2 => strip.push('w'),
+        1 => strip.push('0'),
+        _ => {}
+    }
+}
+
+fn parse_error(message: &str) -> Error {
+    Error::Parse {
+        line: 1,
+        message: message.to_string(),
+    }
+}
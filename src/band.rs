@@ -0,0 +1,47 @@
+//! Stepping a band of rows of a grid apart from the rest of it: the next
+//! generation of the band only needs the row above it and the row below it,
+//! its halo, so that workers owning a band each can step a grid together,
+//! handing each other their top and bottom rows every generation.
+
+use crate::bitwise;
+use fixedbitset::FixedBitSet;
+
+/// Lay out `width` cells of a row packed into 32 bit words, the first in
+/// the lowest bit, along `cells` from bit `start` on.
+pub(crate) fn unpack(words: &[u32], width: usize, cells: &mut FixedBitSet, start: usize) {
+    for col in 0..width {
+        cells.set(start + col, words[col / 32] >> (col % 32) & 1 == 1);
+    }
+}
+
+/// The next generation of the middle `height` rows of the `width` cells
+/// wide `rows`, under the `birth` and `survival` bitmasks of neighbor
+/// counts, the first and last rows of `rows` being the halo. The left and
+/// right edges are stitched together when `wrap_cols` is set, and meet
+/// dead cells otherwise.
+pub(crate) fn step(rows: &FixedBitSet, (width, height): (usize, usize), wrap_cols: bool, masks: (u16, u16)) -> FixedBitSet {
+    let mut next = FixedBitSet::with_capacity(rows.len());
+    bitwise::step(rows, &mut next, (width, height + 2), false, false, masks, |_, _| true);
+    // The bitwise kernel leaves the first and last columns wrong.
+    for row in 1..=height {
+        for col in [0, width - 1] {
+            let mut count = 0;
+            for neighbor_row in row - 1..=row + 1 {
+                // Columns a grid's width to the right, so as not to go below 0.
+                for shifted_col in col + width - 1..=col + width + 1 {
+                    if (width..2 * width).contains(&shifted_col) || wrap_cols {
+                        count += rows[neighbor_row * width + shifted_col % width] as u16;
+                    }
+                }
+            }
+            let alive = rows[row * width + col];
+            let mask = if alive { masks.1 } else { masks.0 };
+            next.set(row * width + col, mask >> (count - alive as u16) & 1 == 1);
+        }
+    }
+    let mut band = FixedBitSet::with_capacity(width * height);
+    for idx in 0..width * height {
+        band.set(idx, next[width + idx]);
+    }
+    band
+}
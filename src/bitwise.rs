@@ -0,0 +1,258 @@
+//! Stepping two state B/S rules on the Moore neighborhood 64 cells at a
+//! time: the grid is read as one long string of bits, row after row, so that
+//! a cell's neighbors are the bits 1, `width - 1`, `width` and `width + 1`
+//! before and after it, and the 8 shifted words are added up bit by bit with
+//! full adders into the 4 bits of every cell's neighbor count.
+//!
+//! Shifting along the string wraps each row's first and last cells into the
+//! rows next to them, so those two columns come out wrong and have to be
+//! stepped cell by cell afterwards.
+//!
+//! With the `simd` feature, builds for wasm32 with the `simd128` target
+//! feature add up two words at a time in 128 bit vectors. The choice is made
+//! at compile time, as WebAssembly engines refuse whole modules holding
+//! instructions they lack, so a page wanting both loads the SIMD build where
+//! the engine validates SIMD and the plain one elsewhere, as the README
+//! shows.
+
+use fixedbitset::FixedBitSet;
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+/// Bits of many cells side by side, one cell per bit position.
+trait Lanes: Copy + Default + BitAnd<Output = Self> + BitOr<Output = Self> + BitXor<Output = Self> + Not<Output = Self> {}
+
+impl<T> Lanes for T where T: Copy + Default + BitAnd<Output = T> + BitOr<Output = T> + BitXor<Output = T> + Not<Output = T> {}
+
+/// Sum and carry of three bits, in every bit position at once.
+fn full_add<T: Lanes>(a: T, b: T, c: T) -> (T, T) {
+    (a ^ b ^ c, a & b | c & (a ^ b))
+}
+
+/// The 4 bits, lowest first, of the number of set bits among `bits` in
+/// every bit position.
+fn count<T: Lanes>(bits: [T; 8]) -> [T; 4] {
+    let (ones_a, twos_a) = full_add(bits[0], bits[1], bits[2]);
+    let (ones_b, twos_b) = full_add(bits[3], bits[4], bits[5]);
+    let (ones_c, twos_c) = (bits[6] ^ bits[7], bits[6] & bits[7]);
+    let (ones, twos_d) = full_add(ones_a, ones_b, ones_c);
+    let (twos_e, fours_a) = full_add(twos_a, twos_b, twos_c);
+    let (twos, fours_b) = (twos_e ^ twos_d, twos_e & twos_d);
+    [ones, twos, fours_a ^ fours_b, fours_a & fours_b]
+}
+
+/// The next generation of the cells `alive` with the 8 neighbors `around`,
+/// by what a cell with each neighbor count becomes when dead and when alive.
+fn step_lanes<T: Lanes>(around: [T; 8], alive: T, fates: &[(usize, bool, bool)]) -> T {
+    let bits = count(around);
+    let mut next = T::default();
+    for &(count, born, survives) in fates {
+        let matches = (0..4).fold(!T::default(), |matches, bit| {
+            matches & if count >> bit & 1 == 1 { bits[bit] } else { !bits[bit] }
+        });
+        next = next
+            | match (born, survives) {
+                (true, true) => matches,
+                (true, false) => matches & !alive,
+                (false, _) => matches & alive,
+            };
+    }
+    next
+}
+
+#[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+mod simd {
+    use core::arch::wasm32::*;
+    use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+    /// Two words of cells in a 128 bit vector.
+    #[derive(Clone, Copy)]
+    pub(super) struct Wide(v128);
+
+    impl Wide {
+        pub fn new(low: u64, high: u64) -> Wide {
+            Wide(u64x2(low, high))
+        }
+
+        pub fn words(self) -> [u64; 2] {
+            [u64x2_extract_lane::<0>(self.0), u64x2_extract_lane::<1>(self.0)]
+        }
+    }
+
+    impl Default for Wide {
+        fn default() -> Wide {
+            Wide(u64x2_splat(0))
+        }
+    }
+
+    impl BitAnd for Wide {
+        type Output = Wide;
+
+        fn bitand(self, other: Wide) -> Wide {
+            Wide(v128_and(self.0, other.0))
+        }
+    }
+
+    impl BitOr for Wide {
+        type Output = Wide;
+
+        fn bitor(self, other: Wide) -> Wide {
+            Wide(v128_or(self.0, other.0))
+        }
+    }
+
+    impl BitXor for Wide {
+        type Output = Wide;
+
+        fn bitxor(self, other: Wide) -> Wide {
+            Wide(v128_xor(self.0, other.0))
+        }
+    }
+
+    impl Not for Wide {
+        type Output = Wide;
+
+        fn not(self) -> Wide {
+            Wide(v128_not(self.0))
+        }
+    }
+}
+
+/// The cells of a grid as one string of bits, with the row above the grid
+/// before them and the row below it after them, followed by a dead row.
+pub(crate) struct Padded {
+    words: Vec<u64>,
+    /// Where the cells start, on a word boundary.
+    pub base: usize,
+}
+
+impl Padded {
+    /// Pad the `width` by `height` grid of `cells` with its last and first
+    /// rows when `wrap_rows` is set, and with rows all `outside` otherwise.
+    pub fn new(cells: &FixedBitSet, (width, height): (usize, usize), wrap_rows: bool, outside: bool) -> Padded {
+        let size = width * height;
+        let words = size.div_ceil(64);
+        let base = (width + 1).div_ceil(64) * 64;
+        let mut padded = vec![0u64; (base + size + 2 * width + 1).div_ceil(64) + 1];
+        let blocks = cells.as_slice();
+        for (idx, word) in padded[base / 64..base / 64 + words].iter_mut().enumerate() {
+            let block = |idx: usize| u64::from(blocks.get(idx).cloned().unwrap_or(0));
+            *word = block(2 * idx) | block(2 * idx + 1) << 32;
+        }
+        if !size.is_multiple_of(64) {
+            padded[base / 64 + words - 1] &= (1 << (size % 64)) - 1;
+        }
+        for col in 0..width {
+            let (above, below) = if wrap_rows { (cells[size - width + col], cells[col]) } else { (outside, outside) };
+            for (bit, alive) in [(base - width + col, above), (base + size + col, below)] {
+                padded[bit / 64] |= u64::from(alive) << (bit % 64);
+            }
+        }
+        Padded { words: padded, base }
+    }
+
+    /// 64 bits of the string from bit `start` on.
+    pub fn read(&self, start: usize) -> u64 {
+        let (idx, offset) = (start / 64, start % 64);
+        match offset {
+            0 => self.words[idx],
+            _ => self.words[idx] >> offset | self.words[idx + 1] << (64 - offset),
+        }
+    }
+}
+
+/// Words of cells each thread steps at a time with the `threads` feature.
+#[cfg(feature = "threads")]
+const WORDS_PER_JOB: usize = 1024;
+
+/// Step the `width` by `height` grid of `cells` into `next` by the `birth`
+/// and `survival` bitmasks of neighbor counts. The rows above and below the
+/// grid are its last and first rows when `wrap_rows` is set and all
+/// `outside` otherwise. The first and last columns are left wrong, and so
+/// are the words of cells `busy` gives false for, from their first cell to
+/// their last, which keep what `next` had. With the `threads` feature, runs
+/// of words are stepped on different threads.
+pub(crate) fn step(
+    cells: &FixedBitSet,
+    next: &mut FixedBitSet,
+    (width, height): (usize, usize),
+    wrap_rows: bool,
+    outside: bool,
+    (birth, survival): (u16, u16),
+    busy: impl Fn(usize, usize) -> bool + Sync,
+) {
+    let size = width * height;
+    let words = size.div_ceil(64);
+    let padded = Padded::new(cells, (width, height), wrap_rows, outside);
+    let (base, read) = (padded.base, |start: usize| padded.read(start));
+    // The 8 neighbors of the cells of word `word`.
+    let around = |word: usize| {
+        let start = base + 64 * word;
+        [
+            read(start - width - 1),
+            read(start - width),
+            read(start - width + 1),
+            read(start - 1),
+            read(start + 1),
+            read(start + width - 1),
+            read(start + width),
+            read(start + width + 1),
+        ]
+    };
+    let alive = |word: usize| read(base + 64 * word);
+    // What a cell with each neighbor count becomes when dead and when alive,
+    // leaving out the counts that kill it either way.
+    let fates: Vec<(usize, bool, bool)> = (0..=8)
+        .map(|count| (count, birth >> count & 1 == 1, survival >> count & 1 == 1))
+        .filter(|&(_, born, survives)| born || survives)
+        .collect();
+    let busy = |word: usize| busy(64 * word, (64 * word + 63).min(size - 1));
+    // Step the words from `start` on into `blocks`, the blocks of `next`
+    // from the first of word `start` on.
+    let step_words = |start: usize, blocks: &mut [u32]| {
+        let end = start + blocks.len().div_ceil(2);
+        let mut put = |word: usize, mut cells: u64| {
+            if word == words - 1 && !size.is_multiple_of(64) {
+                cells &= (1 << (size % 64)) - 1;
+            }
+            let idx = 2 * (word - start);
+            blocks[idx] = cells as u32;
+            if let Some(block) = blocks.get_mut(idx + 1) {
+                *block = (cells >> 32) as u32;
+            }
+        };
+
+        let mut word = start;
+        #[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+        while word + 2 <= end {
+            use simd::Wide;
+            if !busy(word) && !busy(word + 1) {
+                word += 2;
+                continue;
+            }
+            let (first, second) = (around(word), around(word + 1));
+            let around = std::array::from_fn(|idx| Wide::new(first[idx], second[idx]));
+            let [low, high] = step_lanes(around, Wide::new(alive(word), alive(word + 1)), &fates).words();
+            put(word, low);
+            put(word + 1, high);
+            word += 2;
+        }
+        while word < end {
+            if busy(word) {
+                put(word, step_lanes(around(word), alive(word), &fates));
+            }
+            word += 1;
+        }
+    };
+
+    let next_blocks = next.as_mut_slice();
+    #[cfg(feature = "threads")]
+    if crate::threads::running() && words > WORDS_PER_JOB {
+        use rayon::prelude::*;
+        next_blocks
+            .par_chunks_mut(2 * WORDS_PER_JOB)
+            .enumerate()
+            .for_each(|(job, blocks)| step_words(job * WORDS_PER_JOB, blocks));
+        return;
+    }
+    step_words(0, next_blocks);
+}
@@ -0,0 +1,102 @@
+use crate::{Boundary, Error, Universe, DEFAULT_DENSITY};
+use wasm_bindgen::prelude::*;
+
+/**
+ * Chainable configuration for a new universe.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct UniverseBuilder {
+    width: u32,
+    height: u32,
+    density: f64,
+    seed: Option<u32>,
+    boundary: Boundary,
+    cells: Vec<(u32, u32)>,
+}
+
+impl UniverseBuilder {
+    pub fn with_cells(mut self, cells: &[(u32, u32)]) -> UniverseBuilder {
+        self.cells.extend_from_slice(cells);
+        self
+    }
+}
+
+impl Default for UniverseBuilder {
+    fn default() -> UniverseBuilder {
+        UniverseBuilder {
+            width: 64,
+            height: 64,
+            density: DEFAULT_DENSITY,
+            seed: None,
+            boundary: Boundary::Torus,
+            cells: Vec::new(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl UniverseBuilder {
+    /**
+     * Start from a 64x64 torus randomized like `Universe::new`.
+     */
+    pub fn new() -> UniverseBuilder {
+        UniverseBuilder::default()
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> UniverseBuilder {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /**
+     * Probability of each cell starting alive; use 0 for an empty background.
+     */
+    pub fn density(mut self, density: f64) -> UniverseBuilder {
+        self.density = density;
+        self
+    }
+
+    pub fn seed(mut self, seed: u32) -> UniverseBuilder {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn boundary(mut self, boundary: Boundary) -> UniverseBuilder {
+        self.boundary = boundary;
+        self
+    }
+
+    /**
+     * Cells to bring to life after randomizing, as flattened `row, col` pairs.
+     */
+    pub fn cells(self, coords: &[u32]) -> UniverseBuilder {
+        let cells: Vec<(u32, u32)> = coords
+            .chunks_exact(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect();
+        self.with_cells(&cells)
+    }
+
+    /**
+     * Make the universe, failing when any of the cells is out of range.
+     */
+    pub fn build(self) -> Result<Universe, JsError> {
+        let mut universe = Universe::new_empty(self.width, self.height);
+        let indices = self
+            .cells
+            .iter()
+            .map(|&(row, col)| universe.checked_index(row, col))
+            .collect::<Result<Vec<usize>, Error>>()?;
+        if let Some(seed) = self.seed {
+            universe.set_seed(seed);
+        }
+        universe.boundary = self.boundary;
+        universe.reset_with_density(self.density);
+        for idx in indices {
+            universe.set_alive(idx, true);
+        }
+        Ok(universe)
+    }
+}
@@ -0,0 +1,274 @@
+//! Continuous cellular automata, whose cells hold a level between 0 and 1
+//! rather than one of a few states: Lenia, which convolves the grid with a
+//! ring shaped kernel and grows or shrinks every cell by how well the result
+//! fits a bell curve, and SmoothLife, which brings Life's birth and survival
+//! to the fillings of a disk and of the ring around it.
+//!
+//! Both are computed by plain convolution, which is fine for small grids and
+//! radii but slows down with their area.
+//!
+//! See <https://en.wikipedia.org/wiki/Lenia> and
+//! <https://arxiv.org/abs/1111.1567>.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// A continuous rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Continuous {
+    Lenia(Lenia),
+    SmoothLife(SmoothLife),
+}
+
+impl Continuous {
+    /// Parse `LENIA` or `SMOOTHLIFE` and their parameters, in upper case.
+    pub fn parse(text: &str) -> Option<Continuous> {
+        if let Some(parameters) = text.strip_prefix("LENIA") {
+            return Lenia::parse(parameters).map(Continuous::Lenia);
+        }
+        let parameters = text.strip_prefix("SMOOTHLIFE")?;
+        SmoothLife::parse(parameters).map(Continuous::SmoothLife)
+    }
+
+    /// Row and column offsets of the cells a cell's next level depends on,
+    /// each with its weight in the two sums passed to `next`.
+    pub fn kernel(&self) -> Vec<(i32, i32, [f32; 2])> {
+        match self {
+            Continuous::Lenia(lenia) => lenia.kernel().into_iter().map(|(row, col, weight)| (row, col, [weight, 0.0])).collect(),
+            Continuous::SmoothLife(smooth_life) => smooth_life.kernel(),
+        }
+    }
+
+    /// The level of a cell at `level` after one step, given the sums of the
+    /// levels around it weighted by the kernel.
+    pub fn next(&self, level: f32, sums: [f32; 2]) -> f32 {
+        match self {
+            Continuous::Lenia(lenia) => lenia.next(level, sums[0]),
+            Continuous::SmoothLife(smooth_life) => smooth_life.next(sums),
+        }
+    }
+}
+
+impl fmt::Display for Continuous {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Continuous::Lenia(lenia) => write!(f, "{}", lenia),
+            Continuous::SmoothLife(smooth_life) => write!(f, "{}", smooth_life),
+        }
+    }
+}
+
+/// Split parameters such as `/R13/M0.15` into their letter and value, or
+/// `None` if `text` does not start with one.
+fn parameters(text: &str) -> Option<Vec<(&str, &str)>> {
+    if !text.is_empty() && !text.starts_with('/') {
+        return None;
+    }
+    text.split('/').skip(1).map(|part| part.split_at_checked(1)).collect()
+}
+
+/// The parameters of a Lenia rule with a single ring kernel.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Lenia {
+    /// Reach of the kernel in cells.
+    pub radius: u32,
+    /// Center and width of the bell curve of neighborhood sums that make
+    /// cells grow.
+    pub mu: f32,
+    pub sigma: f32,
+    /// Steps per unit of time, each adding `1 / time_steps` of the growth.
+    pub time_steps: f32,
+}
+
+impl Lenia {
+    /// Orbium, the best known Lenia creature, lives under these.
+    pub const ORBIUM: Lenia = Lenia {
+        radius: 13,
+        mu: 0.15,
+        sigma: 0.015,
+        time_steps: 10.0,
+    };
+
+    /// Parse the `/R` radius, `/M` mu, `/S` sigma and `/T` time steps
+    /// following `Lenia`, any of which may be left out.
+    fn parse(text: &str) -> Option<Lenia> {
+        let mut lenia = Lenia::ORBIUM;
+        for parameter in parameters(text)? {
+            match parameter {
+                ("R", radius) => lenia.radius = radius.parse().ok().filter(|radius| (1..=64).contains(radius))?,
+                ("M", mu) => lenia.mu = mu.parse().ok().filter(|mu: &f32| mu.is_finite())?,
+                ("S", sigma) => lenia.sigma = sigma.parse().ok().filter(|&sigma: &f32| sigma > 0.0 && sigma.is_finite())?,
+                ("T", steps) => lenia.time_steps = steps.parse().ok().filter(|&steps: &f32| steps >= 1.0 && steps.is_finite())?,
+                _ => return None,
+            }
+        }
+        Some(lenia)
+    }
+
+    /// Row and column offsets of the cells within `radius` and their weights,
+    /// which add up to 1: a smooth bump peaking halfway out.
+    fn kernel(&self) -> Vec<(i32, i32, f32)> {
+        let reach = self.radius as i32;
+        let mut kernel = Vec::new();
+        for delta_row in -reach..=reach {
+            for delta_col in -reach..=reach {
+                let distance = f64::from(delta_row * delta_row + delta_col * delta_col).sqrt() / f64::from(self.radius);
+                if distance > 0.0 && distance < 1.0 {
+                    let weight = (4.0 - 1.0 / (4.0 * distance * (1.0 - distance))).exp();
+                    kernel.push((delta_row, delta_col, weight as f32));
+                }
+            }
+        }
+        let total: f32 = kernel.iter().map(|&(_, _, weight)| weight).sum();
+        kernel.iter_mut().for_each(|(_, _, weight)| *weight /= total);
+        kernel
+    }
+
+    /// The level of a cell at `level` whose kernel weighted neighborhood adds
+    /// up to `sum` after one step.
+    fn next(&self, level: f32, sum: f32) -> f32 {
+        let growth = 2.0 * (-(sum - self.mu).powi(2) / (2.0 * self.sigma * self.sigma)).exp() - 1.0;
+        (level + growth / self.time_steps).clamp(0.0, 1.0)
+    }
+
+    fn bits(&self) -> (u32, u32, u32, u32) {
+        (self.radius, self.mu.to_bits(), self.sigma.to_bits(), self.time_steps.to_bits())
+    }
+}
+
+impl PartialEq for Lenia {
+    fn eq(&self, other: &Lenia) -> bool {
+        self.bits() == other.bits()
+    }
+}
+
+impl Eq for Lenia {}
+
+impl Hash for Lenia {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bits().hash(state);
+    }
+}
+
+impl fmt::Display for Lenia {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if *self == Lenia::ORBIUM {
+            return write!(f, "Lenia");
+        }
+        write!(f, "Lenia/R{}/M{}/S{}/T{}", self.radius, self.mu, self.sigma, self.time_steps)
+    }
+}
+
+/// The parameters of a SmoothLife rule in discrete time: a cell's next level
+/// only depends on the filling of the disk around it, a third of `radius`
+/// across, and of the ring from there out to `radius`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SmoothLife {
+    pub radius: u32,
+    /// Ring fillings giving birth to a dead cell, from the lower to the upper
+    /// end, as Life's `B3`.
+    pub birth: (f32, f32),
+    /// Ring fillings keeping a live cell alive, as Life's `S23`.
+    pub survival: (f32, f32),
+}
+
+/// How smoothly the ring and disk fillings cross the interval ends.
+const SMOOTH_RING: f32 = 0.028;
+const SMOOTH_DISK: f32 = 0.147;
+
+impl SmoothLife {
+    /// The rule of Rafler's paper, which has gliders.
+    pub const RAFLER: SmoothLife = SmoothLife {
+        radius: 21,
+        birth: (0.278, 0.365),
+        survival: (0.267, 0.445),
+    };
+
+    /// Parse the `/R` radius, `/B` birth and `/D` survival intervals
+    /// following `SmoothLife`, the intervals written as `0.278-0.365`.
+    fn parse(text: &str) -> Option<SmoothLife> {
+        let interval = |text: &str| -> Option<(f32, f32)> {
+            let (low, high) = text.split_once('-')?;
+            let (low, high): (f32, f32) = (low.parse().ok()?, high.parse().ok()?);
+            (0.0 <= low && low <= high && high <= 1.0).then_some((low, high))
+        };
+        let mut smooth_life = SmoothLife::RAFLER;
+        for parameter in parameters(text)? {
+            match parameter {
+                ("R", radius) => smooth_life.radius = radius.parse().ok().filter(|radius| (3..=64).contains(radius))?,
+                ("B", birth) => smooth_life.birth = interval(birth)?,
+                ("D", survival) => smooth_life.survival = interval(survival)?,
+                _ => return None,
+            }
+        }
+        Some(smooth_life)
+    }
+
+    /// Weights of the cells in the inner disk and in the outer ring, each
+    /// adding up to 1, cells on the edges counting in part.
+    fn kernel(&self) -> Vec<(i32, i32, [f32; 2])> {
+        let outer = self.radius as f32;
+        let inner = outer / 3.0;
+        let reach = self.radius as i32 + 1;
+        let mut kernel = Vec::new();
+        for delta_row in -reach..=reach {
+            for delta_col in -reach..=reach {
+                let distance = ((delta_row * delta_row + delta_col * delta_col) as f32).sqrt();
+                let disk = (inner + 0.5 - distance).clamp(0.0, 1.0);
+                let ring = (outer + 0.5 - distance).clamp(0.0, 1.0) - disk;
+                if disk > 0.0 || ring > 0.0 {
+                    kernel.push((delta_row, delta_col, [disk, ring]));
+                }
+            }
+        }
+        for channel in 0..2 {
+            let total: f32 = kernel.iter().map(|(_, _, weights)| weights[channel]).sum();
+            kernel.iter_mut().for_each(|(_, _, weights)| weights[channel] /= total);
+        }
+        kernel
+    }
+
+    /// The next level of a cell whose disk and ring have the fillings in
+    /// `sums`: between the birth interval's ends for a dead disk and the
+    /// survival interval's for a live one, blended by the disk's filling.
+    fn next(&self, sums: [f32; 2]) -> f32 {
+        let [disk, ring] = sums;
+        let step = |x: f32, edge: f32, width: f32| 1.0 / (1.0 + (-(x - edge) * 4.0 / width).exp());
+        let alive = step(disk, 0.5, SMOOTH_DISK);
+        let blend = |dead: f32, live: f32| dead * (1.0 - alive) + live * alive;
+        let (low, high) = (blend(self.birth.0, self.survival.0), blend(self.birth.1, self.survival.1));
+        step(ring, low, SMOOTH_RING) * (1.0 - step(ring, high, SMOOTH_RING))
+    }
+
+    fn bits(&self) -> (u32, [u32; 4]) {
+        let (birth, survival) = (self.birth, self.survival);
+        (self.radius, [birth.0.to_bits(), birth.1.to_bits(), survival.0.to_bits(), survival.1.to_bits()])
+    }
+}
+
+impl PartialEq for SmoothLife {
+    fn eq(&self, other: &SmoothLife) -> bool {
+        self.bits() == other.bits()
+    }
+}
+
+impl Eq for SmoothLife {}
+
+impl Hash for SmoothLife {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bits().hash(state);
+    }
+}
+
+impl fmt::Display for SmoothLife {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if *self == SmoothLife::RAFLER {
+            return write!(f, "SmoothLife");
+        }
+        write!(
+            f,
+            "SmoothLife/R{}/B{}-{}/D{}-{}",
+            self.radius, self.birth.0, self.birth.1, self.survival.0, self.survival.1
+        )
+    }
+}
@@ -0,0 +1,102 @@
+//! Stepping two state B/S rules from every cell's count of live neighbors,
+//! kept from one generation to the next: a cell can only change when it or
+//! one of its neighbors changed in the generation before, so a mostly still
+//! universe looks at the few cells around its changes rather than at all of
+//! them, and updates the counts around the cells that change.
+
+use crate::Boundary;
+use fixedbitset::FixedBitSet;
+use std::mem::{size_of, size_of_val};
+
+/// The grid the counts are for: its width, height, boundary and torus shift.
+pub(crate) type Topology = (u32, u32, Boundary, i32);
+
+/// The live neighbors of every cell of a grid, with the cells that changed
+/// since the grid was last stepped.
+#[derive(Clone, Debug)]
+pub(crate) struct Counts {
+    topology: Topology,
+    /// The birth and survival bitmasks the grid was last stepped by, if it
+    /// was stepped since the counts were made.
+    masks: Option<(u16, u16)>,
+    /// Live neighbors of each cell.
+    counts: Vec<u8>,
+    /// Cells that changed since the last step, some maybe more than once.
+    changed: Vec<usize>,
+    /// Whether every cell may change in the next step, as after the counts
+    /// were made or the rule changed.
+    everything: bool,
+    /// Cells already among the candidates being gathered, left empty in
+    /// between.
+    queued: FixedBitSet,
+}
+
+impl Counts {
+    /// Counts of live neighbors `counts` of the cells of a grid of
+    /// `topology`, every cell of which may change in the next step.
+    pub fn new(topology: Topology, counts: Vec<u8>) -> Counts {
+        let queued = FixedBitSet::with_capacity(counts.len());
+        Counts {
+            topology,
+            masks: None,
+            counts,
+            changed: Vec::new(),
+            everything: true,
+            queued,
+        }
+    }
+
+    /// Whether these are the counts of a grid of `topology`.
+    pub fn fits(&self, topology: Topology) -> bool {
+        self.topology == topology
+    }
+
+    /// About how many bytes the counts take, counting what their storage
+    /// has room for.
+    pub fn bytes(&self) -> usize {
+        self.counts.capacity() + self.changed.capacity() * size_of::<usize>() + size_of_val(self.queued.as_slice())
+    }
+
+    pub fn count(&self, idx: usize) -> u8 {
+        self.counts[idx]
+    }
+
+    /// Note that cell `idx` came to life if `alive` and died otherwise,
+    /// counting it into or out of the `neighbors` around it.
+    pub fn change(&mut self, idx: usize, alive: bool, neighbors: impl Iterator<Item = usize>) {
+        for neighbor in neighbors {
+            if alive {
+                self.counts[neighbor] += 1;
+            } else {
+                self.counts[neighbor] -= 1;
+            }
+        }
+        self.changed.push(idx);
+    }
+
+    /// The cells that may change when the grid is stepped by `masks`, each
+    /// once: those that changed since the last step and their `neighbors`.
+    /// Starts over noting changes.
+    pub fn candidates<I: Iterator<Item = usize>>(&mut self, masks: (u16, u16), neighbors: impl Fn(usize) -> I) -> Vec<usize> {
+        let everything = self.everything || self.masks != Some(masks);
+        let candidates = if everything {
+            (0..self.counts.len()).collect()
+        } else {
+            let mut candidates = Vec::new();
+            for &idx in &self.changed {
+                for cell in std::iter::once(idx).chain(neighbors(idx)) {
+                    if !self.queued[cell] {
+                        self.queued.insert(cell);
+                        candidates.push(cell);
+                    }
+                }
+            }
+            candidates.iter().for_each(|&cell| self.queued.set(cell, false));
+            candidates
+        };
+        self.masks = Some(masks);
+        self.everything = false;
+        self.changed.clear();
+        candidates
+    }
+}
@@ -0,0 +1,71 @@
+//! The surface of a cube as a topology: six square faces laid out on the
+//! grid three across and two down, every edge of a face stitched to the
+//! edge of the face it meets on the cube.
+//!
+//! Faces are placed in space by their outward normal and the directions
+//! their rows and columns run in. A neighbor beyond the edge of a face is
+//! found by folding the plane of the face over that edge onto the next
+//! face, so patterns cross from one face to another without turning
+//! inside out.
+
+/// Space vectors along the x, y and z axes.
+type Vector = [i64; 3];
+
+/// Every face's outward normal, the direction of its rows, going down, and
+/// of its columns, going right, in the order faces are laid out on the
+/// grid. Columns cross rows to the normal on every face.
+const FACES: [(Vector, Vector, Vector); 6] = [
+    ([0, 0, 1], [0, 1, 0], [1, 0, 0]),
+    ([1, 0, 0], [0, 1, 0], [0, 0, -1]),
+    ([0, 0, -1], [0, 1, 0], [-1, 0, 0]),
+    ([-1, 0, 0], [0, 1, 0], [0, 0, 1]),
+    ([0, 1, 0], [0, 0, -1], [1, 0, 0]),
+    ([0, -1, 0], [0, 0, 1], [1, 0, 0]),
+];
+
+fn dot(a: Vector, b: Vector) -> i64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn add(a: Vector, b: Vector, scale: i64) -> Vector {
+    [a[0] + b[0] * scale, a[1] + b[1] * scale, a[2] + b[2] * scale]
+}
+
+/// The grid position of the cell offset from (row, col) on a cube with
+/// faces of `size` cells across, or `None` when the offset goes past a
+/// corner of the face, where only three faces meet, or past the whole face
+/// next to it.
+pub(crate) fn neighbor(size: u32, row: u32, col: u32, delta_row: i32, delta_col: i32) -> Option<(u32, u32)> {
+    let n = i64::from(size);
+    let face = (row / size * 3 + col / size) as usize;
+    let face_row = i64::from(row % size) + i64::from(delta_row);
+    let face_col = i64::from(col % size) + i64::from(delta_col);
+    let (row_inside, col_inside) = ((0..n).contains(&face_row), (0..n).contains(&face_col));
+    if row_inside && col_inside {
+        return Some(((row as i64 + i64::from(delta_row)) as u32, (col as i64 + i64::from(delta_col)) as u32));
+    }
+    if !row_inside && !col_inside {
+        return None;
+    }
+
+    // The cell's center in space, on a cube reaching from -n to n with the
+    // cells 2 apart.
+    let (normal, down, right) = FACES[face];
+    let mut point = add(add([0; 3], normal, n), down, 2 * face_row + 1 - n);
+    point = add(point, right, 2 * face_col + 1 - n);
+    // Fold the part sticking out past the edge down the next face.
+    let edge = if row_inside { right } else { down };
+    let along = dot(point, edge);
+    let beyond = along.abs() - n;
+    point = add(add(point, edge, along.signum() * n - along), normal, -beyond);
+
+    let next_normal = add([0; 3], edge, along.signum());
+    let next_face = FACES.iter().position(|&(normal, _, _)| normal == next_normal)?;
+    let (_, next_down, next_right) = FACES[next_face];
+    let (next_row, next_col) = ((dot(point, next_down) + n - 1) / 2, (dot(point, next_right) + n - 1) / 2);
+    if !(0..n).contains(&next_row) || !(0..n).contains(&next_col) {
+        return None;
+    }
+    let (face_row, face_col) = (next_face as i64 / 3 * n, next_face as i64 % 3 * n);
+    Some(((face_row + next_row) as u32, (face_col + next_col) as u32))
+}
@@ -0,0 +1,161 @@
+use crate::infinite::MAX_CHUNK_LOG2_STEPS;
+use crate::pattern::MAX_CELLS;
+use std::fmt;
+
+/// Errors reported by fallible universe operations.
+///
+/// Exported methods surface these to JS as a thrown `Error` through
+/// `wasm_bindgen::JsError`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A coordinate fell outside of the grid.
+    OutOfBounds {
+        row: u32,
+        col: u32,
+        width: u32,
+        height: u32,
+    },
+    /// A layer fell outside of a 3D grid.
+    LayerOutOfBounds { layer: u32, depth: u32 },
+    /// Text could not be parsed as a universe or pattern.
+    Parse { line: usize, message: String },
+    /// A pattern would have more cells than patterns can have.
+    PatternSize { width: u32, height: u32 },
+    /// A buffer is too short for the universe it should describe.
+    DataLength { expected: usize, actual: usize },
+    /// No pattern in the library has the given name.
+    UnknownPattern(String),
+    /// A pattern can not be aimed in the requested direction.
+    UnsupportedDirection { pattern: String, direction: String },
+    /// Downloading a pattern failed with the given HTTP status.
+    Fetch { url: String, status: u16 },
+    /// A pattern did not return to its starting shape in time.
+    NotPeriodic { generations: u32 },
+    /// A pattern grew past the live cells or the rows and columns it may
+    /// have to be identified.
+    ObjectSize { population: usize, extent: u32 },
+    /// A rulestring is not in B/S notation.
+    InvalidRule(String),
+    /// A 3D rule is not in Bays' notation.
+    InvalidRule3d(String),
+    /// An ant rule is not a string of turns.
+    InvalidAntRule(String),
+    /// A neighborhood has more cells than neighbor counts can reach.
+    NeighborhoodSize(usize),
+    /// An operation only makes sense under another rule.
+    WrongRule { expected: String, actual: String },
+    /// Two rules can not be blended into each other.
+    IncompatibleRules { from: String, to: String },
+    /// A rule can not run on an unbounded universe.
+    UnboundedRule(String),
+    /// A rule can not run on a layer of a layered universe.
+    LayerRule(String),
+    /// An unbounded universe can not step so many generations at once.
+    Superstep { log2_steps: u32 },
+    /// Chunks would take too long to step so many generations one by one.
+    ChunkSuperstep { log2_steps: u32 },
+    /// The build steps on one thread only.
+    NoThreads,
+    /// The page is not cross-origin isolated, so it can not share memory
+    /// with web workers.
+    NotIsolated,
+    /// Rows that are not a band of the grid.
+    Band { row_start: u32, row_end: u32, height: u32 },
+    /// A halo row is shorter than a row of the grid.
+    HaloLength { expected: usize, actual: usize },
+    /// A universe can not be stepped a band at a time, for the reason given.
+    Unbandable(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::OutOfBounds {
+                row,
+                col,
+                width,
+                height,
+            } => write!(
+                f,
+                "cell at row {} col {} is outside of the {}x{} universe",
+                row, col, width, height
+            ),
+            Error::LayerOutOfBounds { layer, depth } => {
+                write!(f, "layer {} is outside of the {} layers of the universe", layer, depth)
+            }
+            Error::Parse { line, message } => write!(f, "parse error on line {}: {}", line, message),
+            Error::PatternSize { width, height } => write!(
+                f,
+                "a {}x{} pattern has more than the {} cells a pattern can have",
+                width, height, MAX_CELLS
+            ),
+            Error::DataLength { expected, actual } => {
+                write!(f, "expected at least {} bytes of cell data, got {}", expected, actual)
+            }
+            Error::UnknownPattern(name) => write!(f, "there is no pattern named {:?}", name),
+            Error::UnsupportedDirection { pattern, direction } => {
+                write!(f, "{} can not travel {}", pattern, direction)
+            }
+            Error::Fetch { url, status } => write!(f, "fetching {} failed with HTTP status {}", url, status),
+            Error::NotPeriodic { generations } => {
+                write!(f, "pattern does not repeat within {} generations", generations)
+            }
+            Error::ObjectSize { population, extent } => write!(
+                f,
+                "pattern grows past {} live cells or {} rows or columns before repeating",
+                population, extent
+            ),
+            Error::InvalidRule(rule) => write!(f, "{:?} is not a B/S rulestring such as B3/S23", rule),
+            Error::InvalidRule3d(rule) => write!(f, "{:?} is not a 3D rule such as 4555 or 5766", rule),
+            Error::InvalidAntRule(rule) => write!(f, "{:?} is not an ant rule of L, R, N and U turns such as RL", rule),
+            Error::NeighborhoodSize(size) => {
+                write!(f, "a neighborhood can have at most 255 cells, not {}", size)
+            }
+            Error::WrongRule { expected, actual } => {
+                write!(f, "this needs the {} rule but the universe runs {}", expected, actual)
+            }
+            Error::IncompatibleRules { from, to } => {
+                write!(f, "{} and {} do not count the same neighbors or have different states", from, to)
+            }
+            Error::UnboundedRule(rule) => {
+                write!(f, "{} can not run unbounded, which needs a two state B/S rule without B0", rule)
+            }
+            Error::LayerRule(rule) => {
+                write!(f, "{} can not run on a layer, which needs a two state B/S rule", rule)
+            }
+            Error::Superstep { log2_steps } => {
+                write!(f, "can not step 2^{} generations without cells leaving the plane", log2_steps)
+            }
+            Error::ChunkSuperstep { log2_steps } => write!(
+                f,
+                "chunks step one generation at a time, up to 2^{} at once rather than 2^{}; HashLife steps more",
+                MAX_CHUNK_LOG2_STEPS, log2_steps
+            ),
+            Error::NoThreads => write!(
+                f,
+                "this build steps on one thread; build with the threads feature for wasm32 with atomics and bulk \
+                 memory to step on more"
+            ),
+            Error::NotIsolated => write!(
+                f,
+                "web workers can only share memory with cross-origin isolated pages; serve the page with the \
+                 headers Cross-Origin-Opener-Policy: same-origin and Cross-Origin-Embedder-Policy: require-corp"
+            ),
+            Error::Band {
+                row_start,
+                row_end,
+                height,
+            } => write!(
+                f,
+                "rows {} up to {} are not a band of the {} rows of the universe",
+                row_start, row_end, height
+            ),
+            Error::HaloLength { expected, actual } => {
+                write!(f, "expected {} words for each halo row, got {}", expected, actual)
+            }
+            Error::Unbandable(reason) => write!(f, "the universe can not tick in bands, as {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,44 @@
+//! Downloading pattern files with the JS `fetch` API.
+
+use crate::Error;
+use futures::Future;
+use js_sys::Promise;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+#[wasm_bindgen]
+extern "C" {
+    type Response;
+
+    #[wasm_bindgen(js_name = fetch)]
+    fn fetch_url(url: &str) -> Promise;
+
+    #[wasm_bindgen(method, getter)]
+    fn ok(this: &Response) -> bool;
+
+    #[wasm_bindgen(method, getter)]
+    fn status(this: &Response) -> u16;
+
+    #[wasm_bindgen(method, catch)]
+    fn text(this: &Response) -> Result<Promise, JsValue>;
+}
+
+/// Fetch `url` and resolve to its body, failing on non 2xx responses.
+pub(crate) fn text(url: &str) -> impl Future<Item = String, Error = JsValue> {
+    let url = url.to_string();
+    JsFuture::from(fetch_url(&url))
+        .and_then(move |response| {
+            let response: Response = response.unchecked_into();
+            if !response.ok() {
+                let error = Error::Fetch {
+                    url,
+                    status: response.status(),
+                };
+                return Err(JsError::from(error).into());
+            }
+            Ok(JsFuture::from(response.text()?))
+        })
+        .and_then(|body| body)
+        .map(|body| body.as_string().unwrap_or_default())
+}
@@ -0,0 +1,465 @@
+//! Bill Gosper's HashLife: the plane as a quadtree in which equal squares
+//! are stored once, every square remembering its center a power of two
+//! generations on. Patterns built of repeating parts, such as guns and
+//! breeders, share almost all of their squares over time, so that they run
+//! millions of generations ahead in a few steps.
+//!
+//! A square of side 2^k holds everything its center can become in the next
+//! 2^(k-2) generations, as nothing travels faster than a cell a generation.
+//! Its center that far on is found from the 9 overlapping half sized
+//! squares inside it, each stepped half as far, combined into 4 squares
+//! that are stepped the rest of the way.
+//!
+//! Stepping stores new squares and results all the time, most of which
+//! nothing refers to a few generations later. Once the cache outgrows its
+//! budget, it is compacted down to the squares of the current plane and the
+//! results among them.
+
+use crate::lookup::LifeTable;
+use std::collections::HashMap;
+use std::mem::size_of;
+use wasm_bindgen::prelude::*;
+
+/// Level of the 8x8 leaves, 2^3 cells a side.
+const LEAF_LEVEL: u32 = 3;
+
+/// Level of a root spanning every `i64` coordinate.
+const MAX_LEVEL: u32 = 64;
+
+/// Bytes the cache may take before it is compacted, unless set otherwise.
+pub(crate) const DEFAULT_CACHE_BUDGET: usize = 256 << 20;
+
+/**
+ * How much a HashLife universe has stored, and how well it is reused.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Squares stored, every one different from the others.
+    pub nodes: u32,
+    /// Squares whose center some generations on is stored.
+    pub results: u32,
+    /// About how many bytes the squares and results take.
+    pub bytes: u32,
+    /// Steps of a square found among the results.
+    pub hits: u64,
+    /// Steps of a square worked out anew.
+    pub misses: u64,
+    /// Times the cache was compacted.
+    pub compactions: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Node {
+    /// 8x8 cells, one byte per row with column 0 in the lowest bit.
+    Leaf(u64),
+    /// Quadrants of a node of the given level, ordered nw, ne, sw, se.
+    Inner { level: u32, children: [usize; 4] },
+}
+
+/// A plane of cells running a two state B/S rule on the Moore neighborhood
+/// without B0, centered on (0, 0).
+#[derive(Clone, Debug)]
+pub(crate) struct HashLife {
+    nodes: Vec<Node>,
+    /// The live cells under each node, saturating.
+    populations: Vec<u64>,
+    /// Every node by what it holds, so that equal squares are stored once.
+    index: HashMap<Node, usize>,
+    /// The empty node of each level from the leaves up.
+    empties: Vec<usize>,
+    /// The center of a node `2^j` generations on, by the node and `j`.
+    results: HashMap<(usize, u32), usize>,
+    table: LifeTable,
+    /// The node over rows and columns from `-2^(level - 1)` up to
+    /// `2^(level - 1)`.
+    root: usize,
+    /// Bytes the cache may take before it is compacted, if limited.
+    budget: Option<usize>,
+    hits: u64,
+    misses: u64,
+    compactions: u32,
+}
+
+impl HashLife {
+    /// An empty plane running the rule with the birth and survival bitmasks
+    /// `masks`.
+    pub fn new(masks: (u16, u16)) -> HashLife {
+        let mut tree = HashLife {
+            nodes: Vec::new(),
+            populations: Vec::new(),
+            index: HashMap::new(),
+            empties: Vec::new(),
+            results: HashMap::new(),
+            table: LifeTable::new(masks),
+            root: 0,
+            budget: Some(DEFAULT_CACHE_BUDGET),
+            hits: 0,
+            misses: 0,
+            compactions: 0,
+        };
+        let mut empty = tree.node(Node::Leaf(0));
+        tree.empties.push(empty);
+        for _ in LEAF_LEVEL..MAX_LEVEL {
+            empty = tree.join([empty; 4]);
+            tree.empties.push(empty);
+        }
+        tree.root = tree.empty(LEAF_LEVEL + 1);
+        tree
+    }
+
+    /// Switch to the rule with the birth and survival bitmasks `masks`,
+    /// forgetting what the squares became under the last one.
+    pub fn set_rule(&mut self, masks: (u16, u16)) {
+        if masks != self.table.masks() {
+            self.table = LifeTable::new(masks);
+            self.results.clear();
+        }
+    }
+
+    fn level(&self, node: usize) -> u32 {
+        match self.nodes[node] {
+            Node::Leaf(_) => LEAF_LEVEL,
+            Node::Inner { level, .. } => level,
+        }
+    }
+
+    fn empty(&self, level: u32) -> usize {
+        self.empties[(level - LEAF_LEVEL) as usize]
+    }
+
+    fn children(&self, node: usize) -> [usize; 4] {
+        match self.nodes[node] {
+            Node::Inner { children, .. } => children,
+            Node::Leaf(_) => unreachable!("leaves have no quadrants"),
+        }
+    }
+
+    fn leaf(&self, node: usize) -> u64 {
+        match self.nodes[node] {
+            Node::Leaf(cells) => cells,
+            Node::Inner { .. } => unreachable!("inner nodes have no cells of their own"),
+        }
+    }
+
+    /// The stored node equal to `node`, storing it if there is none.
+    fn node(&mut self, node: Node) -> usize {
+        if let Some(&id) = self.index.get(&node) {
+            return id;
+        }
+        let population = match node {
+            Node::Leaf(cells) => u64::from(cells.count_ones()),
+            Node::Inner { children, .. } => children
+                .iter()
+                .fold(0u64, |population, &child| population.saturating_add(self.populations[child])),
+        };
+        self.nodes.push(node);
+        self.populations.push(population);
+        self.index.insert(node, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    /// The node with the quadrants `children`, ordered nw, ne, sw, se.
+    fn join(&mut self, children: [usize; 4]) -> usize {
+        let level = self.level(children[0]) + 1;
+        self.node(Node::Inner { level, children })
+    }
+
+    /// Position of absolute `row` and `col` from the root's top left, if it
+    /// lies under the root.
+    fn position(&self, row: i64, col: i64) -> Option<(u64, u64)> {
+        let half = 1i128 << (self.level(self.root) - 1);
+        let (top, left) = (i128::from(row) + half, i128::from(col) + half);
+        if top < 0 || left < 0 || top >= 2 * half || left >= 2 * half {
+            return None;
+        }
+        Some((top as u64, left as u64))
+    }
+
+    /// The 8 cells of the leaf row holding cell (row, col), column 0 in the
+    /// lowest bit, dead outside of the root.
+    fn leaf_row(&self, row: i64, col: i64) -> u8 {
+        let (top, left) = match self.position(row, col) {
+            Some(position) => position,
+            None => return 0,
+        };
+        let mut node = self.root;
+        for level in (LEAF_LEVEL..self.level(self.root)).rev() {
+            if self.populations[node] == 0 {
+                return 0;
+            }
+            let quadrant = (top >> level & 1) * 2 + (left >> level & 1);
+            node = self.children(node)[quadrant as usize];
+        }
+        (self.leaf(node) >> (top % 8 * 8)) as u8
+    }
+
+    pub fn cell(&self, row: i64, col: i64) -> bool {
+        self.leaf_row(row, col) >> col.rem_euclid(8) & 1 == 1
+    }
+
+    /// Up to 32 cells of `row` from `col` on, the first in the lowest bit.
+    pub fn bits(&self, row: i64, col: i64) -> u32 {
+        let start = col.div_euclid(8) * 8;
+        let cells = (0..5).fold(0u64, |cells, byte| {
+            cells | u64::from(self.leaf_row(row, start.wrapping_add(8 * byte))) << (8 * byte)
+        });
+        (cells >> (col - start)) as u32
+    }
+
+    pub fn set_cell(&mut self, row: i64, col: i64, alive: bool) {
+        while self.position(row, col).is_none() {
+            self.expand();
+        }
+        let (top, left) = self.position(row, col).unwrap_or_default();
+        self.root = self.put(self.root, top, left, alive);
+    }
+
+    /// `node` with the cell `top` rows and `left` columns from its top left
+    /// set to `alive`.
+    fn put(&mut self, node: usize, top: u64, left: u64, alive: bool) -> usize {
+        let level = self.level(node);
+        if level == LEAF_LEVEL {
+            let bit = 1 << (top * 8 + left);
+            let cells = self.leaf(node);
+            return self.node(Node::Leaf(if alive { cells | bit } else { cells & !bit }));
+        }
+        let half = level - 1;
+        let quadrant = ((top >> half & 1) * 2 + (left >> half & 1)) as usize;
+        let mask = (1 << half) - 1;
+        let mut children = self.children(node);
+        children[quadrant] = self.put(children[quadrant], top & mask, left & mask, alive);
+        self.join(children)
+    }
+
+    pub fn population(&self) -> u64 {
+        self.populations[self.root]
+    }
+
+    /// Double the root's side, keeping it centered on (0, 0).
+    fn expand(&mut self) {
+        let level = self.level(self.root);
+        let empty = self.empty(level - 1);
+        let [nw, ne, sw, se] = self.children(self.root);
+        let children = [
+            self.join([empty, empty, empty, nw]),
+            self.join([empty, empty, ne, empty]),
+            self.join([empty, sw, empty, empty]),
+            self.join([se, empty, empty, empty]),
+        ];
+        self.root = self.join(children);
+    }
+
+    /// Whether every live cell under the root lies in its middle quarter,
+    /// the 4 squares of an eighth of its side around the center.
+    fn padded(&self) -> bool {
+        let level = self.level(self.root);
+        self.children(self.root).iter().enumerate().all(|(quadrant, &child)| {
+            let inner = 3 - quadrant;
+            let grandchildren = self.children(child);
+            let outer_empty = |node: usize, level: u32, inner: usize| {
+                (0..4).all(|idx| idx == inner || self.children(node)[idx] == self.empty(level))
+            };
+            outer_empty(child, level - 2, inner) && outer_empty(grandchildren[inner], level - 3, inner)
+        })
+    }
+
+    /// Step the plane `2^log2_steps` generations forward, unless a live cell
+    /// is too close to the edges of the `i64` plane to be sure of what
+    /// becomes of it.
+    pub fn advance(&mut self, log2_steps: u32) -> bool {
+        while self.level(self.root) < (log2_steps + 3).max(6) || !self.padded() {
+            if self.level(self.root) == MAX_LEVEL {
+                return false;
+            }
+            self.expand();
+        }
+        self.root = self.step(self.root, log2_steps);
+        self.keep_to_budget();
+        true
+    }
+
+    /// About how many bytes the squares and results take, counting what
+    /// their storage has room for.
+    fn bytes(&self) -> usize {
+        self.nodes.capacity() * size_of::<Node>()
+            + self.populations.capacity() * size_of::<u64>()
+            + self.index.capacity() * (size_of::<(Node, usize)>() + 1)
+            + self.results.capacity() * (size_of::<((usize, u32), usize)>() + 1)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            nodes: self.nodes.len() as u32,
+            results: self.results.len() as u32,
+            bytes: self.bytes().min(u32::MAX as usize) as u32,
+            hits: self.hits,
+            misses: self.misses,
+            compactions: self.compactions,
+        }
+    }
+
+    pub fn budget(&self) -> Option<usize> {
+        self.budget
+    }
+
+    /// Limit the cache to `budget` bytes, compacting it at once if it takes
+    /// more.
+    pub fn set_budget(&mut self, budget: Option<usize>) {
+        self.budget = budget;
+        self.keep_to_budget();
+    }
+
+    fn keep_to_budget(&mut self) {
+        if self.budget.is_some_and(|budget| self.bytes() > budget) {
+            self.compact();
+        }
+    }
+
+    /// Drop every square the plane no longer holds, and every result of or
+    /// to one, storing the rest anew.
+    pub fn compact(&mut self) {
+        let mut live = vec![false; self.nodes.len()];
+        let mut unvisited: Vec<usize> = self.empties.iter().cloned().chain([self.root]).collect();
+        while let Some(node) = unvisited.pop() {
+            if !live[node] {
+                live[node] = true;
+                if let Node::Inner { children, .. } = self.nodes[node] {
+                    unvisited.extend_from_slice(&children);
+                }
+            }
+        }
+        // Squares are stored after their quadrants, so that those are always
+        // renumbered first.
+        let mut ids = vec![usize::MAX; self.nodes.len()];
+        let mut nodes = Vec::with_capacity(live.iter().filter(|&&live| live).count());
+        let mut populations = Vec::with_capacity(nodes.capacity());
+        for (old, node) in self.nodes.iter().enumerate().filter(|&(old, _)| live[old]) {
+            ids[old] = nodes.len();
+            nodes.push(match *node {
+                Node::Inner { level, children } => Node::Inner {
+                    level,
+                    children: children.map(|child| ids[child]),
+                },
+                leaf => leaf,
+            });
+            populations.push(self.populations[old]);
+        }
+        self.index = nodes.iter().enumerate().map(|(id, &node)| (node, id)).collect();
+        self.results = self
+            .results
+            .iter()
+            .filter(|&(&(node, _), &result)| live[node] && live[result])
+            .map(|(&(node, log2_steps), &result)| ((ids[node], log2_steps), ids[result]))
+            .collect();
+        self.nodes = nodes;
+        self.populations = populations;
+        self.empties = self.empties.iter().map(|&empty| ids[empty]).collect();
+        self.root = ids[self.root];
+        self.compactions += 1;
+    }
+
+    /// The 16 rows of a level 4 node, column 0 in the lowest bit.
+    fn rows(&self, node: usize) -> [u16; 16] {
+        let [nw, ne, sw, se] = self.children(node).map(|leaf| self.leaf(leaf));
+        let mut rows = [0; 16];
+        for row in 0..8 {
+            let byte = |cells: u64| u16::from((cells >> (row * 8)) as u8);
+            rows[row] = byte(nw) | byte(ne) << 8;
+            rows[row + 8] = byte(sw) | byte(se) << 8;
+        }
+        rows
+    }
+
+    /// The 8x8 leaf from `offset` rows and columns into `rows`.
+    fn leaf_at(&mut self, rows: &[u16], offset: usize) -> usize {
+        let cells = (0..8).fold(0, |cells, row| cells | u64::from((rows[offset + row] >> offset) as u8) << (row * 8));
+        self.node(Node::Leaf(cells))
+    }
+
+    /// The square of half the side in the middle of `node`.
+    fn center(&mut self, node: usize) -> usize {
+        if self.level(node) == LEAF_LEVEL + 1 {
+            let rows = self.rows(node);
+            return self.leaf_at(&rows, 4);
+        }
+        let [nw, ne, sw, se] = self.children(node);
+        let children = [self.children(nw)[3], self.children(ne)[2], self.children(sw)[1], self.children(se)[0]];
+        self.join(children)
+    }
+
+    /// The center of a level 4 node `2^log2_steps`, up to 4, generations on,
+    /// stepping its cells by the lookup table 2x2 blocks at a time.
+    fn step_leaves(&mut self, node: usize, log2_steps: u32) -> usize {
+        let mut rows = self.rows(node).to_vec();
+        for _ in 0..1 << log2_steps {
+            // Each generation loses the cells along the edges, whose
+            // neighbors lie outside of the square.
+            let size = rows.len() - 2;
+            let mut next = vec![0u16; size];
+            for row in (0..size).step_by(2) {
+                for col in (0..size).step_by(2) {
+                    let window = (0..4).fold(0, |window, window_row| {
+                        window | (rows[row + window_row] >> col & 0b1111) << (window_row * 4)
+                    });
+                    let center = u16::from(self.table.center(window));
+                    next[row] |= (center & 0b11) << col;
+                    next[row + 1] |= (center >> 2 & 0b11) << col;
+                }
+            }
+            rows = next;
+        }
+        let offset = (rows.len() - 8) / 2;
+        self.leaf_at(&rows, offset)
+    }
+
+    /// The center of `node`, of level `k`, `2^log2_steps` generations on,
+    /// for `log2_steps` at most `k - 2`.
+    fn step(&mut self, node: usize, log2_steps: u32) -> usize {
+        let level = self.level(node);
+        if self.populations[node] == 0 {
+            return self.empty(level - 1);
+        }
+        if let Some(&result) = self.results.get(&(node, log2_steps)) {
+            self.hits += 1;
+            return result;
+        }
+        self.misses += 1;
+        let result = if level == LEAF_LEVEL + 1 {
+            self.step_leaves(node, log2_steps)
+        } else {
+            let [nw, ne, sw, se] = self.children(node);
+            let [_, nw_ne, nw_sw, nw_se] = self.children(nw);
+            let [ne_nw, _, ne_sw, ne_se] = self.children(ne);
+            let [sw_nw, sw_ne, _, sw_se] = self.children(sw);
+            let [se_nw, se_ne, se_sw, _] = self.children(se);
+            // The 9 overlapping squares of half the side, row by row.
+            let squares = [
+                nw,
+                self.join([nw_ne, ne_nw, nw_se, ne_sw]),
+                ne,
+                self.join([nw_sw, nw_se, sw_nw, sw_ne]),
+                self.join([nw_se, ne_sw, sw_ne, se_nw]),
+                self.join([ne_sw, ne_se, se_nw, se_ne]),
+                sw,
+                self.join([sw_ne, se_nw, sw_se, se_sw]),
+                se,
+            ];
+            // A full step takes both halves of the time, a shorter one
+            // only the second.
+            let (first, second) = if log2_steps == level - 2 { (true, level - 3) } else { (false, log2_steps) };
+            let mut parts = [0; 9];
+            for (part, &square) in parts.iter_mut().zip(&squares) {
+                *part = if first { self.step(square, level - 3) } else { self.center(square) };
+            }
+            let mut quadrants = [0; 4];
+            for (quadrant, corner) in quadrants.iter_mut().zip([0, 1, 3, 4]) {
+                let square = self.join([parts[corner], parts[corner + 1], parts[corner + 3], parts[corner + 4]]);
+                *quadrant = self.step(square, second);
+            }
+            self.join(quadrants)
+        };
+        self.results.insert((node, log2_steps), result);
+        result
+    }
+}
@@ -0,0 +1,437 @@
+//! An unbounded universe for Life-like rules, holding its cells in 64x64
+//! chunks that are allocated as patterns grow into them and dropped once
+//! they empty, so that guns and puffers run on without ever meeting an edge.
+//! Universes created for HashLife hold them in its quadtree instead.
+
+use crate::hashlife::HashLife;
+use crate::{CacheStats, Error, Rule};
+use std::collections::{HashMap, HashSet};
+use wasm_bindgen::prelude::*;
+
+/// Cells along each side of a chunk.
+const CHUNK_SIZE: i64 = 64;
+
+/// A chunk of cells, one word per row with column 0 in the lowest bit.
+type Chunk = [u64; CHUNK_SIZE as usize];
+
+/// The most generations, as a power of two, a universe steps at once.
+const MAX_LOG2_STEPS: u32 = 61;
+
+/// The most generations, as a power of two, chunks step at once, ticking
+/// them one by one.
+pub(crate) const MAX_CHUNK_LOG2_STEPS: u32 = 16;
+
+/**
+ * How an unbounded universe holds and steps its cells.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Engine {
+    /// 64x64 chunks around the live cells, stepped a generation at a time.
+    Chunks,
+    /// A HashLife quadtree, which steps patterns of repeating parts such as
+    /// guns and breeders many generations at once through
+    /// `tick_superstep`, but steps chaotic ones slower than chunks.
+    HashLife,
+}
+
+/**
+ * An unbounded grid of cells running a two state B/S rule such as Conway's
+ * Life. Rows and columns go any distance in both directions from (0, 0).
+ *
+ * Cells are addressed by absolute coordinates, which do not move. A view
+ * onto the plane pans by moving its origin, the absolute cell shown at its
+ * top left, and addresses cells relative to it.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct InfiniteUniverse {
+    /// Every chunk holding live cells by its row and column of chunks.
+    chunks: HashMap<(i64, i64), Chunk>,
+    rule: Rule,
+    generation: u64,
+    /// Absolute row and column of the view's top left cell.
+    origin: (i64, i64),
+    /// The cells under `Engine::HashLife`, leaving `chunks` empty.
+    hashlife: Option<HashLife>,
+}
+
+impl InfiniteUniverse {
+    /// The chunk holding a cell, and the cell's row and column within it.
+    fn locate(row: i64, col: i64) -> ((i64, i64), usize, usize) {
+        let key = (row.div_euclid(CHUNK_SIZE), col.div_euclid(CHUNK_SIZE));
+        (key, row.rem_euclid(CHUNK_SIZE) as usize, col.rem_euclid(CHUNK_SIZE) as usize)
+    }
+
+    fn cell(&self, row: i64, col: i64) -> bool {
+        if let Some(tree) = &self.hashlife {
+            return tree.cell(row, col);
+        }
+        let (key, row, col) = InfiniteUniverse::locate(row, col);
+        self.chunks.get(&key).is_some_and(|chunk| chunk[row] >> col & 1 == 1)
+    }
+
+    /// Set a cell alive or dead, allocating its chunk for a live cell and
+    /// dropping it once its last cell dies.
+    fn put_cell(&mut self, row: i64, col: i64, alive: bool) {
+        if let Some(tree) = &mut self.hashlife {
+            return tree.set_cell(row, col, alive);
+        }
+        let (key, row, col) = InfiniteUniverse::locate(row, col);
+        if alive {
+            self.chunks.entry(key).or_insert([0; CHUNK_SIZE as usize])[row] |= 1 << col;
+        } else if let Some(chunk) = self.chunks.get_mut(&key) {
+            chunk[row] &= !(1 << col);
+            if chunk.iter().all(|&word| word == 0) {
+                self.chunks.remove(&key);
+            }
+        }
+    }
+
+    /// Up to 32 cells of `row` from `col` on, the first in the lowest bit.
+    fn bits(&self, row: i64, col: i64) -> u32 {
+        if let Some(tree) = &self.hashlife {
+            return tree.bits(row, col);
+        }
+        let ((chunk_row, chunk_col), row, offset) = InfiniteUniverse::locate(row, col);
+        let mut bits = self.word((chunk_row, chunk_col), row as i64) >> offset;
+        if offset > 32 {
+            bits |= self.word((chunk_row, chunk_col + 1), row as i64) << (64 - offset);
+        }
+        bits as u32
+    }
+
+    /// Row `row` of the chunk at `key`, which may run one past either end
+    /// into the chunks above and below, 0 where there is none.
+    fn word(&self, key: (i64, i64), row: i64) -> u64 {
+        let (chunk_row, row) = (key.0 + row.div_euclid(CHUNK_SIZE), row.rem_euclid(CHUNK_SIZE));
+        self.chunks.get(&(chunk_row, key.1)).map_or(0, |chunk| chunk[row as usize])
+    }
+
+    /// The chunk at `key` after one generation.
+    fn next_chunk(&self, key: (i64, i64)) -> Chunk {
+        // Rows -1 to 64 of the chunk, each with the last column of the chunk
+        // to the left in bit 0 and the first column of the one to the right
+        // in bit 65.
+        let mut rows = [0u128; CHUNK_SIZE as usize + 2];
+        for (padded_row, bits) in rows.iter_mut().enumerate() {
+            let row = padded_row as i64 - 1;
+            let left = self.word((key.0, key.1 - 1), row) >> 63;
+            let right = self.word((key.0, key.1 + 1), row) & 1;
+            *bits = u128::from(left) | u128::from(self.word(key, row)) << 1 | u128::from(right) << 65;
+        }
+        let mut next = [0; CHUNK_SIZE as usize];
+        for (row, word) in next.iter_mut().enumerate() {
+            for col in 0..CHUNK_SIZE as usize {
+                let window = |bits: u128| (bits >> col & 0b111).count_ones();
+                let alive = (rows[row + 1] >> (col + 1) & 1) as u8;
+                let neighbors = window(rows[row]) + window(rows[row + 1]) + window(rows[row + 2]) - u32::from(alive);
+                *word |= u64::from(self.rule.next(alive, neighbors)) << col;
+            }
+        }
+        next
+    }
+}
+
+#[wasm_bindgen]
+impl InfiniteUniverse {
+    /**
+     * Create an empty unbounded universe running Conway's Life.
+     */
+    pub fn new() -> InfiniteUniverse {
+        InfiniteUniverse::default()
+    }
+
+    /**
+     * Create an empty unbounded universe running Conway's Life on `engine`.
+     */
+    pub fn with_engine(engine: Engine) -> InfiniteUniverse {
+        let mut universe = InfiniteUniverse::default();
+        if engine == Engine::HashLife {
+            universe.hashlife = universe.rule.life_masks().map(HashLife::new);
+        }
+        universe
+    }
+
+    pub fn engine(&self) -> Engine {
+        match self.hashlife {
+            Some(_) => Engine::HashLife,
+            None => Engine::Chunks,
+        }
+    }
+
+    /**
+     * Switch to another rule, which must have two states, count the 8 Moore
+     * neighbors and leave cells without live neighbors dead: B0 would fill
+     * the whole plane at once.
+     */
+    pub fn set_rule(&mut self, rulestring: &str) -> Result<(), JsError> {
+        let rule: Rule = rulestring.parse()?;
+        if !rule.is_unbounded() {
+            return Err(Error::UnboundedRule(rule.to_string()).into());
+        }
+        if let (Some(tree), Some(masks)) = (&mut self.hashlife, rule.life_masks()) {
+            tree.set_rule(masks);
+        }
+        self.rule = rule;
+        Ok(())
+    }
+
+    pub fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    pub fn get_cell(&self, row: i64, col: i64) -> bool {
+        self.cell(row, col)
+    }
+
+    pub fn set_cell(&mut self, row: i64, col: i64, alive: bool) {
+        self.put_cell(row, col, alive);
+    }
+
+    /**
+     * Set every cell in `coords`, given as flattened `row, col` pairs, to
+     * `alive`.
+     */
+    pub fn set_cells(&mut self, coords: &[i64], alive: bool) {
+        for pair in coords.chunks_exact(2) {
+            self.put_cell(pair[0], pair[1], alive);
+        }
+    }
+
+    /**
+     * Returns the cells of the `width` by `height` window from (row, col)
+     * packed into 32 bit words, every row of the window starting a new word
+     * with its first column in the lowest bit.
+     */
+    pub fn cells_in_rect(&self, row: i64, col: i64, width: u32, height: u32) -> Vec<u32> {
+        let mut cells = Vec::with_capacity(width.div_ceil(32) as usize * height as usize);
+        for window_row in 0..i64::from(height) {
+            for word_col in (0..i64::from(width)).step_by(32) {
+                let mut bits = self.bits(row + window_row, col + word_col);
+                let remaining = i64::from(width) - word_col;
+                if remaining < 32 {
+                    bits &= (1 << remaining) - 1;
+                }
+                cells.push(bits);
+            }
+        }
+        cells
+    }
+
+    /**
+     * Returns the live cells of the `width` by `height` window from
+     * (row, col) as flattened `row, col` pairs, row by row.
+     */
+    pub fn live_cells_in_rect(&self, row: i64, col: i64, width: u32, height: u32) -> Vec<i64> {
+        let words = width.div_ceil(32) as usize;
+        let mut coords = Vec::new();
+        for (index, &word) in self.cells_in_rect(row, col, width, height).iter().enumerate() {
+            let (window_row, word_col) = (index / words, index % words * 32);
+            let mut bits = word;
+            while bits != 0 {
+                let offset = word_col + bits.trailing_zeros() as usize;
+                coords.extend_from_slice(&[row + window_row as i64, col + offset as i64]);
+                bits &= bits - 1;
+            }
+        }
+        coords
+    }
+
+    /**
+     * Move the view so that its top left cell is the one at absolute
+     * (row, col).
+     */
+    pub fn set_origin(&mut self, row: i64, col: i64) {
+        self.origin = (row, col);
+    }
+
+    pub fn origin_row(&self) -> i64 {
+        self.origin.0
+    }
+
+    pub fn origin_col(&self) -> i64 {
+        self.origin.1
+    }
+
+    /**
+     * Move the view `delta_row` rows down and `delta_col` columns right.
+     */
+    pub fn pan(&mut self, delta_row: i64, delta_col: i64) {
+        self.origin = (self.origin.0 + delta_row, self.origin.1 + delta_col);
+    }
+
+    /**
+     * Returns the absolute row of row `view_row` of the view.
+     */
+    pub fn absolute_row(&self, view_row: i64) -> i64 {
+        self.origin.0 + view_row
+    }
+
+    pub fn absolute_col(&self, view_col: i64) -> i64 {
+        self.origin.1 + view_col
+    }
+
+    /**
+     * Returns the row of the view absolute row `row` is shown on, negative
+     * above the view.
+     */
+    pub fn view_row(&self, row: i64) -> i64 {
+        row - self.origin.0
+    }
+
+    pub fn view_col(&self, col: i64) -> i64 {
+        col - self.origin.1
+    }
+
+    /**
+     * Returns the cells of a `width` by `height` view packed as by
+     * `cells_in_rect`.
+     */
+    pub fn view_cells(&self, width: u32, height: u32) -> Vec<u32> {
+        self.cells_in_rect(self.origin.0, self.origin.1, width, height)
+    }
+
+    /**
+     * Returns the live cells of a `width` by `height` view as flattened
+     * `row, col` pairs in view coordinates, row by row.
+     */
+    pub fn live_view_cells(&self, width: u32, height: u32) -> Vec<u32> {
+        let (row, col) = self.origin;
+        let coords = self.live_cells_in_rect(row, col, width, height);
+        coords
+            .chunks_exact(2)
+            .flat_map(|pair| [(pair[0] - row) as u32, (pair[1] - col) as u32])
+            .collect()
+    }
+
+    /**
+     * Step every chunk holding live cells, and every chunk next to one of
+     * them that live cells reach the edge of, one generation forward. Under
+     * HashLife, a pattern reaching more than 2^61 cells from (0, 0) stays as
+     * it is, as `tick_superstep` would report.
+     */
+    pub fn tick(&mut self) {
+        if let Some(tree) = &mut self.hashlife {
+            if tree.advance(0) {
+                self.generation += 1;
+            }
+            return;
+        }
+        let mut keys = HashSet::new();
+        for (&(chunk_row, chunk_col), chunk) in &self.chunks {
+            let top = chunk[0] != 0;
+            let bottom = chunk[CHUNK_SIZE as usize - 1] != 0;
+            let left = chunk.iter().any(|&word| word & 1 != 0);
+            let right = chunk.iter().any(|&word| word >> 63 != 0);
+            for delta_row in -1..=1 {
+                for delta_col in -1..=1 {
+                    let reaches_row = match delta_row {
+                        -1 => top,
+                        1 => bottom,
+                        _ => true,
+                    };
+                    let reaches_col = match delta_col {
+                        -1 => left,
+                        1 => right,
+                        _ => true,
+                    };
+                    if reaches_row && reaches_col {
+                        keys.insert((chunk_row + delta_row, chunk_col + delta_col));
+                    }
+                }
+            }
+        }
+        self.chunks = keys
+            .into_iter()
+            .map(|key| (key, self.next_chunk(key)))
+            .filter(|(_, chunk)| chunk.iter().any(|&word| word != 0))
+            .collect();
+        self.generation += 1;
+    }
+
+    /**
+     * Step `2^log2_steps` generations forward at once, up to 2^61. HashLife
+     * takes about as long for a gun's millionth generation as for its
+     * thousandth; chunks are ticked one generation at a time, up to 2^16.
+     * Fails, leaving the cells as they were, when live cells could leave
+     * the plane, which under HashLife needs them within 2^61 cells of
+     * (0, 0), or when chunks would take too long.
+     */
+    pub fn tick_superstep(&mut self, log2_steps: u32) -> Result<(), JsError> {
+        if log2_steps > MAX_LOG2_STEPS {
+            return Err(Error::Superstep { log2_steps }.into());
+        }
+        match &mut self.hashlife {
+            Some(tree) => {
+                if !tree.advance(log2_steps) {
+                    return Err(Error::Superstep { log2_steps }.into());
+                }
+                self.generation += 1 << log2_steps;
+            }
+            None if log2_steps > MAX_CHUNK_LOG2_STEPS => return Err(Error::ChunkSuperstep { log2_steps }.into()),
+            None => (0..1u64 << log2_steps).for_each(|_| self.tick()),
+        }
+        Ok(())
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /**
+     * Returns the number of live cells.
+     */
+    pub fn population(&self) -> u32 {
+        if let Some(tree) = &self.hashlife {
+            return tree.population().min(u64::from(u32::MAX)) as u32;
+        }
+        self.chunks.values().flatten().map(|word| word.count_ones()).sum()
+    }
+
+    /**
+     * Returns what the HashLife cache holds, or nothing on chunks.
+     */
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.hashlife.as_ref().map(HashLife::stats)
+    }
+
+    /**
+     * Compact the HashLife cache whenever it grows past `bytes` after a
+     * step, 256 MiB unless set otherwise, or never for `None`. Compacting
+     * keeps the squares of the current cells, so that a plane needing more
+     * than the budget compacts after every step. Chunks keep no cache.
+     */
+    pub fn set_cache_budget(&mut self, bytes: Option<u32>) {
+        if let Some(tree) = &mut self.hashlife {
+            tree.set_budget(bytes.map(|bytes| bytes as usize));
+        }
+    }
+
+    pub fn cache_budget(&self) -> Option<u32> {
+        self.hashlife
+            .as_ref()
+            .and_then(HashLife::budget)
+            .map(|bytes| bytes.min(u32::MAX as usize) as u32)
+    }
+
+    /**
+     * Drop everything the HashLife cache holds but the squares of the
+     * current cells and what is known of them, as when it outgrows its
+     * budget. Stepping works out again what later generations need of the
+     * rest.
+     */
+    pub fn compact_cache(&mut self) {
+        if let Some(tree) = &mut self.hashlife {
+            tree.compact();
+        }
+    }
+
+    /**
+     * Returns the number of 64x64 chunks allocated, all of which hold live
+     * cells, and 0 under HashLife.
+     */
+    pub fn chunk_count(&self) -> u32 {
+        self.chunks.len() as u32
+    }
+}
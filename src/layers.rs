@@ -0,0 +1,246 @@
+//! Universes of several 2D layers stacked over each other, each running its
+//! own Life-like rule on neighbor counts that can take in the cells of other
+//! layers too: coupled cellular automata, where one layer can feed or hold
+//! back another.
+
+use crate::life3d::sum_threes;
+use crate::{Error, Rule};
+use fixedbitset::FixedBitSet;
+use wasm_bindgen::prelude::*;
+
+/// One layer's cells and the rule they run.
+#[derive(Clone, Debug)]
+struct Layer {
+    cells: FixedBitSet,
+    rule: Rule,
+}
+
+/// How much the cells of layer `source` count toward the neighbors of the
+/// cells of layer `target`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Coupling {
+    target: usize,
+    source: usize,
+    weight: i32,
+}
+
+/**
+ * A stack of `width` by `height` layers, wrapping around at the edges, each
+ * running a two state B/S rule. Without couplings every layer runs on its
+ * own; `set_coupling` lets a layer count the cells of another as well.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct LayeredUniverse {
+    width: u32,
+    height: u32,
+    layers: Vec<Layer>,
+    couplings: Vec<Coupling>,
+    generation: u64,
+}
+
+impl LayeredUniverse {
+    fn index(&self, row: u32, col: u32) -> usize {
+        (row * self.width + col) as usize
+    }
+
+    fn checked_layer(&self, layer: u32) -> Result<usize, Error> {
+        if layer as usize >= self.layers.len() {
+            return Err(Error::LayerOutOfBounds {
+                layer,
+                depth: self.layers.len() as u32,
+            });
+        }
+        Ok(layer as usize)
+    }
+
+    fn checked_index(&self, layer: u32, row: u32, col: u32) -> Result<(usize, usize), Error> {
+        let layer = self.checked_layer(layer)?;
+        if row >= self.height || col >= self.width {
+            return Err(Error::OutOfBounds {
+                row,
+                col,
+                width: self.width,
+                height: self.height,
+            });
+        }
+        Ok((layer, self.index(row, col)))
+    }
+
+    fn parse_rule(rulestring: &str) -> Result<Rule, Error> {
+        let rule: Rule = rulestring.parse()?;
+        if !rule.is_life_like() {
+            return Err(Error::LayerRule(rule.to_string()));
+        }
+        Ok(rule)
+    }
+
+    /// The live cells among the 9 at and around every cell of a layer.
+    fn squares(&self, layer: &Layer) -> Vec<u8> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let alive: Vec<u8> = (0..layer.cells.len()).map(|idx| u8::from(layer.cells[idx])).collect();
+        sum_threes(&sum_threes(&alive, 1, width), width, height)
+    }
+}
+
+#[wasm_bindgen]
+impl LayeredUniverse {
+    /**
+     * Create a universe with a single empty layer running Conway's Life.
+     */
+    pub fn new(width: u32, height: u32) -> LayeredUniverse {
+        LayeredUniverse {
+            width,
+            height,
+            layers: vec![Layer {
+                cells: FixedBitSet::with_capacity((width * height) as usize),
+                rule: Rule::default(),
+            }],
+            couplings: Vec::new(),
+            generation: 0,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /**
+     * Put an empty layer running `rulestring` on top of the stack, returning
+     * its index. Layers need two state B/S rules counting the 8 Moore
+     * neighbors.
+     */
+    pub fn add_layer(&mut self, rulestring: &str) -> Result<u32, JsError> {
+        let rule = LayeredUniverse::parse_rule(rulestring)?;
+        self.layers.push(Layer {
+            cells: FixedBitSet::with_capacity((self.width * self.height) as usize),
+            rule,
+        });
+        Ok(self.layers.len() as u32 - 1)
+    }
+
+    pub fn layer_count(&self) -> u32 {
+        self.layers.len() as u32
+    }
+
+    pub fn set_layer_rule(&mut self, layer: u32, rulestring: &str) -> Result<(), JsError> {
+        let layer = self.checked_layer(layer)?;
+        self.layers[layer].rule = LayeredUniverse::parse_rule(rulestring)?;
+        Ok(())
+    }
+
+    pub fn layer_rule(&self, layer: u32) -> Result<String, JsError> {
+        Ok(self.layers[self.checked_layer(layer)?].rule.to_string())
+    }
+
+    /**
+     * Count the live cells among the 9 at and around each cell's place in
+     * layer `source`, `weight` times over, toward the neighbors of the cell
+     * in layer `target`. A negative weight holds the target back where the
+     * source is alive, and a weight of 0 removes the coupling. Counts below
+     * zero count as zero.
+     */
+    pub fn set_coupling(&mut self, target: u32, source: u32, weight: i32) -> Result<(), JsError> {
+        let (target, source) = (self.checked_layer(target)?, self.checked_layer(source)?);
+        self.couplings.retain(|coupling| (coupling.target, coupling.source) != (target, source));
+        if weight != 0 {
+            self.couplings.push(Coupling { target, source, weight });
+        }
+        Ok(())
+    }
+
+    /**
+     * Returns how much layer `source` counts toward the neighbors of layer
+     * `target`, 0 if it does not.
+     */
+    pub fn coupling(&self, target: u32, source: u32) -> i32 {
+        self.couplings
+            .iter()
+            .find(|coupling| (coupling.target, coupling.source) == (target as usize, source as usize))
+            .map_or(0, |coupling| coupling.weight)
+    }
+
+    pub fn get_cell(&self, layer: u32, row: u32, col: u32) -> Result<bool, JsError> {
+        let (layer, idx) = self.checked_index(layer, row, col)?;
+        Ok(self.layers[layer].cells[idx])
+    }
+
+    pub fn set_cell(&mut self, layer: u32, row: u32, col: u32, alive: bool) -> Result<(), JsError> {
+        let (layer, idx) = self.checked_index(layer, row, col)?;
+        self.layers[layer].cells.set(idx, alive);
+        Ok(())
+    }
+
+    /**
+     * Set every cell of `layer` in `coords`, given as flattened `row, col`
+     * pairs, to `alive`.
+     */
+    pub fn set_cells(&mut self, layer: u32, coords: &[u32], alive: bool) -> Result<(), JsError> {
+        for pair in coords.chunks_exact(2) {
+            let (layer, idx) = self.checked_index(layer, pair[0], pair[1])?;
+            self.layers[layer].cells.set(idx, alive);
+        }
+        Ok(())
+    }
+
+    /**
+     * Returns the cells of one layer packed into 32 bit words, every row
+     * starting a new word with its first column in the lowest bit.
+     */
+    pub fn layer_cells(&self, layer: u32) -> Result<Vec<u32>, JsError> {
+        let cells = &self.layers[self.checked_layer(layer)?].cells;
+        let mut words = Vec::with_capacity(self.width.div_ceil(32) as usize * self.height as usize);
+        for row in 0..self.height {
+            for word_col in (0..self.width).step_by(32) {
+                let start = self.index(row, word_col);
+                let bits = (0..32.min(self.width - word_col) as usize)
+                    .filter(|&offset| cells[start + offset])
+                    .fold(0, |bits, offset| bits | 1 << offset);
+                words.push(bits);
+            }
+        }
+        Ok(words)
+    }
+
+    /**
+     * Returns the number of live cells in one layer.
+     */
+    pub fn population(&self, layer: u32) -> Result<u32, JsError> {
+        Ok(self.layers[self.checked_layer(layer)?].cells.count_ones(..) as u32)
+    }
+
+    /**
+     * Step every layer one generation forward at once, each by its own live
+     * neighbors and those its couplings bring in from the layers as they
+     * were.
+     */
+    pub fn tick(&mut self) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        let squares: Vec<Vec<u8>> = self.layers.iter().map(|layer| self.squares(layer)).collect();
+        for (target, layer) in self.layers.iter_mut().enumerate() {
+            let mut counts: Vec<i32> = (0..layer.cells.len())
+                .map(|idx| i32::from(squares[target][idx]) - i32::from(layer.cells[idx]))
+                .collect();
+            for coupling in self.couplings.iter().filter(|coupling| coupling.target == target) {
+                for (count, &square) in counts.iter_mut().zip(&squares[coupling.source]) {
+                    *count = count.saturating_add(coupling.weight.saturating_mul(i32::from(square)));
+                }
+            }
+            for (idx, count) in counts.into_iter().enumerate() {
+                let alive = layer.rule.next(u8::from(layer.cells[idx]), count.max(0) as u32) == 1;
+                layer.cells.set(idx, alive);
+            }
+        }
+        self.generation += 1;
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
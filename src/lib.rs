@@ -1,7 +1,64 @@
+mod ant;
+mod apgcode;
+mod band;
+mod bitwise;
+mod builder;
+mod continuous;
+mod counts;
+mod cube;
+mod error;
+mod fetch;
+mod hashlife;
+mod infinite;
+mod layers;
+mod lif;
+mod life3d;
+mod lookup;
+mod macrocell;
+mod pattern;
+mod patterns;
+mod plaintext;
+mod render;
+mod rle;
+mod rng;
+mod row;
+mod rule;
+mod sparse;
+mod table;
+mod threads;
+mod tiles;
+mod timer;
 mod utils;
 
+pub use builder::UniverseBuilder;
+pub use error::Error;
+pub use hashlife::CacheStats;
+pub use infinite::{Engine, InfiniteUniverse};
+pub use layers::LayeredUniverse;
+pub use life3d::Universe3d;
+pub use pattern::{Blend, Direction, Pattern, Symmetry, Transform};
+pub use row::Row;
+pub use rule::Rule;
+pub use timer::TimingStats;
+
+use ant::{Ant, AntRule};
+use continuous::Continuous;
+use counts::Counts;
 use fixedbitset::FixedBitSet;
+use lookup::LifeTable;
+use macrocell::Macrocell;
+use render::RenderStyle;
+use rng::Rng;
+use rule::{Neighborhood, SAND_TOPPLING, WIREWORLD_CONDUCTOR, WIREWORLD_HEAD, WIREWORLD_TAIL};
+use sparse::LiveCells;
 use std::fmt;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::mem::{size_of, size_of_val};
+use std::rc::Rc;
+use std::str::FromStr;
+use tiles::{DirtyTiles, Tiles};
+use timer::Timer;
 use wasm_bindgen::prelude::*;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
@@ -16,10 +73,23 @@ extern "C" {
     #[wasm_bindgen(js_namespace = Math)]
     pub fn random() -> f64;
 
+    /// Milliseconds since the page started, to a fraction of one.
+    #[wasm_bindgen(js_namespace = performance)]
+    fn now() -> f64;
+
     #[wasm_bindgen(js_namespace = console)]
     pub fn log(msg: &str);
+
+    #[wasm_bindgen(js_namespace = console)]
+    fn time(label: &str);
+
+    #[wasm_bindgen(js_namespace = console, js_name = timeEnd)]
+    fn time_end(label: &str);
 }
 
+/// Probability of a cell being alive in a freshly randomized universe.
+const DEFAULT_DENSITY: f64 = 0.3;
+
 macro_rules! log {
     ( $( $t:tt )* ) => {
         log(&format!( $( $t )* ));
@@ -28,208 +98,3574 @@ macro_rules! log {
 
 trait SetIterBool {
     fn set_iter(&mut self, from: usize, source: &[bool]);
+    fn toggle_bit(&mut self, idx: usize);
 }
 
 impl SetIterBool for FixedBitSet {
+    fn toggle_bit(&mut self, idx: usize) {
+        let cell = self[idx];
+        self.set(idx, !cell);
+    }
+
     fn set_iter(&mut self, from: usize, source: &[bool]) {
         let limit = self.len();
-        let mut idx = from;
-        for item in source.iter() {
-            if idx >= limit { () }
+        for (idx, item) in (from..limit).zip(source.iter()) {
             self.set(idx, *item);
-            idx += 1;
         }
     }
 }
 
+/**
+ * What lies beyond the edges of the universe.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    /// Opposite edges are stitched together, the left and right ones
+    /// possibly shifted, see `Universe::set_torus_shift`.
+    Torus,
+    /// Everything outside the grid is empty space: dead, except under B0
+    /// rules where empty space strobes as reported by `background`.
+    Dead,
+    /// Everything outside the grid is alive, so that Life grows in from the
+    /// edges.
+    Alive,
+    /// The left and right edges are stitched together, and beyond the top
+    /// and bottom ones is empty space as with `Dead`.
+    HorizontalCylinder,
+    /// The top and bottom edges are stitched together, and beyond the left
+    /// and right ones is empty space as with `Dead`.
+    VerticalCylinder,
+    /// A Klein bottle: the left and right edges are stitched together as on
+    /// a torus, and so are the top and bottom ones but with a twist, cells
+    /// crossing them coming back on the mirrored column.
+    KleinBottle,
+    /// A cross-surface: both pairs of edges are stitched together with a
+    /// twist, cells crossing the left or right edge also coming back on the
+    /// mirrored row.
+    CrossSurface,
+    /// Every edge is a mirror: cells beyond it are the reflections of those
+    /// inside, so a cell on the edge counts itself among its neighbors.
+    Mirror,
+    /// The surface of a cube, for a grid 3n cells wide and 2n high: six
+    /// faces n cells across, laid out three across and two down, every
+    /// edge stitched to the one it meets on the cube.
+    /// Cells on the corners of faces have 7 neighbors, as only three faces
+    /// meet there. Grids of other sizes get a dead boundary instead.
+    Cube,
+}
+
+impl Boundary {
+    /// Whether the top and bottom edges and whether the left and right ones
+    /// are stitched together, as `(rows, cols)`.
+    fn wraps(self) -> (bool, bool) {
+        match self {
+            Boundary::Torus | Boundary::KleinBottle | Boundary::CrossSurface => (true, true),
+            Boundary::HorizontalCylinder => (false, true),
+            Boundary::VerticalCylinder => (true, false),
+            Boundary::Dead | Boundary::Alive | Boundary::Mirror | Boundary::Cube => (false, false),
+        }
+    }
+
+    /// Whether crossing the top or bottom edge mirrors the column and
+    /// whether crossing the left or right edge mirrors the row, as
+    /// `(rows, cols)`.
+    fn twists(self) -> (bool, bool) {
+        match self {
+            Boundary::KleinBottle => (true, false),
+            Boundary::CrossSurface => (true, true),
+            _ => (false, false),
+        }
+    }
+}
+
+/**
+ * Which part of the grid stays in place when the universe is resized.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Offset added to old coordinates along each axis, as `(rows, cols)`.
+    fn offset(self, old: (u32, u32), new: (u32, u32)) -> (i64, i64) {
+        let grow = |old: u32, new: u32, fraction: i64| (new as i64 - old as i64) * fraction / 2;
+        let (row_fraction, col_fraction) = match self {
+            Anchor::TopLeft => (0, 0),
+            Anchor::Top => (0, 1),
+            Anchor::TopRight => (0, 2),
+            Anchor::Left => (1, 0),
+            Anchor::Center => (1, 1),
+            Anchor::Right => (1, 2),
+            Anchor::BottomLeft => (2, 0),
+            Anchor::Bottom => (2, 1),
+            Anchor::BottomRight => (2, 2),
+        };
+        (grow(old.0, new.0, row_fraction), grow(old.1, new.1, col_fraction))
+    }
+}
+
+/**
+ * A side of the grid.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+/**
+ * How two state B/S rules on the Moore neighborhood step their cells. All
+ * give the same generations; which is faster depends on the browser and
+ * the grid.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kernel {
+    /// 64 cells at a time, adding up their neighbors with bitwise full
+    /// adders.
+    Bitwise,
+    /// A 2x2 block at a time, looking up the 4x4 window around it in a table
+    /// built for the rule.
+    Lookup,
+    /// The cells around those that changed in the last generation alone,
+    /// from every cell's count of live neighbors, kept up to date as cells
+    /// change. Ticks take as long as the universe has changes rather than
+    /// cells, but the counts take a byte a cell. Universes that can not
+    /// step from the live cells alone, as listed by `is_sparse`, step like
+    /// `Bitwise`.
+    Incremental,
+}
+
+/**
+ * What a batch of ticks did, see `Universe::tick_n`.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TickStats {
+    /// Generations stepped.
+    pub generations: u32,
+    /// Cells the rule brought to state 1, over all the generations.
+    pub births: u64,
+    /// Cells the rule took out of state 1, over all the generations.
+    pub deaths: u64,
+}
+
+/**
+ * About how many bytes of wasm memory a universe takes, see
+ * `Universe::memory_stats`.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// The cells, and the copy of them ticks step into unless sparse.
+    pub cells: u32,
+    /// The states, sand, levels and colors of the cells under rules with
+    /// them, and the mask.
+    pub states: u32,
+    /// What ticks keep to go faster: the tiles, the lookup table, the live
+    /// cells of sparse universes and the neighbor counts, all rebuilt when
+    /// dropped.
+    pub caches: u32,
+    /// The deltas and dirty tiles being recorded.
+    pub recording: u32,
+    /// All of them together.
+    pub total: u32,
+}
+
+/// A rule giving way to the universe's rule, see `Universe::morph_rule`.
+#[derive(Clone)]
+struct Morph {
+    from: Rule,
+    generations: u32,
+    elapsed: u32,
+}
+
+/// A rectangle of the grid running a rule of its own, see
+/// `Universe::set_region_rule`.
+#[derive(Clone)]
+struct Region {
+    row: u32,
+    col: u32,
+    height: u32,
+    width: u32,
+    rule: Rule,
+}
+
+/// An edge of the grid forced through a repeating sequence of states, see
+/// `Universe::set_edge_source`.
+#[derive(Clone)]
+struct Source {
+    edge: Edge,
+    /// The states of the edge's cells for one generation after another.
+    frames: Vec<Vec<u8>>,
+}
+
 /**
  * A universe representation in the game of life.
+ *
+ * Two universes are equal when they have the same size and the same live
+ * cells, regardless of their random number generator state.
  */
 #[wasm_bindgen]
+#[derive(Clone)]
 pub struct Universe {
     width: u32,
     height: u32,
     cells: FixedBitSet,
+    /// The cells as they were before the last tick. Ticks copy the cells
+    /// over it and step them into it, swapping it with `cells`, so that no
+    /// generation allocates a grid of its own.
+    previous: FixedBitSet,
+    /// Which tiles changed since Life-like rules last stepped them, dropped
+    /// by every other tick.
+    tiles: Option<Tiles>,
+    /// The live cells, kept along with `cells` while so few live that ticks
+    /// step them alone, leaving `previous` empty.
+    live: Option<LiveCells>,
+    /// Cells that changed in the ticks since they were last taken, if ticks
+    /// record them.
+    deltas: Option<FixedBitSet>,
+    /// The neighbor counts of the cells, kept while the incremental kernel
+    /// steps them.
+    counts: Option<Counts>,
+    /// Which tiles ticks changed since they were last taken, if ticks
+    /// record them.
+    dirty: Option<DirtyTiles>,
+    /// How long the last ticks took, if they are timed.
+    timer: Option<Timer>,
+    seed: u32,
+    rng: Rng,
+    boundary: Boundary,
+    /// Rows a torus shifts by for cells crossing its left or right edge.
+    torus_shift: i32,
+    style: RenderStyle,
+    rule: Rule,
+    /// State of every cell under rules with more than two states, such as
+    /// the dying states of Generations rules, with 0 standing for the state
+    /// kept in `cells`. Empty for two state rules.
+    states: Vec<u8>,
+    /// Grains of sand beyond the first 4 of every toppling cell under the
+    /// sandpile rule. Empty under other rules.
+    sand: Vec<u32>,
+    /// Level of every cell, from 0 to 1, under continuous rules such as
+    /// Lenia, live cells being those from one half up. Empty under other
+    /// rules.
+    levels: Vec<f32>,
+    /// Color of every live cell, from 0, under rules with colored live cells
+    /// such as Immigration. Empty under other rules.
+    colors: Vec<u8>,
+    /// State of empty space, which B0 rules bring to life.
+    background: bool,
+    /// Langton's ants walking over the cells, moving after them every tick.
+    ants: Vec<Ant>,
+    ant_rule: AntRule,
+    /// Neighbor offsets replacing the rule's neighborhood, if any.
+    neighborhood: Option<Vec<(i32, i32)>>,
+    /// How Life-like rules step, and the table of the last rule they stepped
+    /// by through one.
+    kernel: Kernel,
+    life_table: Option<Rc<LifeTable>>,
+    /// JavaScript function replacing the rule, if any.
+    rule_callback: Option<js_sys::Function>,
+    /// The rule the universe is morphing from, if any.
+    morph: Option<Morph>,
+    /// Regions running their own rule, later ones drawn over earlier ones.
+    regions: Vec<Region>,
+    /// Whether cells only count neighbors from their own region.
+    isolated_regions: bool,
+    /// Chance of every cell flipping after each tick.
+    noise_rate: f64,
+    /// Cells the rule brought to state 1 on the last tick.
+    births: u32,
+    /// Cells the rule took out of state 1 on the last tick.
+    deaths: u32,
+    /// How close live cells may come to the edges of a dead boundary before
+    /// the grid grows, if it grows at all.
+    expand_margin: Option<u32>,
+    /// Rows and columns growth has added above and left of the cells.
+    origin: (u32, u32),
+    /// Cells that exist, if not every cell does. The others stay dead.
+    mask: Option<FixedBitSet>,
+    /// Edges whose cells are set anew every generation, later ones over
+    /// earlier ones where they meet.
+    sources: Vec<Source>,
+    generation: u64,
 }
 
 impl fmt::Display for Universe {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.style.leading_newline {
+            writeln!(f)?;
+        }
         for i in 0..self.cells.len() {
-            if i % (self.width as usize) == 0 {
-                write!(f, "\n")?;
+            if i != 0 && i % (self.width as usize) == 0 {
+                write!(f, "{}", self.style.row_separator)?;
             }
-            let symbol = if self.cells[i] { '◻' } else { '◼' };
+            let symbol = match self.state(i) {
+                _ if !self.exists(i) => self.style.missing,
+                0 => self.style.dead,
+                _ if self.cells[i] => self.style.alive,
+                _ => self.style.dying,
+            };
             write!(f, "{}", symbol)?;
         }
+        if self.style.trailing_newline {
+            writeln!(f)?;
+        }
         Ok(())
     }
 }
 
+impl PartialEq for Universe {
+    fn eq(&self, other: &Universe) -> bool {
+        self.width == other.width && self.height == other.height && self.cells == other.cells
+    }
+}
+
+impl Eq for Universe {}
+
+impl Hash for Universe {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.width.hash(state);
+        self.height.hash(state);
+        self.cells.hash(state);
+    }
+}
+
+impl FromStr for Universe {
+    type Err = Error;
+
+    /**
+     * Parse the output of `render` back into a universe. Besides '◻' and '◼',
+     * 'O' and '.' are accepted for alive and dead cells; blank lines are
+     * skipped and short rows are padded with dead cells.
+     */
+    fn from_str(s: &str) -> Result<Universe, Error> {
+        let mut rows = Vec::new();
+        for (line_idx, line) in s.lines().enumerate() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+            let row = line
+                .chars()
+                .enumerate()
+                .map(|(col, symbol)| match symbol {
+                    '◻' | 'O' => Ok(true),
+                    '◼' | '.' => Ok(false),
+                    other => Err(Error::Parse {
+                        line: line_idx + 1,
+                        message: format!("unexpected cell symbol {:?} in column {}", other, col + 1),
+                    }),
+                })
+                .collect::<Result<Vec<bool>, Error>>()?;
+            rows.push(row);
+        }
+
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0) as u32;
+        let mut universe = Universe::new_empty(width, rows.len() as u32);
+        for (row, cells) in rows.iter().enumerate() {
+            let idx = universe.get_index(row as u32, 0);
+            universe.cells.set_iter(idx, cells);
+        }
+        Ok(universe)
+    }
+}
+
 impl Universe {
     pub fn get_cells(&self) -> &FixedBitSet {
         &self.cells
     }
 
+    /**
+     * Returns a view of the given row, or `None` when it is out of range.
+     */
+    pub fn row(&self, row: u32) -> Option<Row<'_>> {
+        if row >= self.height {
+            return None;
+        }
+        Some(Row::new(&self.cells, self.get_index(row, 0), self.width as usize))
+    }
+
+    /// The part of `row` in a window `width` columns wide from `col`, or
+    /// `None` when none of it is in the universe.
+    fn window_row(&self, row: u32, col: u32, width: u32) -> Option<Row<'_>> {
+        if row >= self.height || col >= self.width {
+            return None;
+        }
+        let visible = width.min(self.width - col) as usize;
+        Some(Row::new(&self.cells, self.get_index(row, col), visible))
+    }
+
+    /**
+     * Iterate over every row of the universe, top to bottom.
+     */
+    pub fn rows(&self) -> impl Iterator<Item = Row<'_>> {
+        (0..self.height).map(move |row| Row::new(&self.cells, self.get_index(row, 0), self.width as usize))
+    }
+
     pub fn set_cells(&mut self, cells: &[(u32, u32)]) {
         for (row, col) in cells.iter() {
             let idx = self.get_index(*row, *col);
-            self.cells.set(idx, true);
+            self.set_alive(idx, true);
         }
     }
 
     pub fn empty_cells(&mut self) {
         self.cells.set_range(.., false);
+        if let Some(live) = &mut self.live {
+            live.clear();
+        }
+        self.counts = None;
+        self.states.iter_mut().for_each(|state| *state = 0);
+        self.sand.iter_mut().for_each(|grains| *grains = 0);
+        self.levels.iter_mut().for_each(|level| *level = 0.0);
+        self.colors.iter_mut().for_each(|color| *color = 0);
+    }
+
+    /// Set a cell alive or dead, ending any dying state it was in. Cells the
+    /// mask leaves out stay dead.
+    fn set_alive(&mut self, idx: usize, alive: bool) {
+        let (alive, was) = (alive && self.exists(idx), self.cells[idx]);
+        self.cells.set(idx, alive);
+        self.edited(idx, was);
+        if let Some(state) = self.states.get_mut(idx) {
+            *state = 0;
+        }
+        if let Some(grains) = self.sand.get_mut(idx) {
+            *grains = 0;
+        }
+        if let Some(level) = self.levels.get_mut(idx) {
+            *level = alive as u8 as f32;
+        }
+        if let Some(color) = self.colors.get_mut(idx) {
+            *color = 0;
+        }
+    }
+
+    /// Flip a cell between alive and dead, ending any dying state it was in.
+    fn toggle(&mut self, idx: usize) {
+        if !self.exists(idx) {
+            return;
+        }
+        self.cells.toggle_bit(idx);
+        self.edited(idx, !self.cells[idx]);
+        if let Some(state) = self.states.get_mut(idx) {
+            *state = 0;
+        }
+        if let Some(grains) = self.sand.get_mut(idx) {
+            *grains = 0;
+        }
+        if let Some(level) = self.levels.get_mut(idx) {
+            *level = self.cells[idx] as u8 as f32;
+        }
+        if let Some(color) = self.colors.get_mut(idx) {
+            *color = 0;
+        }
+    }
+
+    /// Bring the live cells and neighbor counts, if kept, up to date with a
+    /// change to cell `idx`, which was alive if `was`.
+    fn edited(&mut self, idx: usize, was: bool) {
+        let alive = self.cells[idx];
+        if alive == was {
+            return;
+        }
+        let width = self.width as usize;
+        if let Some(live) = &mut self.live {
+            let cell = ((idx / width) as u32, (idx % width) as u32);
+            if alive {
+                live.insert(cell);
+            } else {
+                live.remove(&cell);
+            }
+        }
+        if let Some(mut counts) = self.counts.take() {
+            counts.change(idx, alive, self.moore_neighbors(idx));
+            self.counts = Some(counts);
+        }
+    }
+
+    /// The cells around cell `idx` on the Moore neighborhood, as
+    /// `neighbor_index` finds them.
+    fn moore_neighbors(&self, idx: usize) -> impl Iterator<Item = usize> + '_ {
+        let (row, col) = ((idx / self.width as usize) as u32, (idx % self.width as usize) as u32);
+        let offsets = Neighborhood::Moore.offsets(row);
+        offsets.iter().filter_map(move |&(delta_row, delta_col)| self.neighbor_index(row, col, delta_row, delta_col))
+    }
+
+    /// Put a cell at `level` under a continuous rule, clamped to between 0
+    /// and 1.
+    fn put_level(&mut self, idx: usize, level: f32) {
+        let level = if level.is_nan() || !self.exists(idx) { 0.0 } else { level.clamp(0.0, 1.0) };
+        let was = self.cells[idx];
+        self.cells.set(idx, level >= 0.5);
+        self.edited(idx, was);
+        self.levels[idx] = level;
+    }
+
+    /// Grains of sand on a cell under the sandpile rule.
+    fn grains(&self, idx: usize) -> u32 {
+        match self.state(idx) {
+            SAND_TOPPLING => u32::from(SAND_TOPPLING) + self.sand[idx],
+            state if state < SAND_TOPPLING => u32::from(state),
+            _ => 0,
+        }
+    }
+
+    /// Put `grains` grains of sand on a cell under the sandpile rule.
+    fn put_grains(&mut self, idx: usize, grains: u32) {
+        let toppling = u32::from(SAND_TOPPLING);
+        self.put_state(idx, grains.min(toppling) as u8);
+        self.sand[idx] = grains.saturating_sub(toppling);
+    }
+
+    /// Put a cell in `state`, clamped to the last state of the current rule.
+    fn put_state(&mut self, idx: usize, state: u8) {
+        let state = if self.exists(idx) { state.min(self.state_count() - 1) } else { 0 };
+        let colored = !self.colors.is_empty() && state > 1 && state < self.rule.states();
+        self.set_alive(idx, state == 1 || colored);
+        if colored {
+            self.colors[idx] = state - 1;
+        } else if state > 1 {
+            self.states[idx] = state;
+        }
+    }
+
+    /// Fail unless the universe runs under `rule`.
+    fn expect_rule(&self, rule: Rule) -> Result<(), Error> {
+        if self.rule != rule {
+            return Err(Error::WrongRule {
+                expected: rule.to_string(),
+                actual: self.rule.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether a cell is on the board, which every cell is without a mask.
+    fn exists(&self, idx: usize) -> bool {
+        self.mask.as_ref().is_none_or(|mask| mask[idx])
+    }
+
+    /// Kill every cell the mask leaves out.
+    fn apply_mask(&mut self) {
+        if let Some(mask) = self.mask.take() {
+            for idx in (0..mask.len()).filter(|&idx| !mask[idx]) {
+                self.set_alive(idx, false);
+            }
+            self.mask = Some(mask);
+        }
+    }
+
+    /// State of a cell: 0 when dead, 1 when alive, and from 2 up when dying,
+    /// or 1 and up by color when alive under rules with colored live cells.
+    fn state(&self, idx: usize) -> u8 {
+        if self.cells[idx] {
+            1 + self.colors.get(idx).cloned().unwrap_or(0)
+        } else {
+            self.states.get(idx).cloned().unwrap_or(0)
+        }
     }
-}
 
-#[wasm_bindgen]
-impl Universe {
     /**
-     * Create and initialize a new universe.
+     * Returns the smallest rectangle holding every live cell as
+     * `(row, col, width, height)`, or `None` when the universe is empty.
      */
-    pub fn new(width: u32, height: u32) -> Universe {
-        utils::set_panic_hook();
+    pub fn bounding_box(&self) -> Option<(u32, u32, u32, u32)> {
+        let mut ones = self.cells.ones().map(|idx| (idx as u32 / self.width, idx as u32 % self.width));
+        let (first_row, first_col) = ones.next()?;
+        let (mut min_col, mut max_row, mut max_col) = (first_col, first_row, first_col);
+        for (row, col) in ones {
+            min_col = min_col.min(col);
+            max_col = max_col.max(col);
+            max_row = row;
+        }
+        Some((first_row, min_col, max_col - min_col + 1, max_row - first_row + 1))
+    }
+
+    /**
+     * Blend every cell of `pattern` into the universe, centered on (row, col)
+     * and wrapping around the edges.
+     */
+    fn write_pattern(&mut self, pattern: &Pattern, row: u32, col: u32, blend: Blend) {
+        let top = row as i64 - (pattern.height() / 2) as i64;
+        let left = col as i64 - (pattern.width() / 2) as i64;
+        for pattern_row in 0..pattern.height() {
+            for pattern_col in 0..pattern.width() {
+                let target_row = (top + pattern_row as i64).rem_euclid(self.height as i64) as u32;
+                let target_col = (left + pattern_col as i64).rem_euclid(self.width as i64) as u32;
+                let idx = self.get_index(target_row, target_col);
+                let alive = blend.apply(self.cells[idx], pattern.get(pattern_row, pattern_col));
+                if alive != self.cells[idx] || blend == Blend::Overwrite {
+                    self.set_alive(idx, alive);
+                }
+            }
+        }
+    }
+
+    /**
+     * Copy the `width` x `height` region whose top left corner is (row, col)
+     * into a pattern, wrapping around the edges.
+     */
+    fn region_pattern(&self, row: u32, col: u32, width: u32, height: u32) -> Result<Pattern, Error> {
+        let (width, height) = (width.min(self.width), height.min(self.height));
+        let mut pattern = Pattern::new(width, height)?;
+        for (i, idx) in self.region_indices(row, col, width, height)?.into_iter().enumerate() {
+            pattern.set(i as u32 / width, i as u32 % width, self.cells[idx]);
+        }
+        Ok(pattern)
+    }
+
+    fn put_library_pattern(
+        &mut self,
+        name: &str,
+        row: u32,
+        col: u32,
+        transform: Option<Transform>,
+        blend: Blend,
+    ) -> Result<(), Error> {
+        self.checked_index(row, col)?;
+        let entry = patterns::find(name).ok_or_else(|| Error::UnknownPattern(name.to_string()))?;
+        let pattern = rle::parse(entry.rle)?.transformed(transform.unwrap_or(Transform::Identity));
+        self.write_pattern(&pattern, row, col, blend);
+        Ok(())
+    }
+
+    /**
+     * Put a library pattern whose RLE heads `heading`, rotated to travel in
+     * `direction`.
+     */
+    fn put_moving_pattern(
+        &mut self,
+        name: &str,
+        heading: Direction,
+        row: u32,
+        col: u32,
+        direction: Option<Direction>,
+    ) -> Result<(), Error> {
+        let direction = direction.unwrap_or(heading);
+        let transform = heading
+            .rotation_to(direction)
+            .ok_or_else(|| Error::UnsupportedDirection {
+                pattern: name.to_string(),
+                direction: format!("{:?}", direction),
+            })?;
+        self.put_library_pattern(name, row, col, Some(transform), Blend::Overwrite)
+    }
+
+    /**
+     * Replace the grid with a `width` x `height` one, moving every live or
+     * dying cell to the position returned by `map`. Cells mapped to `None`
+     * are dropped.
+     */
+    fn remap<F>(&mut self, width: u32, height: u32, map: F)
+    where
+        F: Fn(u32, u32) -> Option<(u32, u32)>,
+    {
         let size = (width * height) as usize;
+        let new_index = |idx: usize| {
+            let (row, col) = (idx as u32 / self.width, idx as u32 % self.width);
+            map(row, col)
+                .filter(|&(new_row, new_col)| new_row < height && new_col < width)
+                .map(|(new_row, new_col)| (new_row * width + new_col) as usize)
+        };
         let mut cells = FixedBitSet::with_capacity(size);
-        for i in 0..size {
-            cells.set(i, random() < 0.3);
+        for idx in self.cells.ones() {
+            if let Some(new_idx) = new_index(idx) {
+                cells.insert(new_idx);
+            }
+        }
+        // The rule decides which of these the grid keeps, as in
+        // `fit_states`, so that they come back when it grows from no cells.
+        let kept = |kept: bool| if kept { size } else { 0 };
+        let mut states = vec![0; kept(self.state_count() > 2)];
+        for (idx, &state) in self.states.iter().enumerate() {
+            if let (true, Some(new_idx)) = (state != 0, new_index(idx)) {
+                states[new_idx] = state;
+            }
+        }
+        let mut sand = vec![0; kept(self.rule.is_sandpile())];
+        for (idx, &grains) in self.sand.iter().enumerate() {
+            if let (true, Some(new_idx)) = (grains != 0, new_index(idx)) {
+                sand[new_idx] = grains;
+            }
+        }
+        let mut levels = vec![0.0; kept(self.rule.continuous().is_some())];
+        for (idx, &level) in self.levels.iter().enumerate() {
+            if let (true, Some(new_idx)) = (level != 0.0, new_index(idx)) {
+                levels[new_idx] = level;
+            }
+        }
+        let mut colors = vec![0; kept(self.rule.colors().is_some())];
+        for (idx, &color) in self.colors.iter().enumerate() {
+            if let (true, Some(new_idx)) = (color != 0, new_index(idx)) {
+                colors[new_idx] = color;
+            }
         }
+        // Cells moving in from beyond the old grid exist.
+        let mask = self.mask.as_ref().map(|mask| {
+            let mut new_mask = FixedBitSet::with_capacity(size);
+            new_mask.set_range(.., true);
+            for idx in (0..mask.len()).filter(|&idx| !mask[idx]) {
+                if let Some(new_idx) = new_index(idx) {
+                    new_mask.set(new_idx, false);
+                }
+            }
+            new_mask
+        });
 
-        Universe {
-            width,
-            height,
-            cells,
+        self.ants = self
+            .ants
+            .iter()
+            .filter_map(|ant| {
+                let new_idx = new_index(self.get_index(ant.row, ant.col))?;
+                Some(Ant {
+                    row: new_idx as u32 / width,
+                    col: new_idx as u32 % width,
+                    ..ant.clone()
+                })
+            })
+            .collect();
+
+        self.width = width;
+        self.height = height;
+        self.cells = cells;
+        self.live = None;
+        self.counts = None;
+        self.states = states;
+        self.sand = sand;
+        self.levels = levels;
+        self.colors = colors;
+        self.mask = mask;
+        let (width, height) = (width as usize, height as usize);
+        self.sources.retain(|source| {
+            let length = if matches!(source.edge, Edge::Top | Edge::Bottom) { width } else { height };
+            source.frames[0].len() == length
+        });
+    }
+
+    /// Face every ant where it would after reorienting the cells under it.
+    fn transform_ants(&mut self, transform: Transform) {
+        for ant in self.ants.iter_mut() {
+            ant.heading = ant.heading.transformed(transform);
         }
     }
 
-    pub fn reset(&mut self) {
-        for i in 0..self.cells.len() {
-            self.cells.set(i, random() < 0.3);
+    /// Size the per-cell states for the current rule and ant rule, dropping
+    /// states neither has, the grains of sand for the sandpile rule, the
+    /// levels for continuous rules, which start from the live cells, and the
+    /// colors of live cells for rules with colored ones.
+    fn fit_states(&mut self) {
+        let states = self.state_count();
+        if states <= 2 {
+            self.states = Vec::new();
+        } else if self.states.len() != self.cells.len() {
+            self.states = vec![0; self.cells.len()];
+        } else {
+            self.states.iter_mut().filter(|state| **state >= states).for_each(|state| *state = 0);
+        }
+        if !self.rule.is_sandpile() {
+            self.sand = Vec::new();
+        } else if self.sand.len() != self.cells.len() {
+            self.sand = vec![0; self.cells.len()];
         }
+        if self.rule.continuous().is_none() {
+            self.levels = Vec::new();
+        } else if self.levels.len() != self.cells.len() {
+            self.levels = (0..self.cells.len()).map(|idx| self.cells[idx] as u8 as f32).collect();
+        }
+        match self.rule.colors() {
+            None => self.colors = Vec::new(),
+            Some(_) if self.colors.len() != self.cells.len() => self.colors = vec![0; self.cells.len()],
+            Some(colors) => self.colors.iter_mut().filter(|color| **color >= colors).for_each(|color| *color = 0),
+        }
+    }
+
+    /// Number of cell colors used by the ant rule and every turmite's own.
+    fn ant_colors(&self) -> u8 {
+        let turmites = self.ants.iter().filter_map(|ant| ant.rule.as_ref());
+        turmites.map(|rule| rule.colors()).fold(self.ant_rule.colors(), u8::max)
+    }
+
+    /// Move every ant one step: turn by the color of its cell and its state,
+    /// recolor the cell and step forward. Ants walking off an edge that
+    /// neither wraps nor mirrors are gone.
+    fn step_ants(&mut self) {
+        let mut ants = std::mem::take(&mut self.ants);
+        ants.retain_mut(|ant| {
+            let idx = self.get_index(ant.row, ant.col);
+            let rule = ant.rule.as_deref().unwrap_or(&self.ant_rule);
+            let transition = rule.transition(ant.state, self.state(idx));
+            ant.heading = transition.turn(ant.heading);
+            ant.state = transition.state;
+            self.put_state(idx, transition.color);
+            let (delta_row, delta_col) = ant.heading.offset();
+            match self.neighbor_index(ant.row, ant.col, delta_row, delta_col) {
+                Some(next) => {
+                    ant.row = next as u32 / self.width;
+                    ant.col = next as u32 % self.width;
+                    true
+                }
+                None => false,
+            }
+        });
+        self.ants = ants;
     }
+}
 
+#[wasm_bindgen]
+impl Universe {
     /**
-     * Toggle specified cell value.
+     * Create and initialize a new universe.
      */
-    pub fn toggle_cell(&mut self, row: u32, col: u32) {
-        log!("toggling cell on row {} col {}", row, col);
-        let idx = self.get_index(row, col);
-        let cell = self.cells[idx];
-        self.cells.set(idx, !cell);
+    pub fn new(width: u32, height: u32) -> Universe {
+        Universe::new_with_density(width, height, DEFAULT_DENSITY)
     }
 
     /**
-     * Put a [Glider](https://en.wikipedia.org/wiki/Glider_(Conway%27s_Life)#Hacker_emblem) with center in row, col.
+     * Create a new universe where each cell is alive with probability `density`.
      */
-    pub fn put_glider(&mut self, row: u32, col: u32) {
-        log!("putting glidder on row {} col {}", row, col);
-        let col_left = (col + self.width - 1) % self.width;
+    pub fn new_with_density(width: u32, height: u32, density: f64) -> Universe {
+        let mut universe = Universe::new_empty(width, height);
+        universe.reset_with_density(density);
+        universe
+    }
 
-        let glider_top = [false, true, false];
-        let row_top = (row + self.height - 1) % self.height;
-        self.cells.set_iter(self.get_index(row_top, col_left), &glider_top);
+    /**
+     * Create a new universe with every cell dead.
+     */
+    pub fn new_empty(width: u32, height: u32) -> Universe {
+        utils::set_panic_hook();
+        let size = (width * height) as usize;
+        let seed = utils::random_seed();
 
-        let glider_middle = [false, false, true];
-        self.cells.set_iter(self.get_index(row, col_left), &glider_middle);
+        Universe {
+            width,
+            height,
+            cells: FixedBitSet::with_capacity(size),
+            previous: FixedBitSet::with_capacity(size),
+            tiles: None,
+            live: None,
+            deltas: None,
+            counts: None,
+            dirty: None,
+            timer: None,
+            seed,
+            rng: Rng::new(seed),
+            boundary: Boundary::Torus,
+            torus_shift: 0,
+            style: RenderStyle::default(),
+            rule: Rule::default(),
+            states: Vec::new(),
+            sand: Vec::new(),
+            levels: Vec::new(),
+            colors: Vec::new(),
+            background: false,
+            ants: Vec::new(),
+            ant_rule: AntRule::default(),
+            neighborhood: None,
+            kernel: Kernel::Bitwise,
+            life_table: None,
+            rule_callback: None,
+            morph: None,
+            regions: Vec::new(),
+            isolated_regions: false,
+            noise_rate: 0.0,
+            births: 0,
+            deaths: 0,
+            expand_margin: None,
+            origin: (0, 0),
+            mask: None,
+            sources: Vec::new(),
+            generation: 0,
+        }
+    }
 
-        let glider_bottom = [true, true, true];
-        let row_bottom = (row + 1) % self.height;
-        self.cells.set_iter(self.get_index(row_bottom, col_left), &glider_bottom);
+    /**
+     * Parse a universe from the output of `render`, or from '.'/'O' plaintext.
+     */
+    pub fn from_string(s: &str) -> Result<Universe, JsError> {
+        Ok(s.parse::<Universe>()?)
     }
 
-    pub fn put_pulsar(&mut self, row: u32, col: u32) {
-        log!("putting pulsar on row {} col {}", row, col);
-        let p1 = &[false, false, true, true, true, false, false, false, true, true, true, false, false];
-        let p2 = &[false, false, false, false, false, false, false, false, false, false, false, false, false];
-        let p3 = &[true, false, false, false, false, true, false, true, false, false, false, false, true];
+    /**
+     * Create a universe from external cell data. With `packed` unset every
+     * byte holds one cell, alive when nonzero; otherwise every byte holds
+     * eight cells, lowest bit first, matching the layout behind `cells()`.
+     */
+    pub fn from_bytes(width: u32, height: u32, data: &[u8], packed: bool) -> Result<Universe, JsError> {
+        let size = (width * height) as usize;
+        let expected = if packed { size.div_ceil(8) } else { size };
+        if data.len() < expected {
+            return Err(Error::DataLength {
+                expected,
+                actual: data.len(),
+            }
+            .into());
+        }
 
-        let pulsar = [p1, p2, p3, p3, p3, p1, p2, p1, p3, p3, p3, p2, p1];
-        let left_col = (col + self.width - 6) % self.width;
-        let mut curr_row = (row + self.height - 6) % self.height;
-        for &line in pulsar.iter() {
-            let idx = self.get_index(curr_row, left_col);
-            self.cells.set_iter(idx, line);
-            curr_row = (curr_row + 1) % self.height;
+        let mut universe = Universe::new_empty(width, height);
+        for i in 0..size {
+            let alive = if packed { data[i / 8] & (1 << (i % 8)) != 0 } else { data[i] != 0 };
+            universe.cells.set(i, alive);
         }
+        Ok(universe)
     }
 
     /**
-     * Returns the width of the universe.
+     * Create a new randomized universe whose soup is fully determined by `seed`.
      */
-    pub fn width(&self) -> u32 {
-        self.width
+    pub fn new_with_seed(width: u32, height: u32, seed: u32) -> Universe {
+        let mut universe = Universe::new_empty(width, height);
+        universe.set_seed(seed);
+        universe.reset();
+        universe
     }
 
     /**
-     * Returns the height of the universe.
+     * Reseed the universe's random number generator, making the following
+     * calls to `reset` reproducible.
      */
-    pub fn height(&self) -> u32 {
-        self.height
+    pub fn set_seed(&mut self, seed: u32) {
+        self.seed = seed;
+        self.rng = Rng::new(seed);
     }
 
     /**
-     * Returns a pointer to the universe's cells in wasm memory.
+     * Returns the seed the random number generator was last seeded with.
      */
-    pub fn cells(&self) -> *const u32 {
-        self.cells.as_slice().as_ptr()
+    pub fn seed(&self) -> u32 {
+        self.seed
     }
 
     /**
-     * Returns a string representation of the universe.
+     * Returns whether every cell in the universe is dead.
      */
-    pub fn render(&self) -> String {
-        self.to_string()
+    pub fn is_empty(&self) -> bool {
+        self.cells.ones().next().is_none()
+    }
+
+    pub fn reset(&mut self) {
+        self.reset_with_density(DEFAULT_DENSITY);
     }
 
     /**
-     * Simulate a step in the universe.
+     * Randomize every cell, each one being alive with probability `density`,
+     * and restart the generation count.
      */
-    pub fn tick(&mut self) {
-        let mut next_cells = self.cells.clone();
+    pub fn reset_with_density(&mut self, density: f64) {
+        for i in 0..self.cells.len() {
+            let alive = self.rng.next_f64() < density;
+            self.set_alive(i, alive);
+        }
+        self.background = false;
+        self.generation = 0;
+    }
 
-        for row in 0..self.height {
-            for col in 0..self.width {
-                let index = self.get_index(row, col);
-                let cell = self.cells[index];
-                let live_neighbors = self.live_neighbor_count(row, col);
-
-                let next_cell = match (cell, live_neighbors) {
-                    // Rule 1: Any live cell with fewer than two live neighbours dies.
-                    (true, x) if x < 2 => false,
-                    // Rule 2: Any live cell with two or three live neighbours lives
-                    // onto the next generation.
-                    (true, 2) | (true, 3) => true,
-                    // Rule 3: Any live cell with more than three neighbours dies.
-                    (true, x) if x > 3 => false,
-                    // Rule 4: Any dead cell with exactly three neighbours becomes a live cell.
-                    (false, 3) => true,
-                    // All other cells remain in the same state.
-                    (other, _) => other,
-                };
-                next_cells.set(index, next_cell);
+    /**
+     * Put every cell in a random state of the current rule, each as likely,
+     * as cyclic rules start from, or at a random level under continuous
+     * rules such as Lenia.
+     */
+    pub fn reset_with_states(&mut self) {
+        let states = u64::from(self.rule.states());
+        for idx in 0..self.cells.len() {
+            if self.levels.is_empty() {
+                let state = (self.rng.next_u64() % states) as u8;
+                self.put_state(idx, state);
+            } else {
+                let level = self.rng.next_f64() as f32;
+                self.put_level(idx, level);
             }
         }
-
-        self.cells = next_cells;
+        self.background = false;
+        self.generation = 0;
     }
 
-    fn get_index(&self, row: u32, column: u32) -> usize {
-        (row * self.width + column) as usize
+    /**
+     * Returns how many times the universe has ticked since it was created,
+     * randomized or had its generation reset.
+     */
+    pub fn generation(&self) -> u64 {
+        self.generation
     }
 
-    fn live_neighbor_count(&self, row: u32, column: u32) -> u8 {
-        let mut count = 0;
-        for delta_row in [self.height - 1, 0, 1].iter().cloned() {
-            for delta_col in [self.width - 1, 0, 1].iter().cloned() {
-                if delta_row == 0 && delta_col == 0 {
-                    continue;
-                }
-                let neighbor_row = (row + delta_row) % self.height;
-                let neighbor_col = (column + delta_col) % self.width;
-                let index = self.get_index(neighbor_row, neighbor_col);
-                count += self.cells[index] as u8;
-            }
-        }
-        count
+    pub fn reset_generation(&mut self) {
+        self.generation = 0;
     }
+
+    /**
+     * Returns whether the specified cell is alive, failing when it lies
+     * outside of the universe.
+     */
+    pub fn get_cell(&self, row: u32, col: u32) -> Result<bool, JsError> {
+        Ok(self.cells[self.checked_index(row, col)?])
+    }
+
+    /**
+     * Set the specified cell to a fixed state.
+     */
+    pub fn set_cell(&mut self, row: u32, col: u32, alive: bool) -> Result<(), JsError> {
+        let idx = self.checked_index(row, col)?;
+        self.set_alive(idx, alive);
+        Ok(())
+    }
+
+    /**
+     * Randomize only the `width` x `height` region whose top left corner is
+     * (row, col), each cell being alive with probability `density`. The
+     * region wraps around the edges.
+     */
+    pub fn fill_random_region(&mut self, row: u32, col: u32, width: u32, height: u32, density: f64) -> Result<(), JsError> {
+        for idx in self.region_indices(row, col, width, height)? {
+            let alive = self.rng.next_f64() < density;
+            self.set_alive(idx, alive);
+        }
+        Ok(())
+    }
+
+    /**
+     * Put a `size` x `size` random soup centered on row, col, each cell being
+     * alive with probability `density`. The soup is made to look the same
+     * under every rotation and reflection of `symmetry`, C1 (none) by default.
+     */
+    pub fn put_random_soup(
+        &mut self,
+        row: u32,
+        col: u32,
+        size: u32,
+        density: f64,
+        symmetry: Option<Symmetry>,
+    ) -> Result<(), JsError> {
+        log!("putting {}x{} random soup on row {} col {}", size, size, row, col);
+        self.checked_index(row, col)?;
+        let transforms = symmetry.unwrap_or(Symmetry::C1).transforms();
+        let mut random = Pattern::new(size, size)?;
+        for soup_row in 0..size {
+            for soup_col in 0..size {
+                random.set(soup_row, soup_col, self.rng.next_f64() < density);
+            }
+        }
+        // Every cell copies the first cell of its orbit under the symmetry.
+        let mut soup = Pattern::blank(size, size);
+        for soup_row in 0..size {
+            for soup_col in 0..size {
+                let (first_row, first_col) = transforms
+                    .iter()
+                    .map(|transform| transform.map(soup_row, soup_col, size, size))
+                    .min()
+                    .unwrap_or((soup_row, soup_col));
+                soup.set(soup_row, soup_col, random.get(first_row, first_col));
+            }
+        }
+        self.write_pattern(&soup, row, col, Blend::Overwrite);
+        Ok(())
+    }
+
+    /**
+     * Kill every cell in the `width` x `height` region whose top left corner
+     * is (row, col), wrapping around the edges.
+     */
+    pub fn clear_region(&mut self, row: u32, col: u32, width: u32, height: u32) -> Result<(), JsError> {
+        for idx in self.region_indices(row, col, width, height)? {
+            self.set_alive(idx, false);
+        }
+        Ok(())
+    }
+
+    /**
+     * Flip the state of every cell, dying cells counting as dead.
+     */
+    pub fn invert(&mut self) {
+        self.states.iter_mut().for_each(|state| *state = 0);
+        self.sand.iter_mut().for_each(|grains| *grains = 0);
+        self.colors.iter_mut().for_each(|color| *color = 0);
+        self.levels.iter_mut().for_each(|level| *level = 1.0 - *level);
+        self.live = None;
+        self.counts = None;
+        let len = self.cells.len();
+        let blocks = self.cells.as_mut_slice();
+        for block in blocks.iter_mut() {
+            *block = !*block;
+        }
+        // Bits past the last cell must stay clear.
+        let tail = len % 32;
+        if let Some(last) = blocks.last_mut() {
+            if tail != 0 {
+                *last &= (1 << tail) - 1;
+            }
+        }
+        self.apply_mask();
+    }
+
+    /**
+     * Flip the state of every cell in the `width` x `height` region whose top
+     * left corner is (row, col), wrapping around the edges.
+     */
+    pub fn invert_region(&mut self, row: u32, col: u32, width: u32, height: u32) -> Result<(), JsError> {
+        for idx in self.region_indices(row, col, width, height)? {
+            self.toggle(idx);
+        }
+        Ok(())
+    }
+
+    /**
+     * Toggle specified cell value.
+     */
+    pub fn toggle_cell(&mut self, row: u32, col: u32) -> Result<(), JsError> {
+        log!("toggling cell on row {} col {}", row, col);
+        let idx = self.checked_index(row, col)?;
+        self.toggle(idx);
+        Ok(())
+    }
+
+    /**
+     * Toggle every cell in `indices` (as returned by `row * width + col`),
+     * in one call. Nothing is changed when any index is out of range.
+     */
+    pub fn toggle_cells(&mut self, indices: &[u32]) -> Result<(), JsError> {
+        for &idx in indices {
+            self.checked_index(idx / self.width.max(1), idx % self.width.max(1))?;
+        }
+        for &idx in indices {
+            self.toggle(idx as usize);
+        }
+        Ok(())
+    }
+
+    /**
+     * Set every cell in `coords`, given as flattened `row, col` pairs, to
+     * `alive`. Nothing is changed when any cell is out of range.
+     */
+    #[wasm_bindgen(js_name = set_cells)]
+    pub fn set_cells_js(&mut self, coords: &[u32], alive: bool) -> Result<(), JsError> {
+        let indices = coords
+            .chunks_exact(2)
+            .map(|pair| self.checked_index(pair[0], pair[1]))
+            .collect::<Result<Vec<usize>, Error>>()?;
+        for idx in indices {
+            self.set_alive(idx, alive);
+        }
+        Ok(())
+    }
+
+    /**
+     * Put a [Glider](https://en.wikipedia.org/wiki/Glider_(Conway%27s_Life)#Hacker_emblem) with center in row, col.
+     * Without a transform it travels down and to the right; rotating it
+     * aims it in the other diagonal directions.
+     */
+    pub fn put_glider(&mut self, row: u32, col: u32, transform: Option<Transform>) -> Result<(), JsError> {
+        log!("putting glidder on row {} col {}", row, col);
+        self.put_library_pattern("glider", row, col, transform, Blend::Overwrite)?;
+        Ok(())
+    }
+
+    pub fn put_pulsar(&mut self, row: u32, col: u32, transform: Option<Transform>) -> Result<(), JsError> {
+        log!("putting pulsar on row {} col {}", row, col);
+        self.put_library_pattern("pulsar", row, col, transform, Blend::Overwrite)?;
+        Ok(())
+    }
+
+    /**
+     * Put a [lightweight spaceship](https://conwaylife.com/wiki/Lightweight_spaceship)
+     * with center in row, col, heading west unless told otherwise.
+     */
+    pub fn put_lwss(&mut self, row: u32, col: u32, direction: Option<Direction>) -> Result<(), JsError> {
+        log!("putting lwss on row {} col {}", row, col);
+        self.put_moving_pattern("lwss", Direction::West, row, col, direction)?;
+        Ok(())
+    }
+
+    /**
+     * Put a [middleweight spaceship](https://conwaylife.com/wiki/Middleweight_spaceship)
+     * with center in row, col, heading west unless told otherwise.
+     */
+    pub fn put_mwss(&mut self, row: u32, col: u32, direction: Option<Direction>) -> Result<(), JsError> {
+        log!("putting mwss on row {} col {}", row, col);
+        self.put_moving_pattern("mwss", Direction::West, row, col, direction)?;
+        Ok(())
+    }
+
+    /**
+     * Put a [heavyweight spaceship](https://conwaylife.com/wiki/Heavyweight_spaceship)
+     * with center in row, col, heading west unless told otherwise.
+     */
+    pub fn put_hwss(&mut self, row: u32, col: u32, direction: Option<Direction>) -> Result<(), JsError> {
+        log!("putting hwss on row {} col {}", row, col);
+        self.put_moving_pattern("hwss", Direction::West, row, col, direction)?;
+        Ok(())
+    }
+
+    /**
+     * Put a [Gosper glider gun](https://conwaylife.com/wiki/Gosper_glider_gun)
+     * with center in row, col, firing a stream of gliders diagonally in
+     * `direction` (south east by default).
+     */
+    pub fn put_gosper_gun(&mut self, row: u32, col: u32, direction: Option<Direction>) -> Result<(), JsError> {
+        log!("putting gosper gun on row {} col {}", row, col);
+        self.put_moving_pattern("gosper-glider-gun", Direction::SouthEast, row, col, direction)?;
+        Ok(())
+    }
+
+    /**
+     * Put a pattern from the built-in library, as named by `list_patterns`,
+     * with center in row, col and optionally reoriented. Dead cells of the
+     * pattern overwrite live ones unless another `blend` is given.
+     */
+    pub fn put_pattern(
+        &mut self,
+        name: &str,
+        row: u32,
+        col: u32,
+        transform: Option<Transform>,
+        blend: Option<Blend>,
+    ) -> Result<(), JsError> {
+        log!("putting {} on row {} col {}", name, row, col);
+        self.put_library_pattern(name, row, col, transform, blend.unwrap_or(Blend::Overwrite))?;
+        Ok(())
+    }
+
+    /**
+     * Returns the names of every pattern in the built-in library.
+     */
+    pub fn list_patterns() -> Vec<String> {
+        patterns::LIBRARY.iter().map(|pattern| pattern.name.to_string()).collect()
+    }
+
+    /**
+     * Search the built-in library by name or category ("still life",
+     * "oscillator", "spaceship", "methuselah" or "gun"), ignoring case.
+     * Returns a JSON array such as
+     * `[{"name":"glider","size":{"width":3,"height":3},"period":4,"category":"spaceship"}]`,
+     * where `period` is `null` for patterns that never repeat.
+     */
+    pub fn search_patterns(query: &str) -> String {
+        patterns::search(query)
+    }
+
+    /**
+     * Change the size of the universe keeping its live cells, cropping or
+     * padding with dead cells around `anchor`.
+     */
+    pub fn resize(&mut self, width: u32, height: u32, anchor: Anchor) {
+        let (delta_row, delta_col) = anchor.offset((self.height, self.width), (height, width));
+        self.remap(width, height, |row, col| {
+            let new_row = row as i64 + delta_row;
+            let new_col = col as i64 + delta_col;
+            if new_row < 0 || new_col < 0 {
+                return None;
+            }
+            Some((new_row as u32, new_col as u32))
+        });
+    }
+
+    /**
+     * Shrink the universe to the bounding box of its live cells, keeping
+     * `padding` dead cells on every side. Empty universes are left untouched.
+     */
+    pub fn crop_to_bounding_box(&mut self, padding: u32) {
+        if let Some((row, col, width, height)) = self.bounding_box() {
+            // Dying states, ants and the like can lie above or left of the
+            // live cells, beyond the padding.
+            self.remap(width + 2 * padding, height + 2 * padding, |r, c| {
+                Some(((r + padding).checked_sub(row)?, (c + padding).checked_sub(col)?))
+            });
+        }
+    }
+
+    /**
+     * Move every live cell `dx` columns right and `dy` rows down, wrapping
+     * around the edges.
+     */
+    pub fn shift(&mut self, dx: i32, dy: i32) {
+        let (width, height) = (self.width, self.height);
+        self.remap(width, height, |row, col| {
+            let new_row = (row as i64 + dy as i64).rem_euclid(height as i64);
+            let new_col = (col as i64 + dx as i64).rem_euclid(width as i64);
+            Some((new_row as u32, new_col as u32))
+        });
+    }
+
+    /**
+     * Rotate the universe a quarter turn clockwise, swapping its width and
+     * height.
+     */
+    pub fn rotate_cw(&mut self) {
+        let (width, height) = (self.width, self.height);
+        self.remap(height, width, |row, col| Some((col, height - 1 - row)));
+        self.transform_ants(Transform::Rotate90);
+    }
+
+    /**
+     * Rotate the universe a quarter turn counterclockwise, swapping its width
+     * and height.
+     */
+    pub fn rotate_ccw(&mut self) {
+        let (width, height) = (self.width, self.height);
+        self.remap(height, width, |row, col| Some((width - 1 - col, row)));
+        self.transform_ants(Transform::Rotate270);
+    }
+
+    /**
+     * Mirror the universe left to right.
+     */
+    pub fn flip_horizontal(&mut self) {
+        let (width, height) = (self.width, self.height);
+        self.remap(width, height, |row, col| Some((row, width - 1 - col)));
+        self.transform_ants(Transform::Flip);
+    }
+
+    /**
+     * Mirror the universe top to bottom.
+     */
+    pub fn flip_vertical(&mut self) {
+        let (width, height) = (self.width, self.height);
+        self.remap(width, height, |row, col| Some((height - 1 - row, col)));
+        self.transform_ants(Transform::FlipRotate180);
+    }
+
+    /**
+     * Returns an independent copy of the universe.
+     */
+    #[wasm_bindgen(js_name = clone)]
+    pub fn snapshot(&self) -> Universe {
+        self.clone()
+    }
+
+    /**
+     * Returns whether both universes have the same size and live cells.
+     */
+    pub fn equals(&self, other: &Universe) -> bool {
+        self == other
+    }
+
+    /**
+     * Write every cell of a parsed pattern with its center in row, col,
+     * wrapping around the edges. Dead cells of the pattern overwrite live
+     * ones unless another `blend` is given. Parse a pattern once and stamp it
+     * as many times as needed.
+     */
+    pub fn stamp(&mut self, pattern: &Pattern, row: u32, col: u32, blend: Option<Blend>) -> Result<(), JsError> {
+        self.checked_index(row, col)?;
+        self.write_pattern(pattern, row, col, blend.unwrap_or(Blend::Overwrite));
+        Ok(())
+    }
+
+    /**
+     * Parse a [Run Length Encoded](https://conwaylife.com/wiki/Run_Length_Encoded)
+     * pattern, as found on LifeWiki or exported by Golly, and stamp it
+     * centered on row, col, blended as by `stamp`.
+     */
+    pub fn put_rle(&mut self, rle: &str, row: u32, col: u32, blend: Option<Blend>) -> Result<(), JsError> {
+        log!("putting rle pattern on row {} col {}", row, col);
+        self.checked_index(row, col)?;
+        let pattern = rle::parse(rle)?;
+        self.write_pattern(&pattern, row, col, blend.unwrap_or(Blend::Overwrite));
+        Ok(())
+    }
+
+    /**
+     * Encode the whole universe as an RLE pattern.
+     */
+    pub fn to_rle(&self) -> String {
+        let pattern = self
+            .region_pattern(0, 0, self.width, self.height)
+            .unwrap_or_else(|_| Pattern::blank(0, 0));
+        rle::encode(&pattern, &self.rule.to_string())
+    }
+
+    /**
+     * Encode the `width` x `height` region whose top left corner is (row, col)
+     * as an RLE pattern, wrapping around the edges.
+     */
+    pub fn region_to_rle(&self, row: u32, col: u32, width: u32, height: u32) -> Result<String, JsError> {
+        let pattern = self.region_pattern(row, col, width, height)?;
+        Ok(rle::encode(&pattern, &self.rule.to_string()))
+    }
+
+    /**
+     * Copy the live cells of the `width` x `height` selection whose top left
+     * corner is (row, col) into a pattern cropped to their bounding box, for
+     * use with `Pattern.to_rle`, `width` and `height`. The selection wraps
+     * around the edges like `region_to_rle`.
+     */
+    pub fn export_region(&self, row: u32, col: u32, width: u32, height: u32) -> Result<Pattern, JsError> {
+        Ok(self.region_pattern(row, col, width, height)?.cropped())
+    }
+
+    /**
+     * Parse a [plaintext](https://conwaylife.com/wiki/Plaintext) `.cells`
+     * pattern and stamp it centered on row, col, blended as by `stamp`.
+     */
+    pub fn put_plaintext(&mut self, text: &str, row: u32, col: u32, blend: Option<Blend>) -> Result<(), JsError> {
+        log!("putting plaintext pattern on row {} col {}", row, col);
+        self.checked_index(row, col)?;
+        let pattern = plaintext::parse(text)?;
+        self.write_pattern(&pattern, row, col, blend.unwrap_or(Blend::Overwrite));
+        Ok(())
+    }
+
+    /**
+     * Encode the whole universe as a plaintext `.cells` pattern.
+     */
+    pub fn to_plaintext(&self) -> String {
+        let pattern = self
+            .region_pattern(0, 0, self.width, self.height)
+            .unwrap_or_else(|_| Pattern::blank(0, 0));
+        plaintext::encode(&pattern)
+    }
+
+    /**
+     * Encode the `width` x `height` region whose top left corner is (row, col)
+     * as a plaintext `.cells` pattern, wrapping around the edges.
+     */
+    pub fn region_to_plaintext(&self, row: u32, col: u32, width: u32, height: u32) -> Result<String, JsError> {
+        let pattern = self.region_pattern(row, col, width, height)?;
+        Ok(plaintext::encode(&pattern))
+    }
+
+    /**
+     * Parse a Life 1.05 or Life 1.06 pattern and stamp it centered on
+     * row, col, blended as by `stamp`.
+     */
+    pub fn put_life(&mut self, text: &str, row: u32, col: u32, blend: Option<Blend>) -> Result<(), JsError> {
+        log!("putting life pattern on row {} col {}", row, col);
+        self.checked_index(row, col)?;
+        let pattern = lif::parse(text)?;
+        self.write_pattern(&pattern, row, col, blend.unwrap_or(Blend::Overwrite));
+        Ok(())
+    }
+
+    /**
+     * Encode the live cells of the universe in the Life 1.06 format, using
+     * their columns and rows as coordinates.
+     */
+    pub fn to_life_106(&self) -> String {
+        let pattern = self
+            .region_pattern(0, 0, self.width, self.height)
+            .unwrap_or_else(|_| Pattern::blank(0, 0));
+        lif::encode_106(&pattern)
+    }
+
+    /**
+     * Encode the whole universe in the Life 1.05 format.
+     */
+    pub fn to_life_105(&self) -> String {
+        let pattern = self
+            .region_pattern(0, 0, self.width, self.height)
+            .unwrap_or_else(|_| Pattern::blank(0, 0));
+        lif::encode_105(&pattern)
+    }
+
+    /**
+     * Load a Golly [macrocell](https://golly.sourceforge.io/Help/formats.html#mc)
+     * pattern with the center of its live cells on row, col. Unlike the other
+     * formats the pattern does not wrap around the edges: whatever falls
+     * outside of the universe is cropped, so huge patterns can be loaded
+     * without expanding them in memory.
+     */
+    pub fn put_macrocell(&mut self, text: &str, row: u32, col: u32) -> Result<(), JsError> {
+        log!("putting macrocell pattern on row {} col {}", row, col);
+        self.checked_index(row, col)?;
+        let macrocell = Macrocell::parse(text)?;
+        let (top, left, bottom, right) = match macrocell.bounding_box() {
+            Some(bounds) => bounds,
+            None => return Ok(()),
+        };
+
+        // Pattern coordinates of the universe's top left corner.
+        let origin_row = top + (bottom - top + 1) / 2 - row as i64;
+        let origin_col = left + (right - left + 1) / 2 - col as i64;
+        let window = (
+            top.max(origin_row),
+            left.max(origin_col),
+            bottom.min(origin_row + self.height as i64 - 1),
+            right.min(origin_col + self.width as i64 - 1),
+        );
+        for pattern_row in window.0..=window.2 {
+            let start = self.get_index((pattern_row - origin_row) as u32, (window.1 - origin_col) as u32);
+            let end = start + (window.3 - window.1 + 1).max(0) as usize;
+            self.cells.set_range(start..end, false);
+            if let Some(states) = self.states.get_mut(start..end) {
+                states.iter_mut().for_each(|state| *state = 0);
+            }
+            if let Some(sand) = self.sand.get_mut(start..end) {
+                sand.iter_mut().for_each(|grains| *grains = 0);
+            }
+            if let Some(levels) = self.levels.get_mut(start..end) {
+                levels.iter_mut().for_each(|level| *level = 0.0);
+            }
+            if let Some(colors) = self.colors.get_mut(start..end) {
+                colors.iter_mut().for_each(|color| *color = 0);
+            }
+        }
+        self.live = None;
+        self.counts = None;
+        let width = self.width as i64;
+        macrocell.for_each_live(window, |pattern_row, pattern_col| {
+            let idx = (pattern_row - origin_row) * width + pattern_col - origin_col;
+            self.set_alive(idx as usize, true);
+        });
+        Ok(())
+    }
+
+    /**
+     * Encode the universe as a Golly macrocell file, which stays compact for
+     * huge but sparse universes.
+     */
+    pub fn to_macrocell(&self) -> String {
+        macrocell::encode(self.width, self.height, &self.rule.to_string(), |row, col| {
+            self.cells[self.get_index(row, col)]
+        })
+    }
+
+    /**
+     * Returns what lies beyond the edges of the universe.
+     */
+    pub fn boundary(&self) -> Boundary {
+        self.boundary
+    }
+
+    /**
+     * Change what lies beyond the edges of the universe, keeping the cells.
+     */
+    pub fn set_boundary(&mut self, boundary: Boundary) {
+        self.boundary = boundary;
+    }
+
+    /**
+     * Shear the torus: cells crossing its right edge come back on the left
+     * `rows` rows further down, and those crossing its left edge as many
+     * rows further up, so that spaceships flying sideways cover the whole
+     * grid. Only a torus shifts; 0, the default, is a plain one.
+     */
+    pub fn set_torus_shift(&mut self, rows: i32) {
+        self.torus_shift = rows;
+    }
+
+    pub fn torus_shift(&self) -> i32 {
+        self.torus_shift
+    }
+
+    /**
+     * Returns the width of the universe.
+     */
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /**
+     * Returns the height of the universe.
+     */
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /**
+     * Returns a pointer to the universe's cells in wasm memory.
+     */
+    pub fn cells(&self) -> *const u32 {
+        self.cells.as_slice().as_ptr()
+    }
+
+    /**
+     * Returns the live cells as flattened `row, col` pairs, row by row, so
+     * that drawing a sparse universe takes as long as it has live cells
+     * rather than cells.
+     *
+     * ```js
+     * const live = universe.live_cells();
+     * for (let i = 0; i < live.length; i += 2) {
+     *   context.fillRect(live[i + 1] * size, live[i] * size, size, size);
+     * }
+     * ```
+     */
+    pub fn live_cells(&self) -> Vec<u32> {
+        match &self.live {
+            Some(live) => {
+                let mut cells: Vec<(u32, u32)> = live.iter().cloned().collect();
+                cells.sort_unstable();
+                cells.into_iter().flat_map(|(row, col)| [row, col]).collect()
+            }
+            None => {
+                let width = self.width as usize;
+                self.cells.ones().flat_map(|idx| [(idx / width) as u32, (idx % width) as u32]).collect()
+            }
+        }
+    }
+
+    /**
+     * Returns the cells of the `width` by `height` window from (row, col)
+     * packed into 32 bit words, every row of the window starting a new word
+     * with its first column in the lowest bit. Cells of the window outside
+     * the universe are dead. Only the window is copied, so that rendering a
+     * small part of a huge grid costs little.
+     */
+    pub fn cells_in_rect(&self, row: u32, col: u32, width: u32, height: u32) -> Vec<u32> {
+        let words = width.div_ceil(32) as usize;
+        let mut cells = Vec::with_capacity(words * height as usize);
+        for window_row in row..row.saturating_add(height) {
+            let start = cells.len();
+            if let Some(visible) = self.window_row(window_row, col, width) {
+                cells.extend(visible.words());
+            }
+            cells.resize(start + words, 0);
+        }
+        cells
+    }
+
+    /**
+     * Returns the live cells of the `width` by `height` window from
+     * (row, col) as flattened `row, col` pairs, row by row.
+     */
+    pub fn live_cells_in_rect(&self, row: u32, col: u32, width: u32, height: u32) -> Vec<u32> {
+        let mut coords = Vec::new();
+        for window_row in row..row.saturating_add(height) {
+            if let Some(visible) = self.window_row(window_row, col, width) {
+                for offset in visible.live_columns() {
+                    coords.extend_from_slice(&[window_row, col + offset]);
+                }
+            }
+        }
+        coords
+    }
+
+    /**
+     * Returns a string representation of the universe.
+     */
+    pub fn render(&self) -> String {
+        self.to_string()
+    }
+
+    /**
+     * Choose the characters `render` uses for alive and dead cells, and
+     * optionally for the dying cells of Generations rules.
+     */
+    pub fn set_render_glyphs(&mut self, alive: char, dead: char, dying: Option<char>) {
+        self.style.alive = alive;
+        self.style.dead = dead;
+        if let Some(dying) = dying {
+            self.style.dying = dying;
+        }
+    }
+
+    /**
+     * Choose the text `render` writes between rows, a newline by default.
+     */
+    pub fn set_row_separator(&mut self, separator: &str) {
+        self.style.row_separator = separator.to_string();
+    }
+
+    /**
+     * Choose whether `render` starts and ends its output with a newline.
+     */
+    pub fn set_render_newlines(&mut self, leading: bool, trailing: bool) {
+        self.style.leading_newline = leading;
+        self.style.trailing_newline = trailing;
+    }
+
+    /**
+     * Switch to another rule, given in B/S notation such as `B36/S23`, as
+     * an elementary rule such as `W30`, or by the name of a preset from
+     * `list_rules`, like `HighLife`. Rules separated by `|`, as in
+     * `B3/S23|B36/S23`, take turns: even generations step by the first and
+     * odd ones by the second.
+     */
+    pub fn set_rule(&mut self, rulestring: &str) -> Result<(), JsError> {
+        self.rule = rulestring.parse()?;
+        self.morph = None;
+        self.fit_regions();
+        self.fit_states();
+        Ok(())
+    }
+
+    /**
+     * Replace the rule by a JavaScript function called as
+     * `callback(state, neighbors)` for every cell on each tick, where a
+     * live cell has state 1 and `neighbors` is the number of live cells in
+     * the neighborhood. The function returns the next state, as a number or
+     * as `true` for alive. Passing nothing goes back to the rule.
+     *
+     * This is a slow path meant for trying out rules on small grids: every
+     * cell costs a call into JavaScript, where rulestrings run in
+     * WebAssembly. If the function throws or returns anything else, the tick
+     * leaves the cells as they were.
+     */
+    pub fn set_rule_callback(&mut self, callback: Option<js_sys::Function>) {
+        if callback.is_some() {
+            log!("Rule callback set: every tick now calls into JavaScript for each of the {} cells", self.cells.len());
+        }
+        self.rule_callback = callback;
+    }
+
+    /**
+     * Switch to a rule given as the text of a Golly `.rule` file with a
+     * `@TABLE` section, or of an older `.table` file, using the Moore or von
+     * Neumann neighborhood. Colors from a `@COLORS` section are used by
+     * `pixels`, and `rule` returns the name from the `@RULE` line. Rule
+     * tables always use their own neighborhood, even after
+     * `set_neighborhood`.
+     */
+    pub fn set_rule_table(&mut self, text: &str) -> Result<(), JsError> {
+        self.rule = Rule::from_table(text)?;
+        self.morph = None;
+        self.fit_regions();
+        self.fit_states();
+        Ok(())
+    }
+
+    /**
+     * Switch from rule `from` to rule `to` gradually over `over_generations`
+     * ticks. On each of them every cell follows `to` with a chance growing
+     * steadily up to certainty on the last one, and `from` otherwise, using
+     * the universe's random number generator. `rule` returns `to` from the
+     * start. Both rules must count the same neighbors and have the same
+     * number of states, as `Seeds` and `Conway` do.
+     */
+    pub fn morph_rule(&mut self, from: &str, to: &str, over_generations: u32) -> Result<(), JsError> {
+        let (from, to): (Rule, Rule) = (from.parse()?, to.parse()?);
+        if !from.blends_with(&to) {
+            return Err(Error::IncompatibleRules {
+                from: from.to_string(),
+                to: to.to_string(),
+            }
+            .into());
+        }
+        log!("Morphing from {} to {} over {} generations", from, to, over_generations);
+        self.rule = to;
+        self.morph = Some(Morph {
+            from,
+            generations: over_generations,
+            elapsed: 0,
+        })
+        .filter(|_| over_generations > 0);
+        self.fit_regions();
+        self.fit_states();
+        Ok(())
+    }
+
+    /**
+     * Run `rulestring` instead of the universe's rule in the rectangle of
+     * `height` rows and `width` columns whose top left cell is at (row,
+     * col), cut off at the grid's edges. Regions set later take precedence
+     * where they overlap earlier ones, and stay where they are on the grid
+     * when it is resized or transformed. The rule must count the same
+     * neighbors as the universe's rule and have as many states, as
+     * `HighLife` does next to `Conway`; setting an incompatible universe
+     * rule later drops the region.
+     */
+    pub fn set_region_rule(&mut self, row: u32, col: u32, height: u32, width: u32, rulestring: &str) -> Result<(), JsError> {
+        self.checked_index(row, col)?;
+        let rule: Rule = rulestring.parse()?;
+        if !rule.blends_with(&self.rule) {
+            return Err(Error::IncompatibleRules {
+                from: self.rule.to_string(),
+                to: rule.to_string(),
+            }
+            .into());
+        }
+        self.regions.push(Region {
+            row,
+            col,
+            height: height.min(self.height - row),
+            width: width.min(self.width - col),
+            rule,
+        });
+        Ok(())
+    }
+
+    /**
+     * Go back to running the universe's rule everywhere.
+     */
+    pub fn clear_region_rules(&mut self) {
+        self.regions.clear();
+    }
+
+    /**
+     * Choose how cells near the edge of a region see the cells across it:
+     * as their usual neighbors, the default, or when `isolated` as empty
+     * space, so that each region runs as a universe of its own with dead
+     * boundaries. Cells outside every region form one region together.
+     */
+    pub fn set_isolated_regions(&mut self, isolated: bool) {
+        self.isolated_regions = isolated;
+    }
+
+    /**
+     * The rule the cell at (row, col) runs, its region's or the universe's.
+     */
+    pub fn region_rule(&self, row: u32, col: u32) -> Result<String, JsError> {
+        let index = self.checked_index(row, col)?;
+        let rule = match self.region_tags().get(index) {
+            Some(&tag) if tag > 0 => &self.regions[tag - 1].rule,
+            _ => &self.rule,
+        };
+        Ok(rule.to_string())
+    }
+
+    /**
+     * Returns the current rule in B/S notation, `B3/S23` unless changed.
+     */
+    pub fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    /**
+     * Returns the number of cell states of the current rule: 2 for Life-like
+     * rules, more for Generations rules, or the number of colors of the ant
+     * rule when that is larger.
+     */
+    pub fn state_count(&self) -> u8 {
+        self.rule.states().max(self.ant_colors())
+    }
+
+    /**
+     * Returns the state of the specified cell: 0 when dead, 1 when alive and
+     * from 2 up to `state_count() - 1` while dying under a Generations rule.
+     * Under Wireworld 0 is empty, 1 an electron head, 2 an electron tail and
+     * 3 a conductor, under the forest-fire model 0 is empty, 1 a tree and 2
+     * fire, under an SIR epidemic 0 is susceptible, 1 infected and 2 and up
+     * recovered, under Immigration and QuadLife it is 1 and up by the color
+     * of a live cell, and under the sandpile it is the number of grains of
+     * sand, 4 standing for 4 or more.
+     */
+    pub fn get_state(&self, row: u32, col: u32) -> Result<u8, JsError> {
+        Ok(self.state(self.checked_index(row, col)?))
+    }
+
+    /**
+     * Set the specified cell to a state as returned by `get_state`. States
+     * the current rule does not have are clamped to its last one.
+     */
+    pub fn set_state(&mut self, row: u32, col: u32, state: u8) -> Result<(), JsError> {
+        let idx = self.checked_index(row, col)?;
+        self.put_state(idx, state);
+        Ok(())
+    }
+
+    /**
+     * Drop `grains` grains of sand on the cell at (row, col). Only
+     * available under the sandpile rule.
+     */
+    pub fn drop_sand(&mut self, row: u32, col: u32, grains: u32) -> Result<(), JsError> {
+        self.expect_rule(Rule::SANDPILE)?;
+        let idx = self.checked_index(row, col)?;
+        log!("dropping {} grains of sand on row {} col {}", grains, row, col);
+        self.put_grains(idx, self.grains(idx).saturating_add(grains));
+        Ok(())
+    }
+
+    /**
+     * Returns the grains of sand on the cell at (row, col) under the
+     * sandpile rule, which `get_state` gives as 4 from 4 grains on.
+     */
+    pub fn get_grains(&self, row: u32, col: u32) -> Result<u32, JsError> {
+        self.expect_rule(Rule::SANDPILE)?;
+        Ok(self.grains(self.checked_index(row, col)?))
+    }
+
+    /**
+     * Lay a straight wire of conductor from (row0, col0) to (row1, col1),
+     * both ends included. Only available under the Wireworld rule.
+     */
+    pub fn draw_wire(&mut self, row0: u32, col0: u32, row1: u32, col1: u32) -> Result<(), JsError> {
+        self.expect_rule(Rule::WIREWORLD)?;
+        self.checked_index(row0, col0)?;
+        self.checked_index(row1, col1)?;
+        log!("drawing wire from row {} col {} to row {} col {}", row0, col0, row1, col1);
+        let (row1, col1) = (i64::from(row1), i64::from(col1));
+        let (mut row, mut col) = (i64::from(row0), i64::from(col0));
+        let (delta_row, delta_col) = ((row1 - row).abs(), -(col1 - col).abs());
+        let (step_row, step_col) = ((row1 - row).signum(), (col1 - col).signum());
+        let mut error = delta_row + delta_col;
+        loop {
+            let idx = self.get_index(row as u32, col as u32);
+            self.put_state(idx, WIREWORLD_CONDUCTOR);
+            if row == row1 && col == col1 {
+                return Ok(());
+            }
+            let doubled = 2 * error;
+            if doubled >= delta_col {
+                error += delta_col;
+                row += step_row;
+            }
+            if doubled <= delta_row {
+                error += delta_row;
+                col += step_col;
+            }
+        }
+    }
+
+    /**
+     * Send an electron along a wire: its head goes on (head_row, head_col)
+     * and its tail on (tail_row, tail_col), the cell it came from, so it
+     * travels away from the tail. Only available under the Wireworld rule.
+     */
+    pub fn inject_electron(&mut self, head_row: u32, head_col: u32, tail_row: u32, tail_col: u32) -> Result<(), JsError> {
+        self.expect_rule(Rule::WIREWORLD)?;
+        let head = self.checked_index(head_row, head_col)?;
+        let tail = self.checked_index(tail_row, tail_col)?;
+        log!("injecting electron on row {} col {}", head_row, head_col);
+        self.put_state(tail, WIREWORLD_TAIL);
+        self.put_state(head, WIREWORLD_HEAD);
+        Ok(())
+    }
+
+    /**
+     * Returns the level of the specified cell, from 0 to 1, under a
+     * continuous rule such as Lenia, where `get_state` gives 1 for levels
+     * from one half up. Other rules give 0 or 1 by whether the cell is
+     * alive.
+     */
+    pub fn get_level(&self, row: u32, col: u32) -> Result<f32, JsError> {
+        let idx = self.checked_index(row, col)?;
+        Ok(self.levels.get(idx).cloned().unwrap_or(self.cells[idx] as u8 as f32))
+    }
+
+    /**
+     * Set the specified cell to a level from 0 to 1 under a continuous rule
+     * such as Lenia, or under other rules bring it to life from one half up.
+     */
+    pub fn set_level(&mut self, row: u32, col: u32, level: f32) -> Result<(), JsError> {
+        let idx = self.checked_index(row, col)?;
+        if self.levels.is_empty() {
+            self.set_alive(idx, level >= 0.5);
+        } else {
+            self.put_level(idx, level);
+        }
+        Ok(())
+    }
+
+    /**
+     * Returns the level of every cell in row major order, as described by
+     * `get_level`.
+     */
+    pub fn levels(&self) -> Vec<f32> {
+        (0..self.cells.len())
+            .map(|idx| self.levels.get(idx).cloned().unwrap_or(self.cells[idx] as u8 as f32))
+            .collect()
+    }
+
+    /**
+     * Returns the state of every cell, one byte each in row major order, as
+     * described by `get_state`.
+     */
+    pub fn states(&self) -> Vec<u8> {
+        (0..self.cells.len()).map(|idx| self.state(idx)).collect()
+    }
+
+    /**
+     * Returns how many cells are in each state, from 0 up to
+     * `state_count() - 1`. Under an SIR epidemic these are the susceptible
+     * and infected cells followed by the recovered ones, by how long they
+     * have been immune.
+     */
+    pub fn state_counts(&self) -> Vec<u32> {
+        let mut counts = vec![0; usize::from(self.state_count())];
+        for idx in 0..self.cells.len() {
+            counts[usize::from(self.state(idx))] += 1;
+        }
+        counts
+    }
+
+    /**
+     * Returns how many cells the rule brought to state 1 on the last tick:
+     * births under Life-like rules and new infections under an SIR
+     * epidemic. Ants and noise are not counted.
+     */
+    pub fn births(&self) -> u32 {
+        self.births
+    }
+
+    /**
+     * Returns how many cells the rule took out of state 1 on the last tick,
+     * counted like `births`.
+     */
+    pub fn deaths(&self) -> u32 {
+        self.deaths
+    }
+
+    /**
+     * Returns an RGBA image of the universe with one pixel per cell, ready
+     * for `ImageData`. Live cells are black on white, dying cells fade to
+     * white, Brian's Brain shows firing cells white and refractory ones blue
+     * on black, and Wireworld shows yellow wires carrying electrons with a
+     * blue head and a red tail on black. The forest-fire model shows green
+     * trees and orange fire on brown ground, the sandpile shows 1 to 3
+     * grains blue, yellow and red on navy with toppling cells white, and an
+     * SIR epidemic shows red infected and blue recovered cells among pale
+     * susceptible ones. Immigration and QuadLife show their live cells in
+     * black, red, blue and green by color. Cyclic rules spread their states around the color
+     * wheel, and continuous rules such as Lenia shade cells from white to
+     * black by level. Ant colors beyond the rule's states get colors of
+     * their own.
+     */
+    pub fn pixels(&self) -> Vec<u8> {
+        let two_state = self.levels.is_empty() && self.states.is_empty() && self.mask.is_none();
+        if two_state && self.rule.at(self.generation).is_life_like() {
+            return render::two_state_rgba(&self.cells);
+        }
+        let mut pixels = Vec::with_capacity(self.cells.len() * 4);
+        for idx in 0..self.cells.len() {
+            match self.levels.get(idx) {
+                _ if !self.exists(idx) => pixels.extend_from_slice(&render::MISSING_RGBA),
+                Some(&level) => pixels.extend_from_slice(&render::level_rgba(level)),
+                None => pixels.extend_from_slice(&render::state_rgba(self.rule.at(self.generation), self.state(idx))),
+            }
+        }
+        pixels
+    }
+
+    /**
+     * Returns whether empty space is currently alive. Rules with B0 bring
+     * every cell without live neighbors to life, so empty space strobes on
+     * odd generations unless the rule also has S8, in which case it stays
+     * alive. A display keeping the background steady inverts the cells while
+     * this is set; with a dead boundary the cells beyond the edges are
+     * counted in this state, while with an alive one they are always alive.
+     */
+    pub fn background(&self) -> bool {
+        self.background
+    }
+
+    /**
+     * Choose how two state B/S rules on the Moore neighborhood step, which
+     * changes how fast but not what they give.
+     */
+    pub fn set_kernel(&mut self, kernel: Kernel) {
+        self.kernel = kernel;
+    }
+
+    pub fn kernel(&self) -> Kernel {
+        self.kernel
+    }
+
+    /**
+     * Start `count` web workers to step big grids on, resolving once they
+     * are up. Until then ticks stay on one thread.
+     *
+     * Needs a build with the `threads` feature for wasm32 with the `atomics`
+     * and `bulk-memory` target features, and a page served cross-origin
+     * isolated, with the `Cross-Origin-Opener-Policy: same-origin` and
+     * `Cross-Origin-Embedder-Policy: require-corp` headers. Throws naming
+     * what is missing otherwise.
+     *
+     * ```js
+     * await Universe.start_threads(navigator.hardwareConcurrency);
+     * ```
+     */
+    pub fn start_threads(count: u32) -> Result<js_sys::Promise, JsError> {
+        Ok(threads::start(count)?)
+    }
+
+    /// The lookup table of the rule with the birth and survival bitmasks
+    /// `masks`, built unless it was the last one asked for.
+    fn life_table(&mut self, masks: (u16, u16)) -> Rc<LifeTable> {
+        match &self.life_table {
+            Some(table) if table.masks() == masks => Rc::clone(table),
+            _ => Rc::clone(self.life_table.insert(Rc::new(LifeTable::new(masks)))),
+        }
+    }
+
+    /**
+     * Count the cells at `offsets`, given as flattened `delta_row, delta_col`
+     * pairs, as the neighbors of every cell instead of the rule's own
+     * neighborhood, such as the 8 knight moves away. The neighborhood stays
+     * when the rule changes; an empty list goes back to the rule's. B/S
+     * rulestrings stop at 8 neighbors, so births and survivals in larger
+     * neighborhoods can not ask for more.
+     */
+    pub fn set_neighborhood(&mut self, offsets: &[i32]) -> Result<(), JsError> {
+        let offsets: Vec<(i32, i32)> = offsets.chunks_exact(2).map(|pair| (pair[0], pair[1])).collect();
+        if offsets.len() > usize::from(u8::MAX) {
+            return Err(Error::NeighborhoodSize(offsets.len()).into());
+        }
+        self.neighborhood = if offsets.is_empty() { None } else { Some(offsets) };
+        Ok(())
+    }
+
+    /**
+     * Returns the center of the specified cell as `[x, y]`, in cell widths
+     * from the top left corner of the grid. Cells are unit squares, except
+     * under hexagonal rules like `B2/S34H` where they are hexagons one unit
+     * across, pointing up, with odd rows shifted half a cell right. Tori of
+     * hexagons need an even height for the shift to line up.
+     */
+    pub fn cell_center(&self, row: u32, col: u32) -> Vec<f64> {
+        let shift = if row % 2 == 1 { 0.5 } else { 0.0 };
+        let (row, col) = (f64::from(row), f64::from(col));
+        match self.rule.at(self.generation).neighborhood() {
+            Neighborhood::Hexagonal if self.neighborhood.is_none() => {
+                // Rows of hexagons one unit across are sqrt(3) / 2 apart,
+                // and each hexagon is 2 / sqrt(3) tall.
+                vec![col + 0.5 + shift, row * 3f64.sqrt() / 2.0 + 1.0 / 3f64.sqrt()]
+            }
+            _ => vec![col + 0.5, row + 0.5],
+        }
+    }
+
+    /**
+     * Returns the name of the preset matching the current rule, if any.
+     */
+    pub fn rule_name(&self) -> Option<String> {
+        self.rule.preset_name().map(str::to_string)
+    }
+
+    /**
+     * Returns the names of every rule preset accepted by `set_rule`.
+     */
+    pub fn list_rules() -> Vec<String> {
+        rule::PRESETS.iter().map(|&(name, _)| name.to_string()).collect()
+    }
+
+    /**
+     * Put a Langton's ant on the specified cell, facing `heading` or north.
+     * Every tick, after the cells have been updated, each ant turns by the
+     * color of its cell as given by `set_ant_rule`, recolors the cell and
+     * steps forward. Diagonal headings make ants move diagonally.
+     */
+    pub fn add_ant(&mut self, row: u32, col: u32, heading: Option<Direction>) -> Result<(), JsError> {
+        self.checked_index(row, col)?;
+        log!("putting ant on row {} col {}", row, col);
+        self.ants.push(Ant {
+            row,
+            col,
+            heading: heading.unwrap_or(Direction::North),
+            state: 0,
+            rule: None,
+        });
+        Ok(())
+    }
+
+    /**
+     * Put a turmite on the specified cell, facing `heading` or north. A
+     * turmite is an ant with states of its own, following its own `rule`
+     * rather than the one from `set_ant_rule`: a JSON transition table such
+     * as `[[[1, 2, 0], [0, 8, 0]]]`, listing for each state and then each
+     * cell color the color to leave behind, the turn (1 for none, 2 right, 4
+     * around, 8 left, as in Golly) and the next state. Turmites start in
+     * state 0. Turn letters such as `LLRR` are accepted too.
+     */
+    pub fn add_turmite(&mut self, row: u32, col: u32, rule: &str, heading: Option<Direction>) -> Result<(), JsError> {
+        self.checked_index(row, col)?;
+        let rule: AntRule = rule.parse()?;
+        log!("putting turmite on row {} col {}", row, col);
+        self.ants.push(Ant {
+            row,
+            col,
+            heading: heading.unwrap_or(Direction::North),
+            state: 0,
+            rule: Some(Rc::new(rule)),
+        });
+        self.fit_states();
+        Ok(())
+    }
+
+    /**
+     * Remove the ants on the specified cell, returning how many there were.
+     */
+    pub fn remove_ants(&mut self, row: u32, col: u32) -> Result<u32, JsError> {
+        self.checked_index(row, col)?;
+        let count = self.ants.len();
+        self.ants.retain(|ant| (ant.row, ant.col) != (row, col));
+        Ok((count - self.ants.len()) as u32)
+    }
+
+    /**
+     * Remove every ant.
+     */
+    pub fn clear_ants(&mut self) {
+        self.ants.clear();
+    }
+
+    /**
+     * Returns every ant and turmite as consecutive `row, col, heading, state`
+     * quadruples, headings being `Direction` values and the state of plain
+     * ants always 0.
+     */
+    pub fn ants(&self) -> Vec<u32> {
+        let ants = self.ants.iter();
+        ants.flat_map(|ant| vec![ant.row, ant.col, ant.heading as u32, u32::from(ant.state)]).collect()
+    }
+
+    /**
+     * Set how ants turn, one letter per cell color: `L` and `R` turn left and
+     * right, `N` keeps going and `U` turns around. Colors are cell states, so
+     * Langton's original `RL` turns right on dead cells and left on live
+     * ones, and longer rules like `LLRR` color cells in extra states that the
+     * cell rule leaves alone unless it has them too. For ants alone, pick a
+     * rule that never changes cells such as `B/S012345678`. A turmite
+     * transition table, as taken by `add_turmite`, also works, and puts the
+     * ants following it back in state 0.
+     */
+    pub fn set_ant_rule(&mut self, rule: &str) -> Result<(), JsError> {
+        self.ant_rule = rule.parse()?;
+        self.ants.iter_mut().filter(|ant| ant.rule.is_none()).for_each(|ant| ant.state = 0);
+        self.fit_states();
+        Ok(())
+    }
+
+    /**
+     * Returns the ant rule, `RL` unless changed.
+     */
+    pub fn ant_rule(&self) -> String {
+        self.ant_rule.to_string()
+    }
+
+    /**
+     * Simulate a step in the universe. Under an elementary rule such as
+     * `W30` the rows scroll up and the bottom row is replaced by the next
+     * generation of the row above it, so a seed in the bottom row draws its
+     * history over the grid.
+     */
+    pub fn tick(&mut self) {
+        let start = self.timer.as_ref().map(Timer::start);
+        self.step();
+        if let (Some(timer), Some(start)) = (&mut self.timer, start) {
+            timer.stop(start);
+        }
+    }
+
+    /**
+     * Time every tick, keeping how long the last `window` of them took for
+     * `timing_stats`, and with a `label`, also wrapping each in
+     * `console.time` and `console.timeEnd` under it for the browser's
+     * developer tools. A window of 0, the default, stops timing, and
+     * windows are at most 65536 ticks. Timing anew forgets the ticks timed
+     * before.
+     */
+    pub fn set_timing(&mut self, window: u32, label: Option<String>) {
+        let window = window.min(timer::MAX_WINDOW) as usize;
+        self.timer = Some(window).filter(|&window| window > 0).map(|window| Timer::new(window, label));
+    }
+
+    /**
+     * Returns how long the last ticks took, or nothing while they are not
+     * timed.
+     */
+    pub fn timing_stats(&self) -> Option<TimingStats> {
+        self.timer.as_ref().map(Timer::stats)
+    }
+
+    /// Step one generation forward.
+    fn step(&mut self) {
+        match self.local_masks() {
+            Some(masks) if self.live.is_some() => return self.step_sparse(masks),
+            Some(masks) if self.kernel == Kernel::Incremental => return self.step_incremental(masks),
+            _ => {
+                self.live = None;
+                self.counts = None;
+            }
+        }
+        let before = (self.deltas.is_some() || self.dirty.is_some())
+            .then(|| (self.states.clone(), self.sand.clone(), self.levels.clone(), self.colors.clone()));
+        self.keep_previous();
+        let tiles = self.tiles.take();
+        if self.rule_callback.is_some() {
+            self.step_callback();
+        } else if self.rule.at(self.generation).elementary().is_some() {
+            self.grow_row();
+        } else if let Some(blocks) = self.rule.at(self.generation).blocks() {
+            self.step_blocks(&blocks);
+        } else if let Some(colors) = self.rule.colors() {
+            self.step_colored(colors);
+        } else if self.rule.is_sandpile() {
+            self.step_sand();
+        } else if let Some(continuous) = self.rule.continuous() {
+            self.step_continuous(&continuous);
+        } else {
+            self.step_cells(tiles);
+        }
+        self.apply_mask();
+        self.births = self.cells.ones().filter(|&idx| !self.previous[idx]).count() as u32;
+        self.deaths = self.previous.ones().filter(|&idx| !self.cells[idx]).count() as u32;
+        self.step_ants();
+        self.add_noise();
+        self.expand();
+        self.generation += 1;
+        self.feed_sources();
+        if let Some(before) = before {
+            self.record_changes(before);
+        }
+        if self.local_masks().is_some() && self.cells.count_ones(..) * sparse::SPARSE_BELOW < self.cells.len() {
+            let width = self.width as usize;
+            self.live = Some(self.cells.ones().map(|idx| ((idx / width) as u32, (idx % width) as u32)).collect());
+            self.previous = FixedBitSet::default();
+            self.tiles = None;
+        }
+    }
+
+    /**
+     * Tick `n` times in one call, so that a render loop can step many
+     * generations a frame without crossing into wasm for each, returning
+     * the births and deaths of them all.
+     */
+    pub fn tick_n(&mut self, n: u32) -> TickStats {
+        let mut stats = TickStats::default();
+        for _ in 0..n {
+            self.tick();
+            stats.generations += 1;
+            stats.births += u64::from(self.births);
+            stats.deaths += u64::from(self.deaths);
+        }
+        stats
+    }
+
+    /**
+     * Tick for as long as `max_ms` milliseconds allow by `performance.now`,
+     * returning how many generations ran, so that a render loop can fill
+     * each frame with as many as the device manages. The tick running over
+     * the budget is not cut short.
+     */
+    pub fn tick_for_budget(&mut self, max_ms: f64) -> u32 {
+        let start = utils::now_ms();
+        let mut generations = 0;
+        while utils::now_ms() - start < max_ms {
+            self.tick();
+            generations += 1;
+        }
+        generations
+    }
+
+    /**
+     * Record which cells ticks change, for `take_deltas`, or stop. Cells
+     * changed any other way, by setting them or changing the rule, are not
+     * recorded, and neither is `background` turning over.
+     */
+    pub fn set_record_deltas(&mut self, record: bool) {
+        self.deltas = match self.deltas.take() {
+            _ if !record => None,
+            Some(deltas) => Some(deltas),
+            None => Some(FixedBitSet::with_capacity(self.cells.len())),
+        };
+    }
+
+    pub fn records_deltas(&self) -> bool {
+        self.deltas.is_some()
+    }
+
+    /**
+     * Returns the indices of the cells that ticks changed since the last
+     * call, in order and each once, and starts over, so that a renderer can
+     * redraw those cells alone. Every cell is returned after the grid
+     * changed size, and none while deltas are not recorded.
+     *
+     * ```js
+     * universe.set_record_deltas(true);
+     * universe.tick();
+     * for (const idx of universe.take_deltas()) {
+     *   drawCell(Math.floor(idx / width), idx % width);
+     * }
+     * ```
+     */
+    pub fn take_deltas(&mut self) -> Vec<u32> {
+        let len = self.cells.len();
+        match &mut self.deltas {
+            Some(deltas) if deltas.len() != len => {
+                *deltas = FixedBitSet::with_capacity(len);
+                (0..len as u32).collect()
+            }
+            Some(deltas) => {
+                let taken = deltas.ones().map(|idx| idx as u32).collect();
+                deltas.clear();
+                taken
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /**
+     * Record which 64x64 tiles of the grid ticks change, for
+     * `take_dirty_rects`, or stop. Like deltas, only the changes of ticks are
+     * recorded, but keeping them costs much less.
+     */
+    pub fn set_record_dirty_rects(&mut self, record: bool) {
+        let size = (self.width as usize, self.height as usize);
+        self.dirty = match self.dirty.take() {
+            _ if !record => None,
+            Some(dirty) => Some(dirty),
+            None => Some(DirtyTiles::new(size)),
+        };
+    }
+
+    pub fn records_dirty_rects(&self) -> bool {
+        self.dirty.is_some()
+    }
+
+    /**
+     * Returns rectangles covering the 64x64 tiles that ticks changed since
+     * the last call, four numbers each: the row and column of the top left
+     * cell, the width and the height, and starts over. Changed tiles next to
+     * each other along a row come as one rectangle, and rectangles stop at
+     * the edges of the grid. The first call after recording starts, or after
+     * the grid changed size, covers the whole grid, and none are returned
+     * while they are not recorded.
+     *
+     * ```js
+     * const rects = universe.take_dirty_rects();
+     * for (let i = 0; i < rects.length; i += 4) {
+     *   const [row, col, width, height] = rects.subarray(i, i + 4);
+     *   context.putImageData(image, 0, 0, col, row, width, height);
+     * }
+     * ```
+     */
+    pub fn take_dirty_rects(&mut self) -> Vec<u32> {
+        let size = (self.width as usize, self.height as usize);
+        match &mut self.dirty {
+            Some(dirty) if !dirty.fits(size) => {
+                *dirty = DirtyTiles::new(size);
+                dirty.take()
+            }
+            Some(dirty) => dirty.take(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Mark the cells that differ from `previous`, or from the states, sand,
+    /// levels and colors `before` the tick, in the deltas and dirty tiles
+    /// being recorded, or all of them once the grid changed size.
+    fn record_changes(&mut self, (states, sand, levels, colors): (Vec<u8>, Vec<u32>, Vec<f32>, Vec<u8>)) {
+        let (len, size) = (self.cells.len(), (self.width as usize, self.height as usize));
+        let resized = self.previous.len() != len
+            || states.len() != self.states.len()
+            || sand.len() != self.sand.len()
+            || levels.len() != self.levels.len()
+            || colors.len() != self.colors.len();
+        fn changed<'a, T: PartialEq>(before: &'a [T], after: &'a [T]) -> impl Iterator<Item = usize> + 'a {
+            before.iter().zip(after).enumerate().filter(|(_, (before, after))| before != after).map(|(idx, _)| idx)
+        }
+        let changed: Vec<usize> = if resized {
+            Vec::new()
+        } else {
+            changed(&states, &self.states)
+                .chain(changed(&sand, &self.sand))
+                .chain(changed(&levels, &self.levels))
+                .chain(changed(&colors, &self.colors))
+                .collect()
+        };
+        if let Some(deltas) = &mut self.deltas {
+            if resized || deltas.len() != len {
+                *deltas = FixedBitSet::with_capacity(len);
+                deltas.set_range(.., true);
+            } else {
+                let blocks = self.previous.as_slice().iter().zip(self.cells.as_slice());
+                for (delta, (before, after)) in deltas.as_mut_slice().iter_mut().zip(blocks) {
+                    *delta |= before ^ after;
+                }
+                changed.iter().for_each(|&idx| deltas.insert(idx));
+            }
+        }
+        if let Some(dirty) = &mut self.dirty {
+            if resized || !dirty.fits(size) {
+                *dirty = DirtyTiles::new(size);
+            } else {
+                dirty.mark_changes(&self.previous, &self.cells);
+                changed.iter().for_each(|&idx| dirty.mark(idx));
+            }
+        }
+    }
+
+    /**
+     * Tick only the rows from `row_start` up to `row_end`, taking the rows
+     * just above and below them from `above` and `below` rather than from
+     * the universe, packed as by `cells_in_rect`. The other rows stay as
+     * they are. Workers each owning a band of a grid, with a wasm instance
+     * each, can step it together by handing each other the rows
+     * `band_halos` returns every generation, the band on top getting the
+     * bottom row of the grid on a torus and dead cells otherwise.
+     *
+     * ```js
+     * const halos = universe.band_halos(start, end);
+     * // Send halos.subarray(0, words) up and halos.subarray(words) down,
+     * // and receive the rows next to the band from the workers around it.
+     * universe.tick_band(start, end, above, below);
+     * ```
+     *
+     * Only works under two state B/S rules without B0 that step every cell
+     * alike, as listed by `is_sparse`, and with left and right edges that
+     * either meet dead cells or are stitched together without a twist or a
+     * shift.
+     */
+    pub fn tick_band(&mut self, row_start: u32, row_end: u32, above: &[u32], below: &[u32]) -> Result<(), JsError> {
+        Ok(self.step_band(row_start, row_end, above, below)?)
+    }
+
+    /**
+     * Returns the top and bottom rows of the band from `row_start` up to
+     * `row_end`, packed as by `cells_in_rect`, every row taking the same
+     * number of words: the halos of the bands above and below it.
+     */
+    pub fn band_halos(&self, row_start: u32, row_end: u32) -> Result<Vec<u32>, JsError> {
+        self.check_band(row_start, row_end)?;
+        let mut halos = self.cells_in_rect(row_start, 0, self.width, 1);
+        halos.extend(self.cells_in_rect(row_end - 1, 0, self.width, 1));
+        Ok(halos)
+    }
+
+    /// Fail unless the rows from `row_start` up to `row_end` are a band of
+    /// the grid, with at least one row.
+    fn check_band(&self, row_start: u32, row_end: u32) -> Result<(), Error> {
+        if row_start >= row_end || row_end > self.height {
+            return Err(Error::Band {
+                row_start,
+                row_end,
+                height: self.height,
+            });
+        }
+        Ok(())
+    }
+
+    fn step_band(&mut self, row_start: u32, row_end: u32, above: &[u32], below: &[u32]) -> Result<(), Error> {
+        self.check_band(row_start, row_end)?;
+        let words = self.width.div_ceil(32) as usize;
+        if let Some(halo) = [above, below].iter().find(|halo| halo.len() < words) {
+            return Err(Error::HaloLength {
+                expected: words,
+                actual: halo.len(),
+            });
+        }
+        let masks = self.local_masks().ok_or_else(|| {
+            Error::Unbandable(format!("it needs a two state B/S rule without B0 stepping every cell alike, not {}", self.rule))
+        })?;
+        let wrap_cols = match self.boundary {
+            Boundary::CrossSurface => None,
+            Boundary::Torus if self.torus_shift != 0 => None,
+            boundary => Some(boundary.wraps().1),
+        }
+        .ok_or_else(|| Error::Unbandable("cells crossing its left and right edges come back on other rows".to_string()))?;
+        if self.width == 0 {
+            self.generation += 1;
+            return Ok(());
+        }
+        let (width, height) = (self.width as usize, (row_end - row_start) as usize);
+        let first = row_start as usize * width;
+        let mut rows = FixedBitSet::with_capacity(width * (height + 2));
+        band::unpack(above, width, &mut rows, 0);
+        for idx in 0..width * height {
+            rows.set(width + idx, self.cells[first + idx]);
+        }
+        band::unpack(below, width, &mut rows, width * (height + 1));
+        let next = band::step(&rows, (width, height), wrap_cols, masks);
+        self.births = 0;
+        self.deaths = 0;
+        for idx in 0..width * height {
+            let alive = next[idx];
+            if alive != self.cells[first + idx] {
+                self.cells.set(first + idx, alive);
+                self.births += alive as u32;
+                self.deaths += !alive as u32;
+                self.record_change(first + idx);
+            }
+        }
+        self.live = None;
+        self.counts = None;
+        self.previous = FixedBitSet::default();
+        self.tiles = None;
+        self.generation += 1;
+        Ok(())
+    }
+
+    /**
+     * Returns about how many bytes of wasm memory the universe takes,
+     * counting what its buffers have room for, so that pages running many
+     * universes for long can see what grows. wasm memory itself never
+     * shrinks, but what `shrink_to_fit` frees is reused.
+     */
+    pub fn memory_stats(&self) -> MemoryStats {
+        let bits = |bits: &FixedBitSet| size_of_val(bits.as_slice());
+        let cells = bits(&self.cells) + bits(&self.previous);
+        let states = self.states.capacity()
+            + self.sand.capacity() * size_of::<u32>()
+            + self.levels.capacity() * size_of::<f32>()
+            + self.colors.capacity()
+            + self.mask.as_ref().map_or(0, bits);
+        let caches = self.tiles.as_ref().map_or(0, Tiles::bytes)
+            + self.life_table.as_deref().map_or(0, LifeTable::bytes)
+            + self.live.as_ref().map_or(0, |live| live.capacity() * (size_of::<(u32, u32)>() + 1))
+            + self.counts.as_ref().map_or(0, Counts::bytes);
+        let recording = self.deltas.as_ref().map_or(0, bits) + self.dirty.as_ref().map_or(0, DirtyTiles::bytes);
+        let clamp = |bytes: usize| bytes.min(u32::MAX as usize) as u32;
+        MemoryStats {
+            cells: clamp(cells),
+            states: clamp(states),
+            caches: clamp(caches),
+            recording: clamp(recording),
+            total: clamp(cells + states + caches + recording),
+        }
+    }
+
+    /**
+     * Free what the universe can do without: drop the caches, which ticks
+     * rebuild when they need them at the cost of a slower next tick, and
+     * the copy of the cells ticks step into, and give back the room its
+     * buffers keep for growth.
+     */
+    pub fn shrink_to_fit(&mut self) {
+        // Tiles find the cells that changed since the last tick in the copy.
+        self.previous = FixedBitSet::default();
+        self.tiles = None;
+        self.life_table = None;
+        self.counts = None;
+        if let Some(live) = &mut self.live {
+            live.shrink_to_fit();
+        }
+        self.states.shrink_to_fit();
+        self.sand.shrink_to_fit();
+        self.levels.shrink_to_fit();
+        self.colors.shrink_to_fit();
+        self.ants.shrink_to_fit();
+        self.regions.shrink_to_fit();
+        self.sources.shrink_to_fit();
+    }
+
+    /**
+     * Returns whether ticks step the live cells alone rather than the whole
+     * grid, as they do under two state B/S rules without B0 once fewer than
+     * one cell in 1024 lives, until more than one in 256 does. Dying
+     * states, regions, masks, edge sources, ants, noise and growing grids
+     * keep to the whole grid, and so do boundaries brought to life or
+     * mirroring the cells. Sparse universes still keep the grid, a bit a
+     * cell, but not the copy of it that ticks step into.
+     */
+    pub fn is_sparse(&self) -> bool {
+        self.live.is_some()
+    }
+
+    /// The birth and survival bitmasks of the rule, if the next generation
+    /// follows from the live cells and the cells around them alone, as
+    /// stepping the live cells or the neighbor counts needs.
+    fn local_masks(&self) -> Option<(u16, u16)> {
+        let rule = self.rule.at(self.generation);
+        let plain = rule.is_unbounded()
+            && !self.background
+            && !matches!(self.boundary, Boundary::Alive | Boundary::Mirror | Boundary::Cube)
+            && self.rule_callback.is_none()
+            && self.neighborhood.is_none()
+            && self.morph.is_none()
+            && self.regions.is_empty()
+            && self.states.is_empty()
+            && self.levels.is_empty()
+            && self.colors.is_empty()
+            && self.mask.is_none()
+            && self.sources.is_empty()
+            && self.ants.is_empty()
+            && self.noise_rate <= 0.0
+            && self.expand_margin.is_none();
+        rule.life_masks().filter(|_| plain)
+    }
+
+    /// Step the live cells one generation forward, going back to the whole
+    /// grid if they grow too many.
+    fn step_sparse(&mut self, masks: (u16, u16)) {
+        let live = self.live.take().unwrap_or_default();
+        let offsets = Neighborhood::Moore.offsets(0);
+        let changes = sparse::changes(&live, masks, offsets, |(row, col), (delta_row, delta_col)| {
+            let idx = self.neighbor_index(row, col, delta_row, delta_col)? as u32;
+            Some((idx / self.width, idx % self.width))
+        });
+        self.live = Some(live);
+        self.births = 0;
+        self.deaths = 0;
+        for ((row, col), alive) in changes {
+            let idx = self.get_index(row, col);
+            self.cells.set(idx, alive);
+            self.edited(idx, !alive);
+            self.births += alive as u32;
+            self.deaths += !alive as u32;
+            self.record_change(idx);
+        }
+        self.generation += 1;
+        if self.live.as_ref().map_or(0, LiveCells::len) * sparse::DENSE_ABOVE > self.cells.len() {
+            self.live = None;
+        }
+    }
+
+    /// Step the cells around those that changed since the last tick one
+    /// generation forward from their neighbor counts, counting afresh if the
+    /// grid is new to them.
+    fn step_incremental(&mut self, (birth, survival): (u16, u16)) {
+        let topology = (self.width, self.height, self.boundary, self.torus_shift);
+        let mut counts = match self.counts.take() {
+            Some(counts) if counts.fits(topology) => counts,
+            _ => {
+                let count = |idx| self.moore_neighbors(idx).filter(|&neighbor| self.cells[neighbor]).count() as u8;
+                Counts::new(topology, (0..self.cells.len()).map(count).collect())
+            }
+        };
+        let changes: Vec<(usize, bool)> = counts
+            .candidates((birth, survival), |idx| self.moore_neighbors(idx))
+            .into_iter()
+            .filter_map(|idx| {
+                let alive = self.cells[idx];
+                let next = if alive { survival } else { birth } >> counts.count(idx) & 1 == 1;
+                (next != alive).then_some((idx, next))
+            })
+            .collect();
+        self.births = 0;
+        self.deaths = 0;
+        for (idx, alive) in changes {
+            self.cells.set(idx, alive);
+            counts.change(idx, alive, self.moore_neighbors(idx));
+            self.births += alive as u32;
+            self.deaths += !alive as u32;
+            self.record_change(idx);
+        }
+        self.counts = Some(counts);
+        self.previous = FixedBitSet::default();
+        self.tiles = None;
+        self.generation += 1;
+    }
+
+    /// Mark cell `idx` as changed in the deltas and dirty tiles being
+    /// recorded, unless they are for a grid of another size, which they are
+    /// taken all of anyway.
+    fn record_change(&mut self, idx: usize) {
+        let (len, size) = (self.cells.len(), (self.width as usize, self.height as usize));
+        if let Some(deltas) = self.deltas.as_mut().filter(|deltas| deltas.len() == len) {
+            deltas.insert(idx);
+        }
+        if let Some(dirty) = self.dirty.as_mut().filter(|dirty| dirty.fits(size)) {
+            dirty.mark(idx);
+        }
+    }
+
+    /**
+     * Force the cells of `edge` through `frames` after every tick, the
+     * states of the edge's cells for one generation after another, starting
+     * over after the last. The top and bottom edges run left to right, the
+     * left and right ones top to bottom, so that a top edge source cycling
+     * through a glider's rows streams gliders in. The cells take the frame of
+     * the current generation at once. Resizing the grid drops sources whose
+     * edge no longer fits their frames.
+     */
+    pub fn set_edge_source(&mut self, edge: Edge, frames: &[u8]) -> Result<(), JsError> {
+        let length = self.edge_cells(edge).len();
+        if length == 0 {
+            return Ok(());
+        }
+        if frames.is_empty() || !frames.len().is_multiple_of(length) {
+            return Err(Error::DataLength {
+                expected: frames.len().div_ceil(length).max(1) * length,
+                actual: frames.len(),
+            }
+            .into());
+        }
+        self.sources.retain(|source| source.edge != edge);
+        self.sources.push(Source {
+            edge,
+            frames: frames.chunks(length).map(<[u8]>::to_vec).collect(),
+        });
+        self.feed_sources();
+        Ok(())
+    }
+
+    /**
+     * Leave the cells of `edge` to the rule again.
+     */
+    pub fn clear_edge_source(&mut self, edge: Edge) {
+        self.sources.retain(|source| source.edge != edge);
+    }
+
+    /**
+     * Returns the number of frames the source on `edge` cycles through, 0
+     * when it has none.
+     */
+    pub fn edge_source_period(&self, edge: Edge) -> u32 {
+        self.sources
+            .iter()
+            .find(|source| source.edge == edge)
+            .map_or(0, |source| source.frames.len() as u32)
+    }
+
+    /// Indexes of the cells along `edge`, in the order of its frames.
+    fn edge_cells(&self, edge: Edge) -> Vec<usize> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        if width == 0 || height == 0 {
+            return Vec::new();
+        }
+        match edge {
+            Edge::Top => (0..width).collect(),
+            Edge::Bottom => ((height - 1) * width..height * width).collect(),
+            Edge::Left => (0..height).map(|row| row * width).collect(),
+            Edge::Right => (0..height).map(|row| row * width + width - 1).collect(),
+        }
+    }
+
+    /// Set the cells of every edge source to its frame for this generation.
+    fn feed_sources(&mut self) {
+        let sources = std::mem::take(&mut self.sources);
+        for source in &sources {
+            let frame = &source.frames[(self.generation % source.frames.len() as u64) as usize];
+            for (idx, &state) in self.edge_cells(source.edge).into_iter().zip(frame) {
+                self.put_state(idx, state);
+            }
+        }
+        self.sources = sources;
+    }
+
+    /// Copy the cells over `previous`, reusing its memory unless the grid
+    /// changed size, first marking the tiles of the cells changed since the
+    /// last tick.
+    fn keep_previous(&mut self) {
+        if self.previous.len() != self.cells.len() {
+            self.previous = FixedBitSet::with_capacity(self.cells.len());
+        } else if let Some(tiles) = &mut self.tiles {
+            tiles.mark_changes(&self.previous, &self.cells);
+        }
+        self.previous.as_mut_slice().copy_from_slice(self.cells.as_slice());
+    }
+
+    /**
+     * Grow a universe with a dead boundary whenever its live cells come
+     * within `margin` cells of an edge after a tick, adding dead cells on
+     * that side until they are twice the margin away, so that patterns
+     * spreading out never reach the edge. `None`, the default, keeps the
+     * size fixed. The cells move down and right by what was added above and
+     * left of them, as reported by `origin_row` and `origin_col`.
+     */
+    pub fn set_auto_expand(&mut self, margin: Option<u32>) {
+        self.expand_margin = margin;
+    }
+
+    pub fn auto_expand(&self) -> Option<u32> {
+        self.expand_margin
+    }
+
+    /**
+     * Returns the rows automatic growth has added above the cells in all,
+     * so that a cell that was on row `r` is now on row `r + origin_row()`.
+     */
+    pub fn origin_row(&self) -> u32 {
+        self.origin.0
+    }
+
+    /**
+     * Returns the columns automatic growth has added left of the cells in
+     * all.
+     */
+    pub fn origin_col(&self) -> u32 {
+        self.origin.1
+    }
+
+    /**
+     * Only keep the cells where `mask`, one byte per cell row by row, is
+     * nonzero, such as a disk or a hexagon for an arena of that shape. The
+     * rest are not on the board: they are always dead, count as dead to
+     * their neighbors whatever the boundary, render as spaces and come out
+     * of `pixels` transparent. Live cells left out die at once.
+     */
+    pub fn set_mask(&mut self, mask: &[u8]) -> Result<(), JsError> {
+        if mask.len() < self.cells.len() {
+            return Err(Error::DataLength {
+                expected: self.cells.len(),
+                actual: mask.len(),
+            }
+            .into());
+        }
+        let mut bits = FixedBitSet::with_capacity(self.cells.len());
+        for (idx, &exists) in mask.iter().take(self.cells.len()).enumerate() {
+            bits.set(idx, exists != 0);
+        }
+        self.mask = Some(bits);
+        self.apply_mask();
+        Ok(())
+    }
+
+    /**
+     * Keep only the cells whose centers lie within the largest circle
+     * centered on the grid.
+     */
+    pub fn set_circular_mask(&mut self) {
+        let (width, height) = (f64::from(self.width), f64::from(self.height));
+        let radius = width.min(height) / 2.0;
+        let mut mask = FixedBitSet::with_capacity(self.cells.len());
+        for idx in 0..self.cells.len() as u32 {
+            let row = f64::from(idx / self.width) + 0.5 - height / 2.0;
+            let col = f64::from(idx % self.width) + 0.5 - width / 2.0;
+            mask.set(idx as usize, row * row + col * col <= radius * radius);
+        }
+        self.mask = Some(mask);
+        self.apply_mask();
+    }
+
+    /**
+     * Put every cell back on the board.
+     */
+    pub fn clear_mask(&mut self) {
+        self.mask = None;
+    }
+
+    /**
+     * Returns whether the cell at (row, col) is on the board, as every cell
+     * is without a mask.
+     */
+    pub fn cell_exists(&self, row: u32, col: u32) -> Result<bool, JsError> {
+        Ok(self.exists(self.checked_index(row, col)?))
+    }
+
+    /// Grow the grid on every side the live cells came within the margin
+    /// of under automatic growth. Live empty space would fill the grid to
+    /// its edges, so a live background stops the growth.
+    fn expand(&mut self) {
+        let margin = match self.expand_margin {
+            Some(margin) if self.boundary == Boundary::Dead && !self.background => margin,
+            _ => return,
+        };
+        let (row, col, width, height) = match self.bounding_box() {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let grow = |distance: u32| if distance < margin { 2 * margin - distance } else { 0 };
+        let (top, left) = (grow(row), grow(col));
+        let (bottom, right) = (grow(self.height - row - height), grow(self.width - col - width));
+        if top + left + bottom + right == 0 {
+            return;
+        }
+        self.remap(self.width + left + right, self.height + top + bottom, |row, col| Some((row + top, col + left)));
+        for region in self.regions.iter_mut() {
+            region.row += top;
+            region.col += left;
+        }
+        self.origin = (self.origin.0 + top, self.origin.1 + left);
+    }
+
+    /**
+     * Flip every cell with chance `rate` after each tick, about `rate`
+     * times the number of cells in all, drawing from the universe's random
+     * number generator so that runs with the same seed stay the same. A rate
+     * of 0, the default, turns the noise off.
+     */
+    pub fn set_noise_rate(&mut self, rate: f64) {
+        self.noise_rate = if rate.is_nan() { 0.0 } else { rate.clamp(0.0, 1.0) };
+    }
+
+    pub fn noise_rate(&self) -> f64 {
+        self.noise_rate
+    }
+
+    /// Flip random cells at the noise rate, jumping from one to the next by
+    /// geometrically distributed gaps rather than drawing for every cell.
+    fn add_noise(&mut self) {
+        if self.noise_rate <= 0.0 {
+            return;
+        }
+        let log_miss = (1.0 - self.noise_rate).ln();
+        let mut idx = 0;
+        loop {
+            if log_miss.is_finite() {
+                // 1 - next_f64() is never 0, so the gap is finite.
+                let gap = (1.0 - self.rng.next_f64()).ln() / log_miss;
+                idx += gap.min(self.cells.len() as f64) as usize;
+            }
+            if idx >= self.cells.len() {
+                return;
+            }
+            self.toggle(idx);
+            idx += 1;
+        }
+    }
+
+    /// Drop the regions whose rule no longer goes with the universe's.
+    fn fit_regions(&mut self) {
+        let rule = &self.rule;
+        self.regions.retain(|region| region.rule.blends_with(rule));
+    }
+
+    /// For every cell, one more than the index of the region covering it,
+    /// or 0 outside of all regions. Empty when there are no regions.
+    fn region_tags(&self) -> Vec<usize> {
+        if self.regions.is_empty() {
+            return Vec::new();
+        }
+        let mut tags = vec![0; self.cells.len()];
+        for (tag, region) in self.regions.iter().enumerate() {
+            for row in region.row..(region.row + region.height).min(self.height) {
+                for col in region.col..(region.col + region.width).min(self.width) {
+                    tags[self.get_index(row, col)] = tag + 1;
+                }
+            }
+        }
+        tags
+    }
+
+    /// Update every cell by the rule at once, skipping the still ones among
+    /// `tiles` when those are of the last tick.
+    fn step_cells(&mut self, tiles: Option<Tiles>) {
+        if self.rule.at(self.generation).table().is_some() {
+            self.step_table();
+            return;
+        }
+        let table = match (self.kernel, self.rule.at(self.generation).life_masks()) {
+            (Kernel::Lookup, Some(masks)) => Some(self.life_table(masks)),
+            _ => None,
+        };
+        // A copy of the cells since this tick began.
+        let mut next_cells = std::mem::take(&mut self.previous);
+        let mut next_states = self.states.clone();
+        let rule = self.rule.at(self.generation);
+        let tags = self.region_tags();
+        let isolated = self.isolated_regions && !tags.is_empty();
+        // Summed-area tables need every cell to see the grid around it the
+        // same way, which cells near the edges of cube faces do not.
+        let flat = self.cube_face_size().is_none();
+        let (square_counts, square_offsets) = match (rule.square_range(), &self.neighborhood) {
+            (Some((range, middle)), None) if isolated || !flat => (None, Some(square_offsets(range, middle))),
+            (Some((range, middle)), None) => (Some(self.square_counts(range, middle)), None),
+            _ => (None, None),
+        };
+        let from_chance = self
+            .morph
+            .as_ref()
+            .map_or(0.0, |morph| 1.0 - f64::from(morph.elapsed + 1) / f64::from(morph.generations));
+        // Two state Moore rules step many cells at a time, but for the first
+        // and last columns, which are left to the loop below. Rows have to
+        // meet the ones past the top and bottom edges plainly.
+        let plain_rows = !matches!(self.boundary, Boundary::Mirror | Boundary::Cube) && !self.boundary.twists().0;
+        let columns: Vec<u32> = match rule.life_masks() {
+            Some(masks)
+                if plain_rows
+                    && tags.is_empty()
+                    && self.neighborhood.is_none()
+                    && self.morph.is_none()
+                    && self.states.is_empty()
+                    && self.width > 0 =>
+            {
+                let size = (self.width as usize, self.height as usize);
+                let outside = self.outside_state() == 1;
+                let wrap_rows = self.boundary.wraps().0;
+                let mut tiles = tiles.filter(|tiles| tiles.fits(size, masks)).unwrap_or_else(|| Tiles::new(size, masks));
+                tiles.plan();
+                let busy = |first, last| tiles.busy(first, last);
+                match &table {
+                    Some(table) => lookup::step(&self.cells, &mut next_cells, size, wrap_rows, outside, table, busy),
+                    None => bitwise::step(&self.cells, &mut next_cells, size, wrap_rows, outside, masks, busy),
+                }
+                self.tiles = Some(tiles);
+                let mut columns = vec![0, self.width - 1];
+                columns.dedup();
+                columns
+            }
+            _ => (0..self.width).collect(),
+        };
+
+        for row in 0..self.height {
+            for &col in &columns {
+                let index = self.get_index(row, col);
+                let state = self.state(index);
+                // Ant colors the rule does not have stay as they are.
+                if state >= rule.states() {
+                    continue;
+                }
+                let counted = rule.counted_state(state);
+                let neighbors = match &square_counts {
+                    Some(counts) => counts[index],
+                    None if isolated => {
+                        let offsets = square_offsets.as_deref().unwrap_or_else(|| self.neighbor_offsets(row));
+                        self.region_neighbor_count(row, col, counted, offsets, &tags)
+                    }
+                    None => match (rule.weights(), &square_offsets) {
+                        (Some(weights), _) => self.weighted_count(row, col, counted, weights),
+                        (None, Some(offsets)) => self.offsets_count(row, col, counted, offsets),
+                        (None, None) => self.neighbor_count(row, col, counted),
+                    },
+                };
+                let region_rule = match tags.get(index) {
+                    Some(&tag) if tag > 0 => Some(self.regions[tag - 1].rule.at(self.generation)),
+                    _ => None,
+                };
+                let cell_rule = match (region_rule, &self.morph) {
+                    (Some(region_rule), _) => region_rule,
+                    (None, Some(morph)) if self.rng.next_f64() < from_chance => &morph.from,
+                    (None, _) => rule,
+                };
+                let mut next_state = cell_rule.next(state, neighbors);
+                if let (true, Some((chance, to))) = (next_state == state, cell_rule.chance(state, neighbors)) {
+                    if self.rng.next_f64() < chance {
+                        next_state = to;
+                    }
+                }
+                next_cells.set(index, next_state == 1);
+                if let Some(state) = next_states.get_mut(index) {
+                    *state = if next_state > 1 { next_state } else { 0 };
+                }
+            }
+        }
+
+        if let Some(tiles) = &mut self.tiles {
+            tiles.mark_changes(&self.cells, &next_cells);
+        }
+        self.previous = std::mem::replace(&mut self.cells, next_cells);
+        self.states = next_states;
+        let neighbors = if self.background { self.neighborhood_size() } else { 0 };
+        let background = match &self.morph {
+            Some(morph) if self.rng.next_f64() < from_chance => morph.from.next(self.background as u8, neighbors),
+            _ => rule.next(self.background as u8, neighbors),
+        };
+        self.background = background == 1;
+        if let Some(morph) = &mut self.morph {
+            morph.elapsed += 1;
+            if morph.elapsed == morph.generations {
+                self.morph = None;
+            }
+        }
+    }
+
+    /// Update every 2x2 block by a block rule, the blocks starting on even
+    /// rows and columns in even generations and on odd ones in odd
+    /// generations. Blocks cut off by an edge that does not wrap, wraps with
+    /// a twist or a shift or wraps over an odd number of cells stay as they
+    /// are, and so do blocks with ant colors.
+    fn step_blocks(&mut self, blocks: &[u8; 16]) {
+        let phase = (self.generation % 2) as u32;
+        let (wrap_rows, wrap_cols) = self.boundary.wraps();
+        let (twist_rows, twist_cols) = self.boundary.twists();
+        let wrap_rows = wrap_rows && !twist_rows && self.height.is_multiple_of(2);
+        let shifted = self.boundary == Boundary::Torus && self.torus_shift != 0;
+        let wrap_cols = wrap_cols && !twist_cols && !shifted && self.width.is_multiple_of(2);
+        for row in (phase..self.height).step_by(2) {
+            if row + 1 == self.height && !wrap_rows {
+                continue;
+            }
+            for col in (phase..self.width).step_by(2) {
+                if col + 1 == self.width && !wrap_cols {
+                    continue;
+                }
+                let (next_row, next_col) = ((row + 1) % self.height, (col + 1) % self.width);
+                let cells = [
+                    self.get_index(row, col),
+                    self.get_index(row, next_col),
+                    self.get_index(next_row, col),
+                    self.get_index(next_row, next_col),
+                ];
+                if cells.iter().any(|&idx| self.state(idx) > 1) {
+                    continue;
+                }
+                let block = cells.iter().rev().fold(0, |block, &idx| block << 1 | self.cells[idx] as usize);
+                for (bit, &idx) in cells.iter().enumerate() {
+                    self.cells.set(idx, blocks[block] >> bit & 1 == 1);
+                }
+            }
+        }
+    }
+
+    /// Topple every cell with 4 or more grains of sand once, grains falling
+    /// off the edges that neither wrap nor mirror. Cells with ant colors hold no sand
+    /// and lose the grains toppling onto them.
+    fn step_sand(&mut self) {
+        let toppling = u32::from(SAND_TOPPLING);
+        let grains: Vec<u32> = (0..self.cells.len()).map(|idx| self.grains(idx)).collect();
+        let mut next = grains.clone();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let idx = self.get_index(row, col);
+                if grains[idx] < toppling {
+                    continue;
+                }
+                next[idx] -= toppling;
+                for &(delta_row, delta_col) in Neighborhood::VonNeumann.offsets(row) {
+                    if let Some(neighbor) = self.neighbor_index(row, col, delta_row, delta_col) {
+                        next[neighbor] += 1;
+                    }
+                }
+            }
+        }
+        for (idx, grains) in next.into_iter().enumerate() {
+            if self.state(idx) <= SAND_TOPPLING {
+                self.put_grains(idx, grains);
+            }
+        }
+    }
+
+    /// Update every cell by Life under a rule with `colors` colors of live
+    /// cells, newborn cells taking their color from their parents. Cells
+    /// beyond an alive boundary count in the first color.
+    fn step_colored(&mut self, colors: u8) {
+        let mut parents = vec![0; usize::from(colors)];
+        let mut next = Vec::with_capacity(self.cells.len());
+        for row in 0..self.height {
+            for col in 0..self.width {
+                parents.iter_mut().for_each(|count| *count = 0);
+                for &(delta_row, delta_col) in self.neighbor_offsets(row) {
+                    match self.neighbor_index(row, col, delta_row, delta_col) {
+                        Some(neighbor) if self.cells[neighbor] => parents[usize::from(self.colors[neighbor])] += 1,
+                        None if self.boundary == Boundary::Alive => parents[0] += 1,
+                        _ => {}
+                    }
+                }
+                let idx = self.get_index(row, col);
+                next.push(match (self.cells[idx], parents.iter().sum()) {
+                    (true, 2) | (true, 3) => Some(self.colors[idx]),
+                    (false, 3) => Some(birth_color(&parents)),
+                    _ => None,
+                });
+            }
+        }
+        for (idx, color) in next.into_iter().enumerate() {
+            self.set_alive(idx, color.is_some());
+            if let Some(color) = color {
+                self.colors[idx] = color;
+            }
+        }
+    }
+
+    /// Update the level of every cell by a continuous rule, from the sums of
+    /// its neighborhood weighted by the rule's kernel. Levels beyond the
+    /// edges count as 1 beyond an alive boundary and 0 otherwise.
+    fn step_continuous(&mut self, continuous: &Continuous) {
+        let kernel = continuous.kernel();
+        let outside = (self.boundary == Boundary::Alive) as u8 as f32;
+        let mut next = Vec::with_capacity(self.levels.len());
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let mut sums = [0.0; 2];
+                for &(delta_row, delta_col, weights) in &kernel {
+                    let level = match self.neighbor_index(row, col, delta_row, delta_col) {
+                        Some(neighbor) => self.levels[neighbor],
+                        None => outside,
+                    };
+                    sums[0] += weights[0] * level;
+                    sums[1] += weights[1] * level;
+                }
+                next.push(continuous.next(self.levels[self.get_index(row, col)], sums));
+            }
+        }
+        for (idx, level) in next.into_iter().enumerate() {
+            self.put_level(idx, level);
+        }
+    }
+
+    /// Update every cell by the rule callback, leaving the cells alone when
+    /// it fails.
+    fn step_callback(&mut self) {
+        let callback = match &self.rule_callback {
+            Some(callback) => callback,
+            None => return,
+        };
+        let mut next = Vec::with_capacity(self.cells.len());
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let state = self.state(self.get_index(row, col));
+                let neighbors = self.neighbor_count(row, col, 1);
+                let result = callback.call2(&JsValue::NULL, &JsValue::from(state), &JsValue::from(neighbors));
+                let next_state = match result.as_ref().map(|value| (value.as_f64(), value.as_bool())) {
+                    Ok((Some(number), _)) if number >= 0.0 => number.min(f64::from(u8::MAX)) as u8,
+                    Ok((_, Some(alive))) => alive as u8,
+                    _ => {
+                        log!("Rule callback failed on cell ({}, {}): {:?}", row, col, result);
+                        return;
+                    }
+                };
+                next.push(next_state);
+            }
+        }
+        for (index, state) in next.into_iter().enumerate() {
+            self.put_state(index, state);
+        }
+    }
+
+    /// Update every cell by a rule table, looking up each combination of a
+    /// cell's state and its neighbors' only once.
+    fn step_table(&mut self) {
+        let table = match self.rule.at(self.generation).table() {
+            Some(table) => table,
+            None => return,
+        };
+        let offsets = table.neighborhood.offsets();
+        let mut known: HashMap<Vec<u8>, u8> = HashMap::new();
+        let mut next = Vec::with_capacity(self.cells.len());
+        let mut around = Vec::with_capacity(offsets.len() + 1);
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let index = self.get_index(row, col);
+                around.clear();
+                around.push(self.state(index));
+                for &(delta_row, delta_col) in offsets {
+                    around.push(match self.neighbor_index(row, col, delta_row, delta_col) {
+                        Some(neighbor) => self.state(neighbor),
+                        None => self.outside_state(),
+                    });
+                }
+                let state = match known.get(&around) {
+                    Some(&state) => state,
+                    None => {
+                        let state = table.next(around[0], &around[1..]);
+                        known.insert(around.clone(), state);
+                        state
+                    }
+                };
+                next.push(state);
+            }
+        }
+        let background = vec![self.background as u8; offsets.len()];
+        self.background = table.next(self.background as u8, &background) == 1;
+        for (idx, state) in next.into_iter().enumerate() {
+            self.put_state(idx, state);
+        }
+    }
+
+    /// Scroll the rows up, computing the bottom row from the one that was
+    /// there under an elementary rule.
+    fn grow_row(&mut self) {
+        if self.height == 0 {
+            return;
+        }
+        let bottom = self.height - 1;
+        let next_row: Vec<bool> = (0..self.width)
+            .map(|col| {
+                let cell = |delta_col| match self.neighbor_index(bottom, col, 0, delta_col) {
+                    Some(index) => self.cells[index] as u8,
+                    None => self.outside_state(),
+                };
+                self.rule.next(0, u32::from(cell(-1) << 2 | cell(0) << 1 | cell(1))) == 1
+            })
+            .collect();
+        let (width, height) = (self.width, self.height);
+        self.remap(width, height, |row, col| row.checked_sub(1).map(|row| (row, col)));
+        for (col, alive) in next_row.into_iter().enumerate() {
+            let idx = self.get_index(bottom, col as u32);
+            self.set_alive(idx, alive);
+        }
+        let background = self.rule.next(0, if self.background { 0b111 } else { 0 });
+        self.background = background == 1;
+    }
+
+    fn get_index(&self, row: u32, column: u32) -> usize {
+        (row * self.width + column) as usize
+    }
+
+    /**
+     * Indices of the `width` x `height` rectangle whose top left corner is
+     * (row, column), wrapping around the edges. The rectangle is clamped to
+     * the size of the universe so no cell is visited twice.
+     */
+    fn region_indices(&self, row: u32, column: u32, width: u32, height: u32) -> Result<Vec<usize>, Error> {
+        self.checked_index(row, column)?;
+        let mut indices = Vec::with_capacity((width.min(self.width) * height.min(self.height)) as usize);
+        for delta_row in 0..height.min(self.height) {
+            for delta_col in 0..width.min(self.width) {
+                let region_row = (row + delta_row) % self.height;
+                let region_col = (column + delta_col) % self.width;
+                indices.push(self.get_index(region_row, region_col));
+            }
+        }
+        Ok(indices)
+    }
+
+    fn checked_index(&self, row: u32, column: u32) -> Result<usize, Error> {
+        if row >= self.height || column >= self.width {
+            return Err(Error::OutOfBounds {
+                row,
+                col: column,
+                width: self.width,
+                height: self.height,
+            });
+        }
+        Ok(self.get_index(row, column))
+    }
+
+    /**
+     * Index of the cell offset from (row, column), or `None` when it falls
+     * off an edge that neither wraps nor mirrors.
+     */
+    fn neighbor_index(&self, row: u32, column: u32, delta_row: i32, delta_col: i32) -> Option<usize> {
+        if let Some(size) = self.cube_face_size() {
+            let (neighbor_row, neighbor_col) = cube::neighbor(size, row, column, delta_row, delta_col)?;
+            return Some(self.get_index(neighbor_row, neighbor_col));
+        }
+        let (wrap_rows, wrap_cols) = self.boundary.wraps();
+        let (twist_rows, twist_cols) = self.boundary.twists();
+        let (height, width) = (self.height as i64, self.width as i64);
+        let mut neighbor_row = row as i64 + delta_row as i64;
+        let mut neighbor_col = column as i64 + delta_col as i64;
+        if self.boundary == Boundary::Mirror {
+            // Reflections repeat every two grid lengths.
+            let reflect = |coordinate: i64, size: i64| {
+                let coordinate = coordinate.rem_euclid(2 * size);
+                if coordinate < size { coordinate } else { 2 * size - 1 - coordinate }
+            };
+            return Some(self.get_index(reflect(neighbor_row, height) as u32, reflect(neighbor_col, width) as u32));
+        }
+        if self.boundary == Boundary::Torus {
+            neighbor_row += neighbor_col.div_euclid(width) * i64::from(self.torus_shift);
+        }
+        // Each axis in turn: wrap around, mirroring the other axis on an odd
+        // number of twisted crossings, or fall off.
+        if !(0..height).contains(&neighbor_row) {
+            if !wrap_rows {
+                return None;
+            }
+            if twist_rows && neighbor_row.div_euclid(height) % 2 != 0 {
+                neighbor_col = width - 1 - neighbor_col;
+            }
+            neighbor_row = neighbor_row.rem_euclid(height);
+        }
+        if !(0..width).contains(&neighbor_col) {
+            if !wrap_cols {
+                return None;
+            }
+            if twist_cols && neighbor_col.div_euclid(width) % 2 != 0 {
+                neighbor_row = height - 1 - neighbor_row;
+            }
+            neighbor_col = neighbor_col.rem_euclid(width);
+        }
+        Some(self.get_index(neighbor_row as u32, neighbor_col as u32))
+    }
+
+    /// Cells across every face of the cube, when the universe is one.
+    fn cube_face_size(&self) -> Option<u32> {
+        let size = self.height / 2;
+        (self.boundary == Boundary::Cube && size > 0 && self.height == 2 * size && self.width == 3 * size).then_some(size)
+    }
+
+    /// State of the cells beyond the edges of a bounded universe: alive
+    /// beyond an alive boundary and empty space otherwise.
+    fn outside_state(&self) -> u8 {
+        match self.boundary {
+            Boundary::Alive => 1,
+            _ => self.background as u8,
+        }
+    }
+
+    /// Row and column offsets of the neighbors of the cells on `row`.
+    fn neighbor_offsets(&self, row: u32) -> &[(i32, i32)] {
+        match &self.neighborhood {
+            Some(offsets) => offsets,
+            None => self.rule.at(self.generation).neighborhood().offsets(row),
+        }
+    }
+
+    /// Number of cells in the neighborhood of every cell, or under weighted
+    /// rules the count of a cell with all of them alive.
+    fn neighborhood_size(&self) -> u32 {
+        let rule = self.rule.at(self.generation);
+        if let Some(weights) = rule.weights() {
+            return weights.iter().map(|&(_, _, weight)| weight).sum();
+        }
+        match (rule.square_range(), &self.neighborhood) {
+            (Some((range, middle)), None) => (2 * range + 1) * (2 * range + 1) - !middle as u32,
+            _ => self.neighbor_offsets(0).len() as u32,
+        }
+    }
+
+    /// Number of live cells in the square reaching `range` cells out from
+    /// every cell, the cell itself only counting with `middle`. The counts
+    /// come from a summed-area table, so they cost the same for any range.
+    fn square_counts(&self, range: u32, middle: bool) -> Vec<u32> {
+        // A grid of no cells has nothing to count, nor edges to wrap around.
+        if self.cells.len() == 0 {
+            return Vec::new();
+        }
+        let (width, height, range) = (self.width as usize, self.height as usize, range as usize);
+        let side = 2 * range + 1;
+        // `sums[row * stride + col]` holds the live cells above and left of
+        // (row, col) in the grid padded by `range` cells on every side.
+        let stride = width + 2 * range + 1;
+        let mut sums = vec![0u32; (height + 2 * range + 1) * stride];
+        for padded_row in 0..height + 2 * range {
+            let mut row_sum = 0;
+            for padded_col in 0..width + 2 * range {
+                let (delta_row, delta_col) = (padded_row as i32 - range as i32, padded_col as i32 - range as i32);
+                row_sum += match self.neighbor_index(0, 0, delta_row, delta_col) {
+                    Some(index) => self.cells[index] as u32,
+                    None => u32::from(self.outside_state()),
+                };
+                sums[(padded_row + 1) * stride + padded_col + 1] = sums[padded_row * stride + padded_col + 1] + row_sum;
+            }
+        }
+
+        let mut counts = Vec::with_capacity(width * height);
+        for row in 0..height {
+            for col in 0..width {
+                let (top, left, bottom, right) = (row * stride, col, (row + side) * stride, col + side);
+                let count = sums[bottom + right] + sums[top + left] - sums[top + right] - sums[bottom + left];
+                let middle_cell = !middle && self.cells[row * width + col];
+                counts.push(count - middle_cell as u32);
+            }
+        }
+        counts
+    }
+
+    /// Number of neighbors of the cell in `state`, cells beyond a dead
+    /// boundary being in the background state.
+    fn neighbor_count(&self, row: u32, column: u32, state: u8) -> u32 {
+        self.offsets_count(row, column, state, self.neighbor_offsets(row))
+    }
+
+    /// `neighbor_count` at the given `offsets`.
+    fn offsets_count(&self, row: u32, column: u32, state: u8, offsets: &[(i32, i32)]) -> u32 {
+        let mut count = 0;
+        for &(delta_row, delta_col) in offsets {
+            let neighbor = match self.neighbor_index(row, column, delta_row, delta_col) {
+                Some(index) => self.state(index),
+                None => self.outside_state(),
+            };
+            count += (neighbor == state) as u32;
+        }
+        count
+    }
+
+    /// Weighted number of neighbors of the cell in `state`, each one at an
+    /// offset in `weights` adding its weight.
+    fn weighted_count(&self, row: u32, column: u32, state: u8, weights: &[(i32, i32, u32)]) -> u32 {
+        let mut count = 0;
+        for &(delta_row, delta_col, weight) in weights {
+            let neighbor = match self.neighbor_index(row, column, delta_row, delta_col) {
+                Some(index) => self.state(index),
+                None => self.outside_state(),
+            };
+            if neighbor == state {
+                count += weight;
+            }
+        }
+        count
+    }
+
+    /// `neighbor_count` at the given `offsets`, neighbors tagged with
+    /// another region than the cell being in the background state.
+    fn region_neighbor_count(&self, row: u32, column: u32, state: u8, offsets: &[(i32, i32)], tags: &[usize]) -> u32 {
+        let tag = tags[self.get_index(row, column)];
+        let mut count = 0;
+        for &(delta_row, delta_col) in offsets {
+            let neighbor = match self.neighbor_index(row, column, delta_row, delta_col) {
+                Some(index) if tags[index] == tag => self.state(index),
+                _ => self.outside_state(),
+            };
+            count += (neighbor == state) as u32;
+        }
+        count
+    }
+}
+
+/// Offsets of the cells in the square reaching `range` cells out, the middle
+/// cell only included with `middle`.
+fn square_offsets(range: u32, middle: bool) -> Vec<(i32, i32)> {
+    let range = range as i32;
+    (-range..=range)
+        .flat_map(|delta_row| (-range..=range).map(move |delta_col| (delta_row, delta_col)))
+        .filter(|&offset| middle || offset != (0, 0))
+        .collect()
+}
+
+/// Color of a cell born to parents with `parents[color]` of each color: the
+/// one most of them have, or with all of them different the first one none
+/// of them has.
+fn birth_color(parents: &[u32]) -> u8 {
+    let most = parents.iter().cloned().max().unwrap_or(0);
+    let color = if most == 1 { parents.iter().position(|&count| count == 0) } else { parents.iter().position(|&count| count == most) };
+    color.unwrap_or(0) as u8
 }
@@ -0,0 +1,136 @@
+//! The Life 1.05 and Life 1.06 `.lif` pattern formats.
+//!
+//! See <https://conwaylife.com/wiki/Life_1.05> and
+//! <https://conwaylife.com/wiki/Life_1.06>.
+
+use crate::pattern::Pattern;
+use crate::Error;
+use std::convert::TryFrom;
+
+/// Parse a Life 1.05 or 1.06 pattern, picking the version from its
+/// `#Life` header. The result is cropped to the live cells.
+pub(crate) fn parse(text: &str) -> Result<Pattern, Error> {
+    let mut lines = text.lines().enumerate().map(|(idx, line)| (idx + 1, line.trim()));
+    let live = match lines.next() {
+        Some((_, "#Life 1.06")) => parse_106(lines)?,
+        Some((_, "#Life 1.05")) => parse_105(lines)?,
+        _ => return Err(parse_error(1, "expected a #Life 1.05 or #Life 1.06 header")),
+    };
+    from_coordinates(&live)
+}
+
+/// Live cells of a Life 1.06 body, as `(row, col)`.
+fn parse_106<'a, I>(lines: I) -> Result<Vec<(i64, i64)>, Error>
+where
+    I: Iterator<Item = (usize, &'a str)>,
+{
+    let mut live = Vec::new();
+    for (line_number, line) in lines {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let coordinates: Vec<i64> = line
+            .split_whitespace()
+            .map(|value| value.parse::<i64>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| parse_error(line_number, "expected an x y coordinate pair"))?;
+        match coordinates.as_slice() {
+            &[x, y] => live.push((y, x)),
+            _ => return Err(parse_error(line_number, "expected an x y coordinate pair")),
+        }
+    }
+    Ok(live)
+}
+
+/// Live cells of the `#P` blocks of a Life 1.05 body, as `(row, col)`.
+fn parse_105<'a, I>(lines: I) -> Result<Vec<(i64, i64)>, Error>
+where
+    I: Iterator<Item = (usize, &'a str)>,
+{
+    let mut live = Vec::new();
+    let mut block: Option<(i64, i64)> = None;
+    for (line_number, line) in lines {
+        if let Some(offsets) = line.strip_prefix("#P") {
+            let offsets: Vec<i64> = offsets
+                .split_whitespace()
+                .map(|value| value.parse::<i64>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| parse_error(line_number, "expected #P x y"))?;
+            match offsets.as_slice() {
+                &[x, y] => block = Some((y, x)),
+                _ => return Err(parse_error(line_number, "expected #P x y")),
+            }
+            continue;
+        }
+        if line.starts_with('#') || (line.is_empty() && block.is_none()) {
+            continue;
+        }
+        let (top, left) = block.ok_or_else(|| parse_error(line_number, "cells before the first #P line"))?;
+        for (col, symbol) in line.chars().enumerate() {
+            match symbol {
+                '*' => live.push((top, left + col as i64)),
+                '.' => {}
+                other => return Err(parse_error(line_number, &format!("unexpected cell symbol {:?}", other))),
+            }
+        }
+        block = Some((top + 1, left));
+    }
+    Ok(live)
+}
+
+fn from_coordinates(live: &[(i64, i64)]) -> Result<Pattern, Error> {
+    let min_row = live.iter().map(|&(row, _)| row).min().unwrap_or(0);
+    let min_col = live.iter().map(|&(_, col)| col).min().unwrap_or(0);
+    let max_row = live.iter().map(|&(row, _)| row).max().unwrap_or(-1);
+    let max_col = live.iter().map(|&(_, col)| col).max().unwrap_or(-1);
+
+    // The number of rows or columns from `min` to `max`, if it fits a u32.
+    let extent = |min: i64, max: i64| {
+        max.checked_sub(min)
+            .and_then(|span| span.checked_add(1))
+            .and_then(|extent| u32::try_from(extent).ok())
+            .ok_or_else(|| parse_error(1, "pattern is too large"))
+    };
+    let mut pattern = Pattern::new(extent(min_col, max_col)?, extent(min_row, max_row)?)
+        .map_err(|err| parse_error(1, &err.to_string()))?;
+    for &(row, col) in live {
+        pattern.set((row - min_row) as u32, (col - min_col) as u32, true);
+    }
+    Ok(pattern)
+}
+
+/// Encode the live cells of `pattern` as Life 1.06 `x y` pairs.
+pub(crate) fn encode_106(pattern: &Pattern) -> String {
+    let mut out = String::from("#Life 1.06\n");
+    for row in 0..pattern.height() {
+        for col in 0..pattern.width() {
+            if pattern.get(row, col) {
+                out.push_str(&format!("{} {}\n", col, row));
+            }
+        }
+    }
+    out
+}
+
+/// Encode `pattern` as a single Life 1.05 `#P` block.
+pub(crate) fn encode_105(pattern: &Pattern) -> String {
+    let mut out = String::from("#Life 1.05\n#N\n#P 0 0\n");
+    for row in 0..pattern.height() {
+        let line: String = (0..pattern.width())
+            .map(|col| if pattern.get(row, col) { '*' } else { '.' })
+            .collect();
+        match line.trim_end_matches('.') {
+            "" => out.push('.'),
+            trimmed => out.push_str(trimmed),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn parse_error(line: usize, message: &str) -> Error {
+    Error::Parse {
+        line,
+        message: message.to_string(),
+    }
+}
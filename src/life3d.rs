@@ -0,0 +1,301 @@
+//! Life in three dimensions: a stack of layers of cells, each cell counting
+//! the 26 cells of the 3x3x3 cube around it, under rules in Carter Bays'
+//! notation such as 4555 and 5766, from his "Candidates for the Game of
+//! Life in Three Dimensions".
+
+use crate::render::{ALIVE_RGBA, DEAD_RGBA};
+use crate::rng::Rng;
+use crate::Error;
+use fixedbitset::FixedBitSet;
+use std::fmt;
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+/// The most live neighbors a cell can have.
+const NEIGHBORS: u8 = 26;
+
+/// A 3D rule: live cells survive with between `survival.0` and `survival.1`
+/// live neighbors, and dead cells come to life with between `birth.0` and
+/// `birth.1`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Rule3d {
+    survival: (u8, u8),
+    birth: (u8, u8),
+}
+
+impl Rule3d {
+    /// Bays' 4555, whose glider was the first found in 3D.
+    pub const BAYS_4555: Rule3d = Rule3d {
+        survival: (4, 5),
+        birth: (5, 5),
+    };
+
+    fn next(self, alive: bool, neighbors: u8) -> bool {
+        let (low, high) = if alive { self.survival } else { self.birth };
+        (low..=high).contains(&neighbors)
+    }
+}
+
+impl Default for Rule3d {
+    fn default() -> Rule3d {
+        Rule3d::BAYS_4555
+    }
+}
+
+impl FromStr for Rule3d {
+    type Err = Error;
+
+    /// Parse the lower and upper ends of the survival range followed by
+    /// those of the birth range, as four digits such as `5766` or, for
+    /// counts past 9, separated by commas such as `9,13,10,13`.
+    fn from_str(text: &str) -> Result<Rule3d, Error> {
+        let invalid = || Error::InvalidRule3d(text.to_string());
+        let text = text.trim();
+        let numbers: Vec<u8> = if text.contains(',') {
+            text.split(',').map(|number| number.trim().parse().ok()).collect::<Option<_>>()
+        } else {
+            text.chars().map(|digit| digit.to_digit(10).map(|digit| digit as u8)).collect::<Option<_>>()
+        }
+        .ok_or_else(invalid)?;
+        match numbers[..] {
+            [survive_low, survive_high, birth_low, birth_high]
+                if survive_low <= survive_high && birth_low <= birth_high && survive_high.max(birth_high) <= NEIGHBORS =>
+            {
+                Ok(Rule3d {
+                    survival: (survive_low, survive_high),
+                    birth: (birth_low, birth_high),
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Rule3d {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let numbers = [self.survival.0, self.survival.1, self.birth.0, self.birth.1];
+        if numbers.iter().all(|&number| number < 10) {
+            numbers.iter().try_for_each(|number| write!(f, "{}", number))
+        } else {
+            write!(f, "{},{},{},{}", numbers[0], numbers[1], numbers[2], numbers[3])
+        }
+    }
+}
+
+/**
+ * A `width` by `height` by `depth` grid of cells running a 3D Life rule,
+ * wrapping around on every axis. Cells are addressed by layer, row and
+ * column, and each layer can be read out on its own to draw it.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Universe3d {
+    width: u32,
+    height: u32,
+    depth: u32,
+    /// Layer by layer, each row by row.
+    cells: FixedBitSet,
+    rule: Rule3d,
+    seed: u32,
+    rng: Rng,
+    generation: u64,
+}
+
+impl Universe3d {
+    fn index(&self, layer: u32, row: u32, col: u32) -> usize {
+        ((layer * self.height + row) * self.width + col) as usize
+    }
+
+    fn checked_index(&self, layer: u32, row: u32, col: u32) -> Result<usize, Error> {
+        if layer >= self.depth {
+            return Err(Error::LayerOutOfBounds { layer, depth: self.depth });
+        }
+        if row >= self.height || col >= self.width {
+            return Err(Error::OutOfBounds {
+                row,
+                col,
+                width: self.width,
+                height: self.height,
+            });
+        }
+        Ok(self.index(layer, row, col))
+    }
+
+    fn checked_layer(&self, layer: u32) -> Result<(), Error> {
+        self.checked_index(layer, 0, 0).map(|_| ())
+    }
+}
+
+/// Add up every run of three `values`, `stride` apart in groups of `size`
+/// wrapping around at the ends, into the middle one.
+pub(crate) fn sum_threes(values: &[u8], stride: usize, size: usize) -> Vec<u8> {
+    (0..values.len())
+        .map(|idx| {
+            let position = idx / stride % size;
+            let base = idx - position * stride;
+            let before = base + (position + size - 1) % size * stride;
+            let after = base + (position + 1) % size * stride;
+            values[before] + values[idx] + values[after]
+        })
+        .collect()
+}
+
+#[wasm_bindgen]
+impl Universe3d {
+    /**
+     * Create an empty universe running 4555.
+     */
+    pub fn new(width: u32, height: u32, depth: u32) -> Universe3d {
+        Universe3d {
+            width,
+            height,
+            depth,
+            cells: FixedBitSet::with_capacity((width * height * depth) as usize),
+            rule: Rule3d::default(),
+            seed: 0,
+            rng: Rng::new(0),
+            generation: 0,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /**
+     * Switch to another rule in Bays' notation: the fewest and most live
+     * neighbors a live cell survives with, then the fewest and most a dead
+     * cell is born with, such as 4555 or 5766.
+     */
+    pub fn set_rule(&mut self, rulestring: &str) -> Result<(), JsError> {
+        self.rule = rulestring.parse()?;
+        Ok(())
+    }
+
+    pub fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    /**
+     * Seed the random number generator used by `reset_with_density`.
+     */
+    pub fn set_seed(&mut self, seed: u32) {
+        self.seed = seed;
+        self.rng = Rng::new(seed);
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /**
+     * Bring each cell to life with probability `density` and kill the rest.
+     */
+    pub fn reset_with_density(&mut self, density: f64) {
+        for idx in 0..self.cells.len() {
+            let alive = self.rng.next_f64() < density;
+            self.cells.set(idx, alive);
+        }
+        self.generation = 0;
+    }
+
+    pub fn empty_cells(&mut self) {
+        self.cells.clear();
+    }
+
+    pub fn get_cell(&self, layer: u32, row: u32, col: u32) -> Result<bool, JsError> {
+        Ok(self.cells[self.checked_index(layer, row, col)?])
+    }
+
+    pub fn set_cell(&mut self, layer: u32, row: u32, col: u32, alive: bool) -> Result<(), JsError> {
+        let idx = self.checked_index(layer, row, col)?;
+        self.cells.set(idx, alive);
+        Ok(())
+    }
+
+    /**
+     * Set every cell in `coords`, given as flattened `layer, row, col`
+     * triples, to `alive`.
+     */
+    pub fn set_cells(&mut self, coords: &[u32], alive: bool) -> Result<(), JsError> {
+        for triple in coords.chunks_exact(3) {
+            let idx = self.checked_index(triple[0], triple[1], triple[2])?;
+            self.cells.set(idx, alive);
+        }
+        Ok(())
+    }
+
+    /**
+     * Step every cell one generation forward by the live cells among the 26
+     * around it.
+     */
+    pub fn tick_3d(&mut self) {
+        if self.cells.len() == 0 {
+            return;
+        }
+        let (width, height, depth) = (self.width as usize, self.height as usize, self.depth as usize);
+        let alive: Vec<u8> = (0..self.cells.len()).map(|idx| u8::from(self.cells[idx])).collect();
+        let cubes = sum_threes(
+            &sum_threes(&sum_threes(&alive, 1, width), width, height),
+            width * height,
+            depth,
+        );
+        for (idx, (&alive, &cube)) in alive.iter().zip(&cubes).enumerate() {
+            self.cells.set(idx, self.rule.next(alive == 1, cube - alive));
+        }
+        self.generation += 1;
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /**
+     * Returns the number of live cells.
+     */
+    pub fn population(&self) -> u32 {
+        self.cells.count_ones(..) as u32
+    }
+
+    /**
+     * Returns the cells of one layer packed into 32 bit words, every row
+     * starting a new word with its first column in the lowest bit.
+     */
+    pub fn layer_cells(&self, layer: u32) -> Result<Vec<u32>, JsError> {
+        self.checked_layer(layer)?;
+        let mut cells = Vec::with_capacity(self.width.div_ceil(32) as usize * self.height as usize);
+        for row in 0..self.height {
+            for word_col in (0..self.width).step_by(32) {
+                let start = self.index(layer, row, word_col);
+                let bits = (0..32.min(self.width - word_col) as usize)
+                    .filter(|&offset| self.cells[start + offset])
+                    .fold(0, |bits, offset| bits | 1 << offset);
+                cells.push(bits);
+            }
+        }
+        Ok(cells)
+    }
+
+    /**
+     * Returns one layer as RGBA pixels, row by row, live cells black on
+     * white.
+     */
+    pub fn layer_pixels(&self, layer: u32) -> Result<Vec<u8>, JsError> {
+        self.checked_layer(layer)?;
+        let start = self.index(layer, 0, 0);
+        let size = (self.width * self.height) as usize;
+        let mut pixels = Vec::with_capacity(size * 4);
+        for idx in start..start + size {
+            pixels.extend_from_slice(if self.cells[idx] { &ALIVE_RGBA } else { &DEAD_RGBA });
+        }
+        Ok(pixels)
+    }
+}
@@ -0,0 +1,328 @@
+//! Golly's quadtree based macrocell `.mc` format.
+//!
+//! See <https://golly.sourceforge.io/Help/formats.html#mc>.
+
+use crate::Error;
+use std::collections::HashMap;
+
+/// Inclusive `(top, left, bottom, right)` bounds of the live cells of a node.
+type Bounds = (i64, i64, i64, i64);
+
+/// Nodes deeper than this would overflow `i64` coordinates.
+const MAX_LEVEL: u32 = 62;
+
+#[derive(Clone, Debug)]
+enum Node {
+    /// The empty node of any level, always at index 0.
+    Empty,
+    /// An 8x8 leaf, one byte per row with column 0 in the lowest bit.
+    Leaf([u8; 8]),
+    /// A 2x2 node of cell states as used by multi-state macrocells.
+    Cells([bool; 4]),
+    /// Quadrants of a node of the given level, ordered nw, ne, sw, se.
+    Inner { level: u32, children: [usize; 4] },
+}
+
+/// A parsed macrocell quadtree.
+#[derive(Clone, Debug)]
+pub(crate) struct Macrocell {
+    nodes: Vec<Node>,
+    bounds: Vec<Option<Bounds>>,
+}
+
+impl Macrocell {
+    /// Parse a macrocell file; the last node listed is the root.
+    pub fn parse(text: &str) -> Result<Macrocell, Error> {
+        let mut macrocell = Macrocell {
+            nodes: vec![Node::Empty],
+            bounds: vec![None],
+        };
+        let mut lines = text.lines().enumerate().map(|(idx, line)| (idx + 1, line.trim()));
+        match lines.next() {
+            Some((_, header)) if header.starts_with("[M2]") => {}
+            _ => return Err(parse_error(1, "expected a [M2] header")),
+        }
+
+        for (line_number, line) in lines {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let node = if line.starts_with(['.', '*', '$']) {
+                parse_leaf(line, line_number)?
+            } else {
+                macrocell.parse_inner(line, line_number)?
+            };
+            let bounds = macrocell.node_bounds(&node);
+            macrocell.nodes.push(node);
+            macrocell.bounds.push(bounds);
+        }
+        Ok(macrocell)
+    }
+
+    fn parse_inner(&self, line: &str, line_number: usize) -> Result<Node, Error> {
+        let expected = || parse_error(line_number, "expected a level followed by four children");
+        let mut values = line.split_whitespace();
+        let level = values.next().and_then(|level| level.parse::<u32>().ok()).ok_or_else(expected)?;
+        let children: Vec<usize> = values
+            .map(|value| value.parse::<usize>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| expected())?;
+        let children = match children.as_slice() {
+            &[nw, ne, sw, se] => [nw, ne, sw, se],
+            _ => return Err(expected()),
+        };
+        if level == 1 {
+            return Ok(Node::Cells([children[0] != 0, children[1] != 0, children[2] != 0, children[3] != 0]));
+        }
+        if level < 2 {
+            return Err(parse_error(line_number, "node level must be at least 1"));
+        }
+        if level > MAX_LEVEL {
+            return Err(parse_error(line_number, "node level is too large"));
+        }
+        for &child in children.iter() {
+            let child_level = match self.nodes.get(child) {
+                Some(Node::Empty) => continue,
+                Some(node) => self.level(node),
+                None => return Err(parse_error(line_number, &format!("node {} is not defined yet", child))),
+            };
+            if child_level + 1 != level {
+                return Err(parse_error(line_number, &format!("node {} has the wrong level", child)));
+            }
+        }
+        Ok(Node::Inner { level, children })
+    }
+
+    fn level(&self, node: &Node) -> u32 {
+        match node {
+            Node::Empty => 0,
+            Node::Leaf(_) => 3,
+            Node::Cells(_) => 1,
+            Node::Inner { level, .. } => *level,
+        }
+    }
+
+    fn node_bounds(&self, node: &Node) -> Option<Bounds> {
+        let mut bounds: Option<Bounds> = None;
+        let mut include = |cell: Bounds| {
+            bounds = Some(match bounds {
+                None => cell,
+                Some((top, left, bottom, right)) => {
+                    (top.min(cell.0), left.min(cell.1), bottom.max(cell.2), right.max(cell.3))
+                }
+            });
+        };
+        match node {
+            Node::Empty => {}
+            Node::Leaf(rows) => {
+                for (row, bits) in rows.iter().enumerate() {
+                    for col in 0..8 {
+                        if bits & (1 << col) != 0 {
+                            include((row as i64, col, row as i64, col));
+                        }
+                    }
+                }
+            }
+            Node::Cells(cells) => {
+                for (i, &alive) in cells.iter().enumerate() {
+                    if alive {
+                        let (row, col) = ((i / 2) as i64, (i % 2) as i64);
+                        include((row, col, row, col));
+                    }
+                }
+            }
+            Node::Inner { level, children } => {
+                let half = 1i64 << (level - 1);
+                for (i, &child) in children.iter().enumerate() {
+                    if let Some((top, left, bottom, right)) = self.bounds[child] {
+                        let (row, col) = ((i / 2) as i64 * half, (i % 2) as i64 * half);
+                        include((top + row, left + col, bottom + row, right + col));
+                    }
+                }
+            }
+        }
+        bounds
+    }
+
+    fn root(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    /// Bounds of every live cell, relative to the top left corner of the root.
+    pub fn bounding_box(&self) -> Option<Bounds> {
+        self.bounds[self.root()]
+    }
+
+    /// Call `f(row, col)` for every live cell inside `window`, given as
+    /// inclusive bounds relative to the top left corner of the root.
+    pub fn for_each_live<F: FnMut(i64, i64)>(&self, window: Bounds, mut f: F) {
+        self.visit(self.root(), 0, 0, window, &mut f);
+    }
+
+    fn visit<F: FnMut(i64, i64)>(&self, idx: usize, top: i64, left: i64, window: Bounds, f: &mut F) {
+        let overlaps = match self.bounds[idx] {
+            None => false,
+            Some((b_top, b_left, b_bottom, b_right)) => {
+                top + b_top <= window.2
+                    && top + b_bottom >= window.0
+                    && left + b_left <= window.3
+                    && left + b_right >= window.1
+            }
+        };
+        if !overlaps {
+            return;
+        }
+        let mut emit = |row: i64, col: i64| {
+            if row >= window.0 && row <= window.2 && col >= window.1 && col <= window.3 {
+                f(row, col);
+            }
+        };
+        match &self.nodes[idx] {
+            Node::Empty => {}
+            Node::Leaf(rows) => {
+                for (row, bits) in rows.iter().enumerate() {
+                    for col in 0..8 {
+                        if bits & (1 << col) != 0 {
+                            emit(top + row as i64, left + col);
+                        }
+                    }
+                }
+            }
+            Node::Cells(cells) => {
+                for (i, &alive) in cells.iter().enumerate() {
+                    if alive {
+                        emit(top + (i / 2) as i64, left + (i % 2) as i64);
+                    }
+                }
+            }
+            Node::Inner { level, children } => {
+                let half = 1i64 << (level - 1);
+                for (i, &child) in children.iter().enumerate() {
+                    let (row, col) = ((i / 2) as i64 * half, (i % 2) as i64 * half);
+                    self.visit(child, top + row, left + col, window, f);
+                }
+            }
+        }
+    }
+}
+
+/// Encode a `width` x `height` grid as a macrocell file, where `alive`
+/// reports the state of each cell. Identical subtrees are written once.
+pub(crate) fn encode<F>(width: u32, height: u32, rule: &str, alive: F) -> String
+where
+    F: Fn(u32, u32) -> bool,
+{
+    let mut out = format!("[M2] (wasm-game-of-life)\n#R {}\n", rule);
+    let mut leaves: HashMap<[u8; 8], usize> = HashMap::new();
+    let mut inner: HashMap<(u32, [usize; 4]), usize> = HashMap::new();
+    let mut next_id = 1;
+
+    let mut level = 4;
+    while (1u64 << level) < u64::from(width.max(height)) {
+        level += 1;
+    }
+    let leaves_per_side = 1usize << (level - 3);
+
+    let mut layer = Vec::with_capacity(leaves_per_side * leaves_per_side);
+    for leaf_row in 0..leaves_per_side {
+        for leaf_col in 0..leaves_per_side {
+            let mut rows = [0u8; 8];
+            for (row, bits) in rows.iter_mut().enumerate() {
+                for col in 0..8 {
+                    let (cell_row, cell_col) = (leaf_row * 8 + row, leaf_col * 8 + col);
+                    if cell_row < height as usize && cell_col < width as usize && alive(cell_row as u32, cell_col as u32) {
+                        *bits |= 1 << col;
+                    }
+                }
+            }
+            if rows == [0; 8] {
+                layer.push(0);
+                continue;
+            }
+            let id = *leaves.entry(rows).or_insert_with(|| {
+                out.push_str(&leaf_line(&rows));
+                next_id += 1;
+                next_id - 1
+            });
+            layer.push(id);
+        }
+    }
+
+    let mut side = leaves_per_side;
+    for node_level in 4..=level {
+        let half = side / 2;
+        let mut next = Vec::with_capacity(half * half);
+        for row in 0..half {
+            for col in 0..half {
+                let children = [
+                    layer[2 * row * side + 2 * col],
+                    layer[2 * row * side + 2 * col + 1],
+                    layer[(2 * row + 1) * side + 2 * col],
+                    layer[(2 * row + 1) * side + 2 * col + 1],
+                ];
+                // The root is always written, even when it is empty.
+                if children == [0; 4] && node_level != level {
+                    next.push(0);
+                    continue;
+                }
+                let id = *inner.entry((node_level, children)).or_insert_with(|| {
+                    out.push_str(&format!(
+                        "{} {} {} {} {}\n",
+                        node_level, children[0], children[1], children[2], children[3]
+                    ));
+                    next_id += 1;
+                    next_id - 1
+                });
+                next.push(id);
+            }
+        }
+        layer = next;
+        side = half;
+    }
+    out
+}
+
+/// An 8x8 leaf line with trailing dead cells and empty rows left out.
+fn leaf_line(rows: &[u8; 8]) -> String {
+    let used_rows = 8 - rows.iter().rev().take_while(|&&bits| bits == 0).count();
+    let mut line = String::new();
+    for bits in rows.iter().take(used_rows) {
+        for col in 0..(8 - bits.leading_zeros()) {
+            line.push(if bits & (1 << col) != 0 { '*' } else { '.' });
+        }
+        line.push('$');
+    }
+    line.push('\n');
+    line
+}
+
+/// Parse an 8x8 leaf line, where `$` ends a row and `*` is a live cell.
+fn parse_leaf(line: &str, line_number: usize) -> Result<Node, Error> {
+    let mut rows = [0u8; 8];
+    let (mut row, mut col) = (0, 0);
+    for symbol in line.chars() {
+        match symbol {
+            '$' => {
+                row += 1;
+                col = 0;
+            }
+            '.' | '*' if row >= 8 || col >= 8 => {
+                return Err(parse_error(line_number, "leaf is larger than 8x8"));
+            }
+            '.' => col += 1,
+            '*' => {
+                rows[row] |= 1 << col;
+                col += 1;
+            }
+            other => return Err(parse_error(line_number, &format!("unexpected symbol {:?}", other))),
+        }
+    }
+    Ok(Node::Leaf(rows))
+}
+
+fn parse_error(line: usize, message: &str) -> Error {
+    Error::Parse {
+        line,
+        message: message.to_string(),
+    }
+}
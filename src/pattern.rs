@@ -0,0 +1,520 @@
+use crate::render::{ALIVE_RGBA, DEAD_RGBA};
+use crate::{apgcode, fetch, lif, patterns, plaintext, rle, Error};
+use fixedbitset::FixedBitSet;
+use futures::Future;
+use js_sys::Promise;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+
+/// Patterns have at most this many cells, so that a file claiming to be
+/// enormous fails to parse rather than taking all of memory.
+pub(crate) const MAX_CELLS: u64 = 1 << 28;
+
+/**
+ * How a pattern is reoriented before being placed: a clockwise rotation,
+ * optionally preceded by a left to right mirror.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Transform {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    Flip,
+    FlipRotate90,
+    FlipRotate180,
+    FlipRotate270,
+}
+
+impl Transform {
+    /// Every orientation, starting with the identity.
+    pub(crate) const ALL: [Transform; 8] = [
+        Transform::Identity,
+        Transform::Rotate90,
+        Transform::Rotate180,
+        Transform::Rotate270,
+        Transform::Flip,
+        Transform::FlipRotate90,
+        Transform::FlipRotate180,
+        Transform::FlipRotate270,
+    ];
+
+    /// Number of clockwise quarter turns and whether to mirror first.
+    fn parts(self) -> (u32, bool) {
+        match self {
+            Transform::Identity => (0, false),
+            Transform::Rotate90 => (1, false),
+            Transform::Rotate180 => (2, false),
+            Transform::Rotate270 => (3, false),
+            Transform::Flip => (0, true),
+            Transform::FlipRotate90 => (1, true),
+            Transform::FlipRotate180 => (2, true),
+            Transform::FlipRotate270 => (3, true),
+        }
+    }
+
+    /// Where the cell at (row, col) of a `width` x `height` block lands once
+    /// the block is reoriented.
+    pub(crate) fn map(self, row: u32, col: u32, width: u32, height: u32) -> (u32, u32) {
+        let (quarter_turns, flip) = self.parts();
+        let col = if flip { width - 1 - col } else { col };
+        match quarter_turns {
+            0 => (row, col),
+            1 => (col, height - 1 - row),
+            2 => (height - 1 - row, width - 1 - col),
+            _ => (width - 1 - col, row),
+        }
+    }
+}
+
+/**
+ * Symmetry group of a random soup, named as in apgsearch: `C` groups only
+ * rotate, `D` groups also reflect.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    /// No symmetry.
+    C1,
+    /// Unchanged by a half turn.
+    C2,
+    /// Unchanged by a quarter turn.
+    C4,
+    /// Mirrored left to right.
+    D2,
+    /// Mirrored left to right and top to bottom.
+    D4,
+    /// Unchanged by every rotation and reflection.
+    D8,
+}
+
+impl Symmetry {
+    /// The orientations a soup with this symmetry looks the same in.
+    pub(crate) fn transforms(self) -> &'static [Transform] {
+        match self {
+            Symmetry::C1 => &[Transform::Identity],
+            Symmetry::C2 => &[Transform::Identity, Transform::Rotate180],
+            Symmetry::C4 => &Transform::ALL[..4],
+            Symmetry::D2 => &[Transform::Identity, Transform::Flip],
+            Symmetry::D4 => &[
+                Transform::Identity,
+                Transform::Rotate180,
+                Transform::Flip,
+                Transform::FlipRotate180,
+            ],
+            Symmetry::D8 => &Transform::ALL,
+        }
+    }
+}
+
+/**
+ * How the cells of a placed pattern combine with those already there.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Blend {
+    /// Every cell of the pattern, dead ones included, replaces the universe's.
+    Overwrite,
+    /// Live cells are added and dead ones are transparent.
+    Or,
+    /// Live cells toggle the cells under them.
+    Xor,
+}
+
+impl Blend {
+    /// The state of a cell that was `current` after blending `incoming` into
+    /// it.
+    pub(crate) fn apply(self, current: bool, incoming: bool) -> bool {
+        match self {
+            Blend::Overwrite => incoming,
+            Blend::Or => current || incoming,
+            Blend::Xor => current != incoming,
+        }
+    }
+}
+
+/**
+ * Compass heading of a moving pattern, north being the top of the universe.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    /// The rotation turning a pattern heading `self` into one heading
+    /// `target`, or `None` when they are not a whole number of quarter turns
+    /// apart (e.g. an orthogonal spaceship can not head diagonally).
+    pub fn rotation_to(self, target: Direction) -> Option<Transform> {
+        let eighths = (target as u32 + 8 - self as u32) % 8;
+        match eighths {
+            0 => Some(Transform::Identity),
+            2 => Some(Transform::Rotate90),
+            4 => Some(Transform::Rotate180),
+            6 => Some(Transform::Rotate270),
+            _ => None,
+        }
+    }
+
+    /// Every heading, clockwise from north.
+    const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    /// The heading `eighths` eighths of a turn clockwise from this one.
+    pub(crate) fn turned(self, eighths: u32) -> Direction {
+        Direction::ALL[((self as u32 + eighths) % 8) as usize]
+    }
+
+    /// The heading once reoriented by `transform`, like the patterns it
+    /// transforms.
+    pub(crate) fn transformed(self, transform: Transform) -> Direction {
+        let (quarter_turns, flip) = transform.parts();
+        let heading = if flip { Direction::ALL[((8 - self as u32) % 8) as usize] } else { self };
+        heading.turned(2 * quarter_turns)
+    }
+
+    /// Rows and columns moved by one step in this heading.
+    pub(crate) fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::North => (-1, 0),
+            Direction::NorthEast => (-1, 1),
+            Direction::East => (0, 1),
+            Direction::SouthEast => (1, 1),
+            Direction::South => (1, 0),
+            Direction::SouthWest => (1, -1),
+            Direction::West => (0, -1),
+            Direction::NorthWest => (-1, -1),
+        }
+    }
+}
+
+/// Provenance read from the `#N`, `#O` and `#C` lines and the header of an
+/// RLE file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Metadata {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub comments: Vec<String>,
+    pub rule: Option<String>,
+}
+
+/**
+ * A rectangular block of cells, parsed once and stamped into universes as
+ * many times as needed.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    width: u32,
+    height: u32,
+    cells: FixedBitSet,
+    metadata: Metadata,
+}
+
+/// Patterns are equal when their cells are, whatever their metadata.
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Pattern) -> bool {
+        self.width == other.width && self.height == other.height && self.cells == other.cells
+    }
+}
+
+impl Eq for Pattern {}
+
+impl Pattern {
+    /// An empty `width` x `height` pattern, unless it would have more than
+    /// `MAX_CELLS` cells.
+    pub fn new(width: u32, height: u32) -> Result<Pattern, Error> {
+        match u64::from(width) * u64::from(height) {
+            cells if cells <= MAX_CELLS => Ok(Pattern::blank(width, height)),
+            _ => Err(Error::PatternSize { width, height }),
+        }
+    }
+
+    /// An empty pattern the size of cells that already fit in memory, such
+    /// as those of another pattern.
+    pub(crate) fn blank(width: u32, height: u32) -> Pattern {
+        Pattern {
+            width,
+            height,
+            cells: FixedBitSet::with_capacity(width as usize * height as usize),
+            metadata: Metadata::default(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get(&self, row: u32, col: u32) -> bool {
+        row < self.height && col < self.width && self.cells[(row * self.width + col) as usize]
+    }
+
+    pub fn set(&mut self, row: u32, col: u32, alive: bool) {
+        self.cells.set((row * self.width + col) as usize, alive);
+    }
+
+    pub(crate) fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    pub(crate) fn set_metadata(&mut self, metadata: Metadata) {
+        self.metadata = metadata;
+    }
+
+    /// Parse a pattern file of any supported format, told apart by its
+    /// contents: Life 1.0x files by their `#Life` header, plaintext by
+    /// holding only `.`, `O` and `*` cells, and anything else as RLE.
+    pub fn parse(text: &str) -> Result<Pattern, Error> {
+        let trimmed = text.trim_start();
+        if trimmed.starts_with("#Life") {
+            return lif::parse(trimmed);
+        }
+        let is_plaintext = text
+            .lines()
+            .filter(|line| !line.starts_with('!'))
+            .all(|line| line.trim_end().chars().all(|symbol| symbol == '.' || symbol == 'O' || symbol == '*'));
+        if is_plaintext {
+            plaintext::parse(text)
+        } else {
+            rle::parse(text)
+        }
+    }
+
+    /// Returns a copy of the pattern trimmed to the bounding box of its live
+    /// cells, which is 0x0 when there are none.
+    pub fn cropped(&self) -> Pattern {
+        let live: Vec<(u32, u32)> = self
+            .cells
+            .ones()
+            .map(|idx| (idx as u32 / self.width, idx as u32 % self.width))
+            .collect();
+        let top = live.iter().map(|&(row, _)| row).min().unwrap_or(0);
+        let left = live.iter().map(|&(_, col)| col).min().unwrap_or(0);
+        let bottom = live.iter().map(|&(row, _)| row + 1).max().unwrap_or(0);
+        let right = live.iter().map(|&(_, col)| col + 1).max().unwrap_or(0);
+
+        let mut pattern = Pattern::blank(right.saturating_sub(left), bottom.saturating_sub(top));
+        pattern.metadata = self.metadata.clone();
+        for (row, col) in live {
+            pattern.set(row - top, col - left, true);
+        }
+        pattern
+    }
+
+    /// Returns a reoriented copy of the pattern.
+    pub fn transformed(&self, transform: Transform) -> Pattern {
+        let (quarter_turns, _) = transform.parts();
+        let (width, height) = if quarter_turns % 2 == 0 {
+            (self.width, self.height)
+        } else {
+            (self.height, self.width)
+        };
+        let mut pattern = Pattern::blank(width, height);
+        pattern.metadata = self.metadata.clone();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if self.get(row, col) {
+                    let (new_row, new_col) = transform.map(row, col, self.width, self.height);
+                    pattern.set(new_row, new_col, true);
+                }
+            }
+        }
+        pattern
+    }
+}
+
+#[wasm_bindgen]
+impl Pattern {
+    /**
+     * Parse a Run Length Encoded pattern.
+     */
+    pub fn from_rle(text: &str) -> Result<Pattern, JsError> {
+        Ok(rle::parse(text)?)
+    }
+
+    /**
+     * Parse a plaintext `.cells` pattern.
+     */
+    pub fn from_plaintext(text: &str) -> Result<Pattern, JsError> {
+        Ok(plaintext::parse(text)?)
+    }
+
+    /**
+     * Parse a Life 1.05 or Life 1.06 pattern.
+     */
+    pub fn from_life(text: &str) -> Result<Pattern, JsError> {
+        Ok(lif::parse(text)?)
+    }
+
+    /**
+     * Parse a pattern in any of the RLE, plaintext or Life formats.
+     */
+    pub fn from_text(text: &str) -> Result<Pattern, JsError> {
+        Ok(Pattern::parse(text)?)
+    }
+
+    /**
+     * Download and parse a pattern file, resolving to a `Pattern` ready to
+     * be passed to `Universe.stamp`:
+     *
+     * ```js
+     * universe.stamp(await Pattern.from_url(url), row, col);
+     * ```
+     *
+     * The promise is rejected when the request fails or the file can not be
+     * parsed.
+     */
+    pub fn from_url(url: &str) -> Promise {
+        future_to_promise(fetch::text(url).and_then(|text| match Pattern::from_text(&text) {
+            Ok(pattern) => Ok(pattern.into()),
+            Err(error) => Err(error.into()),
+        }))
+    }
+
+    /**
+     * Decode a Catagolue [apgcode](https://conwaylife.com/wiki/Apgcode) such
+     * as `xq4_153`.
+     */
+    pub fn from_apgcode(code: &str) -> Result<Pattern, JsError> {
+        Ok(apgcode::decode(code)?)
+    }
+
+    /**
+     * Returns a pattern of the built-in library, as named by
+     * `Universe.list_patterns`.
+     */
+    pub fn from_library(name: &str) -> Result<Pattern, JsError> {
+        let entry = patterns::find(name).ok_or_else(|| Error::UnknownPattern(name.to_string()))?;
+        Ok(rle::parse(entry.rle)?)
+    }
+
+    #[wasm_bindgen(js_name = width)]
+    pub fn width_js(&self) -> u32 {
+        self.width
+    }
+
+    #[wasm_bindgen(js_name = height)]
+    pub fn height_js(&self) -> u32 {
+        self.height
+    }
+
+    /**
+     * Returns the number of live cells.
+     */
+    pub fn population(&self) -> u32 {
+        self.cells.count_ones(..) as u32
+    }
+
+    /**
+     * Rasterize the pattern into a `max_width` x `max_height` RGBA image,
+     * row by row and ready for `ImageData`. Small patterns are scaled up by a
+     * whole number of pixels per cell and large ones scaled down, a pixel
+     * being alive when any cell it covers is; the result is centered on a
+     * dead background.
+     */
+    pub fn thumbnail(&self, max_width: u32, max_height: u32) -> Vec<u8> {
+        let mut pixels = DEAD_RGBA.repeat((max_width * max_height) as usize);
+        if self.width == 0 || self.height == 0 || max_width == 0 || max_height == 0 {
+            return pixels;
+        }
+        // Pixels per cell when scaling up and cells per pixel when scaling
+        // down, one of them being 1.
+        let zoom = (max_width / self.width).min(max_height / self.height).max(1);
+        let shrink = self.width.div_ceil(max_width).max(self.height.div_ceil(max_height));
+        let image_width = (self.width * zoom).div_ceil(shrink);
+        let image_height = (self.height * zoom).div_ceil(shrink);
+        let top = (max_height - image_height) / 2;
+        let left = (max_width - image_width) / 2;
+
+        for idx in self.cells.ones() {
+            let (row, col) = (idx as u32 / self.width, idx as u32 % self.width);
+            for y in 0..zoom {
+                for x in 0..zoom {
+                    let pixel_row = top + (row * zoom + y) / shrink;
+                    let pixel_col = left + (col * zoom + x) / shrink;
+                    let offset = ((pixel_row * max_width + pixel_col) * 4) as usize;
+                    pixels[offset..offset + 4].copy_from_slice(&ALIVE_RGBA);
+                }
+            }
+        }
+        pixels
+    }
+
+    /**
+     * Returns a reoriented copy of the pattern.
+     */
+    #[wasm_bindgen(js_name = transformed)]
+    pub fn transformed_js(&self, transform: Transform) -> Pattern {
+        self.transformed(transform)
+    }
+
+    /**
+     * Returns the `#N` name line of an RLE file.
+     */
+    pub fn name(&self) -> Option<String> {
+        self.metadata.name.clone()
+    }
+
+    /**
+     * Returns the `#O` line of an RLE file, naming the pattern's author.
+     */
+    pub fn author(&self) -> Option<String> {
+        self.metadata.author.clone()
+    }
+
+    /**
+     * Returns the `#C` comment lines of an RLE file, in order.
+     */
+    pub fn comments(&self) -> Vec<String> {
+        self.metadata.comments.clone()
+    }
+
+    /**
+     * Returns the rule given in the header of an RLE file, such as `B3/S23`.
+     */
+    pub fn rule(&self) -> Option<String> {
+        self.metadata.rule.clone()
+    }
+
+    /**
+     * Encode the pattern as RLE, writing back any metadata it was parsed
+     * with.
+     */
+    pub fn to_rle(&self) -> String {
+        rle::encode(self, self.metadata.rule.as_deref().unwrap_or("B3/S23"))
+    }
+
+    /**
+     * Returns the apgcode of a still life, oscillator or spaceship under
+     * B3/S23. Patterns that do not repeat within 1000 generations, or grow
+     * past 1024 live cells or 256 rows or columns first, have none.
+     */
+    pub fn to_apgcode(&self) -> Result<String, JsError> {
+        Ok(apgcode::encode(self)?)
+    }
+
+    pub fn to_plaintext(&self) -> String {
+        plaintext::encode(self)
+    }
+}
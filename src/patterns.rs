@@ -0,0 +1,155 @@
+//! Library of well known patterns, stored as RLE.
+
+use crate::rle;
+
+/// A named entry of the pattern library.
+pub(crate) struct LibraryPattern {
+    pub name: &'static str,
+    pub category: &'static str,
+    /// Generations before the pattern repeats, if it ever does.
+    pub period: Option<u32>,
+    pub rle: &'static str,
+}
+
+pub(crate) const LIBRARY: &[LibraryPattern] = &[
+    // Still lifes.
+    LibraryPattern {
+        name: "block",
+        category: "still life",
+        period: Some(1),
+        rle: "2o$2o!",
+    },
+    LibraryPattern {
+        name: "beehive",
+        category: "still life",
+        period: Some(1),
+        rle: "b2o$o2bo$b2o!",
+    },
+    LibraryPattern {
+        name: "loaf",
+        category: "still life",
+        period: Some(1),
+        rle: "b2o$o2bo$bobo$2bo!",
+    },
+    LibraryPattern {
+        name: "boat",
+        category: "still life",
+        period: Some(1),
+        rle: "2o$obo$bo!",
+    },
+    LibraryPattern {
+        name: "tub",
+        category: "still life",
+        period: Some(1),
+        rle: "bo$obo$bo!",
+    },
+    // Oscillators.
+    LibraryPattern {
+        name: "blinker",
+        category: "oscillator",
+        period: Some(2),
+        rle: "3o!",
+    },
+    LibraryPattern {
+        name: "toad",
+        category: "oscillator",
+        period: Some(2),
+        rle: "b3o$3o!",
+    },
+    LibraryPattern {
+        name: "beacon",
+        category: "oscillator",
+        period: Some(2),
+        rle: "2o$2o$2b2o$2b2o!",
+    },
+    LibraryPattern {
+        name: "pulsar",
+        category: "oscillator",
+        period: Some(3),
+        rle: "x = 13, y = 13\n2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!",
+    },
+    LibraryPattern {
+        name: "pentadecathlon",
+        category: "oscillator",
+        period: Some(15),
+        rle: "2bo4bo$2ob4ob2o$2bo4bo!",
+    },
+    // Spaceships.
+    LibraryPattern {
+        name: "glider",
+        category: "spaceship",
+        period: Some(4),
+        rle: "bob$2bo$3o!",
+    },
+    LibraryPattern {
+        name: "lwss",
+        category: "spaceship",
+        period: Some(4),
+        rle: "bo2bo$o4b$o3bo$4o!",
+    },
+    LibraryPattern {
+        name: "mwss",
+        category: "spaceship",
+        period: Some(4),
+        rle: "3bo$bo3bo$o$o4bo$5o!",
+    },
+    LibraryPattern {
+        name: "hwss",
+        category: "spaceship",
+        period: Some(4),
+        rle: "3b2o$bo4bo$o$o5bo$6o!",
+    },
+    // Methuselahs.
+    LibraryPattern {
+        name: "r-pentomino",
+        category: "methuselah",
+        period: None,
+        rle: "b2o$2o$bo!",
+    },
+    LibraryPattern {
+        name: "acorn",
+        category: "methuselah",
+        period: None,
+        rle: "bo$3bo$2o2b3o!",
+    },
+    LibraryPattern {
+        name: "diehard",
+        category: "methuselah",
+        period: None,
+        rle: "6bo$2o$bo3b3o!",
+    },
+    // Guns.
+    LibraryPattern {
+        name: "gosper-glider-gun",
+        category: "gun",
+        period: Some(30),
+        rle: "x = 36, y = 9\n24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!",
+    },
+];
+
+/// Look a pattern up by name, ignoring case.
+pub(crate) fn find(name: &str) -> Option<&'static LibraryPattern> {
+    LIBRARY.iter().find(|pattern| pattern.name.eq_ignore_ascii_case(name))
+}
+
+/// Describe the patterns whose name or category contains `query`, ignoring
+/// case, as a JSON array of `{name, size, period, category}` objects. An
+/// empty query matches every pattern.
+pub(crate) fn search(query: &str) -> String {
+    let query = query.trim().to_lowercase();
+    let entries: Vec<String> = LIBRARY
+        .iter()
+        .filter(|pattern| pattern.name.contains(&query) || pattern.category.contains(&query))
+        .map(|pattern| {
+            let (width, height) = rle::parse(pattern.rle)
+                .map(|cells| (cells.width(), cells.height()))
+                .unwrap_or((0, 0));
+            let period = pattern.period.map_or("null".to_string(), |period| period.to_string());
+            format!(
+                "{{\"name\":\"{}\",\"size\":{{\"width\":{},\"height\":{}}},\"period\":{},\"category\":\"{}\"}}",
+                pattern.name, width, height, period, pattern.category
+            )
+        })
+        .collect();
+    format!("[{}]", entries.join(","))
+}
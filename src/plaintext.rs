@@ -0,0 +1,59 @@
+//! The plaintext `.cells` pattern format.
+//!
+//! See <https://conwaylife.com/wiki/Plaintext>.
+
+use crate::pattern::Pattern;
+use crate::Error;
+
+/// Parse a plaintext pattern. Lines starting with `!` are comments, `.` is a
+/// dead cell and `O` (or `*`) a live one; short rows are padded with dead
+/// cells.
+pub(crate) fn parse(text: &str) -> Result<Pattern, Error> {
+    let mut rows = Vec::new();
+    for (line_idx, line) in text.lines().enumerate() {
+        if line.starts_with('!') {
+            continue;
+        }
+        let row = line
+            .trim_end()
+            .chars()
+            .enumerate()
+            .map(|(col, symbol)| match symbol {
+                'O' | '*' => Ok(true),
+                '.' => Ok(false),
+                other => Err(Error::Parse {
+                    line: line_idx + 1,
+                    message: format!("unexpected cell symbol {:?} in column {}", other, col + 1),
+                }),
+            })
+            .collect::<Result<Vec<bool>, Error>>()?;
+        rows.push(row);
+    }
+    while rows.last().is_some_and(Vec::is_empty) {
+        rows.pop();
+    }
+
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0) as u32;
+    let mut pattern = Pattern::new(width, rows.len() as u32).map_err(|err| Error::Parse {
+        line: text.lines().count(),
+        message: err.to_string(),
+    })?;
+    for (row, cells) in rows.iter().enumerate() {
+        for (col, &alive) in cells.iter().enumerate() {
+            pattern.set(row as u32, col as u32, alive);
+        }
+    }
+    Ok(pattern)
+}
+
+/// Encode `pattern` as plaintext, one full width line per row.
+pub(crate) fn encode(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    for row in 0..pattern.height() {
+        for col in 0..pattern.width() {
+            out.push(if pattern.get(row, col) { 'O' } else { '.' });
+        }
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,197 @@
+use crate::Rule;
+use fixedbitset::FixedBitSet;
+
+/// How `render` and the `Display` impl turn cells into text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenderStyle {
+    pub alive: char,
+    pub dead: char,
+    /// Used for cells in any dying state of a Generations rule.
+    pub dying: char,
+    /// Used for cells a mask leaves off the board.
+    pub missing: char,
+    /// Written between consecutive rows.
+    pub row_separator: String,
+    pub leading_newline: bool,
+    pub trailing_newline: bool,
+}
+
+impl Default for RenderStyle {
+    fn default() -> RenderStyle {
+        RenderStyle {
+            alive: '◻',
+            dead: '◼',
+            dying: '▣',
+            missing: ' ',
+            row_separator: "\n".to_string(),
+            leading_newline: true,
+            trailing_newline: false,
+        }
+    }
+}
+
+/// RGBA color of live cells in thumbnails, matching the demo's canvas.
+pub(crate) const ALIVE_RGBA: [u8; 4] = [0x00, 0x00, 0x00, 0xff];
+
+/// RGBA color of dead cells and of the margins of thumbnails.
+pub(crate) const DEAD_RGBA: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+
+/// RGBA color of cells a mask leaves off the board, fully transparent.
+pub(crate) const MISSING_RGBA: [u8; 4] = [0x00, 0x00, 0x00, 0x00];
+
+/// Brian's Brain colors by state: resting cells black, firing ones white
+/// and refractory ones blue.
+const BRIANS_BRAIN_RGBA: [[u8; 4]; 3] = [[0x00, 0x00, 0x00, 0xff], [0xff, 0xff, 0xff, 0xff], [0x30, 0x60, 0xff, 0xff]];
+
+/// Wireworld colors by state: empty cells black, electron heads blue, tails
+/// red and conductors yellow.
+const WIREWORLD_RGBA: [[u8; 4]; 4] = [
+    [0x00, 0x00, 0x00, 0xff],
+    [0x30, 0x60, 0xff, 0xff],
+    [0xff, 0x30, 0x20, 0xff],
+    [0xff, 0xc8, 0x00, 0xff],
+];
+
+/// Forest-fire colors by state: bare ground brown, trees green and fire
+/// orange.
+const FOREST_FIRE_RGBA: [[u8; 4]; 3] = [[0x3b, 0x2a, 0x1a, 0xff], [0x2e, 0x9e, 0x3e, 0xff], [0xff, 0x7a, 0x10, 0xff]];
+
+/// Sandpile colors by grains: none navy, then blue, yellow and red, and
+/// white for toppling cells.
+const SANDPILE_RGBA: [[u8; 4]; 5] = [
+    [0x10, 0x14, 0x30, 0xff],
+    [0x30, 0x70, 0xe0, 0xff],
+    [0xf0, 0xd0, 0x30, 0xff],
+    [0xd0, 0x30, 0x30, 0xff],
+    [0xff, 0xff, 0xff, 0xff],
+];
+
+/// SIR colors by state: susceptible cells pale, infected ones red and
+/// recovered ones blue.
+const SIR_RGBA: [[u8; 4]; 3] = [[0xf0, 0xf0, 0xe8, 0xff], [0xe0, 0x20, 0x20, 0xff], [0x30, 0x60, 0xe0, 0xff]];
+
+/// Colors of live cells by color under rules with colored ones, the first
+/// one being the usual live color.
+const COLORED_RGBA: [[u8; 4]; 4] = [
+    ALIVE_RGBA,
+    [0xe0, 0x30, 0x30, 0xff],
+    [0x30, 0x60, 0xe0, 0xff],
+    [0x30, 0xa0, 0x40, 0xff],
+];
+
+/// Colors of the cell states an ant rule has beyond the cell rule's, used in
+/// turn.
+const ANT_RGBA: [[u8; 4]; 6] = [
+    [0xe0, 0x30, 0x30, 0xff],
+    [0x30, 0x90, 0xe0, 0xff],
+    [0x30, 0xb0, 0x50, 0xff],
+    [0xf0, 0xb0, 0x20, 0xff],
+    [0x90, 0x40, 0xc0, 0xff],
+    [0x20, 0xb0, 0xb0, 0xff],
+];
+
+/// RGBA color of a cell in `state` under `rule`. Dying cells of Generations
+/// rules fade from the live color towards the dead one.
+pub(crate) fn state_rgba(rule: &Rule, state: u8) -> [u8; 4] {
+    if state >= rule.states() {
+        return ANT_RGBA[usize::from(state - rule.states()) % ANT_RGBA.len()];
+    }
+    if let Some([red, green, blue]) = rule.table().and_then(|table| table.color(state)) {
+        return [red, green, blue, 0xff];
+    }
+    if *rule == Rule::BRIANS_BRAIN {
+        return BRIANS_BRAIN_RGBA[usize::from(state.min(2))];
+    }
+    if *rule == Rule::WIREWORLD {
+        return WIREWORLD_RGBA[usize::from(state.min(3))];
+    }
+    if rule.is_forest_fire() {
+        return FOREST_FIRE_RGBA[usize::from(state.min(2))];
+    }
+    if rule.is_sandpile() {
+        return SANDPILE_RGBA[usize::from(state.min(4))];
+    }
+    if rule.is_sir() {
+        return SIR_RGBA[usize::from(state.min(2))];
+    }
+    if rule.colors().is_some() && state > 0 {
+        return COLORED_RGBA[usize::from(state - 1) % COLORED_RGBA.len()];
+    }
+    if rule.is_cyclic() {
+        return hue_rgba(u32::from(state), u32::from(rule.states()));
+    }
+    match state {
+        0 => DEAD_RGBA,
+        1 => ALIVE_RGBA,
+        dying => {
+            let (step, steps) = (u32::from(dying - 1), u32::from(rule.states() - 1));
+            let mut color = ALIVE_RGBA;
+            for (channel, (&alive, &dead)) in color.iter_mut().zip(ALIVE_RGBA.iter().zip(DEAD_RGBA.iter())) {
+                let (alive, dead) = (u32::from(alive), u32::from(dead));
+                *channel = ((alive * (steps - step) + dead * step) / steps) as u8;
+            }
+            color
+        }
+    }
+}
+
+/// RGBA pixels of cells that are only ever alive or dead, row by row.
+#[cfg(not(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128")))]
+pub(crate) fn two_state_rgba(cells: &FixedBitSet) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(cells.len() * 4);
+    for idx in 0..cells.len() {
+        pixels.extend_from_slice(if cells[idx] { &ALIVE_RGBA } else { &DEAD_RGBA });
+    }
+    pixels
+}
+
+/// RGBA pixels of cells that are only ever alive or dead, row by row, 4
+/// at a time.
+#[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+pub(crate) fn two_state_rgba(cells: &FixedBitSet) -> Vec<u8> {
+    use core::arch::wasm32::*;
+    let mut pixels = vec![0; cells.len() * 4];
+    let alive = u32x4_splat(u32::from_le_bytes(ALIVE_RGBA));
+    let dead = u32x4_splat(u32::from_le_bytes(DEAD_RGBA));
+    let mut groups = pixels.chunks_exact_mut(16);
+    for (group, chunk) in groups.by_ref().enumerate() {
+        let lane = |offset: usize| if cells[4 * group + offset] { u32::MAX } else { 0 };
+        let colors = v128_bitselect(alive, dead, u32x4(lane(0), lane(1), lane(2), lane(3)));
+        // SAFETY: the chunk is the 16 bytes stored to, which v128_store does
+        // not need aligned.
+        unsafe { v128_store(chunk.as_mut_ptr() as *mut v128, colors) };
+    }
+    let start = cells.len() / 4 * 4;
+    for (offset, pixel) in groups.into_remainder().chunks_exact_mut(4).enumerate() {
+        pixel.copy_from_slice(if cells[start + offset] { &ALIVE_RGBA } else { &DEAD_RGBA });
+    }
+    pixels
+}
+
+/// RGBA color of a cell at `level` under a continuous rule, from the dead
+/// color at 0 to the live one at 1.
+pub(crate) fn level_rgba(level: f32) -> [u8; 4] {
+    let mut color = ALIVE_RGBA;
+    for (channel, (&alive, &dead)) in color.iter_mut().zip(ALIVE_RGBA.iter().zip(DEAD_RGBA.iter())) {
+        *channel = (f32::from(dead) + (f32::from(alive) - f32::from(dead)) * level).round() as u8;
+    }
+    color
+}
+
+/// A fully saturated color `step` out of `steps` of the way around the color
+/// wheel, starting from red.
+fn hue_rgba(step: u32, steps: u32) -> [u8; 4] {
+    // Position on the wheel in sixths, scaled by 255.
+    let hue = step * 6 * 255 / steps;
+    let (sector, rise) = (hue / 255, (hue % 255) as u8);
+    let fall = 255 - rise;
+    let [red, green, blue] = match sector {
+        0 => [255, rise, 0],
+        1 => [fall, 255, 0],
+        2 => [0, 255, rise],
+        3 => [0, fall, 255],
+        4 => [rise, 0, 255],
+        _ => [255, 0, fall],
+    };
+    [red, green, blue, 0xff]
+}
@@ -0,0 +1,207 @@
+//! The Run Length Encoded pattern format used by LifeWiki and Golly.
+//!
+//! See <https://conwaylife.com/wiki/Run_Length_Encoded>.
+
+use crate::pattern::{Metadata, Pattern, MAX_CELLS};
+use crate::Error;
+
+/// Parse an RLE pattern. `#` comment lines and the `x = .., y = ..` header
+/// are optional; the pattern grows to fit its cells when the header is
+/// missing or too small. `#N`, `#O` and `#C` lines and the header's rule are
+/// kept as the pattern's metadata.
+pub(crate) fn parse(rle: &str) -> Result<Pattern, Error> {
+    let (mut width, mut height) = (0, 0);
+    let mut metadata = Metadata::default();
+    let mut live = Vec::new();
+    let (mut row, mut col) = (0u32, 0u32);
+    let mut run: Option<u32> = None;
+    let mut line_number = 0;
+
+    'lines: for (line_idx, line) in rle.lines().enumerate() {
+        line_number = line_idx + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('#') {
+            parse_comment(comment, &mut metadata);
+            continue;
+        }
+        if line.starts_with('x') {
+            let (x, y, rule) = parse_header(line, line_number)?;
+            width = x;
+            height = y;
+            metadata.rule = rule;
+            continue;
+        }
+
+        for symbol in line.chars() {
+            let count = run.unwrap_or(1);
+            match symbol {
+                '0'..='9' => {
+                    let digit = symbol.to_digit(10).unwrap();
+                    let extended = run
+                        .unwrap_or(0)
+                        .checked_mul(10)
+                        .and_then(|run| run.checked_add(digit))
+                        .ok_or_else(|| parse_error(line_number, "run count is too large"))?;
+                    run = Some(extended);
+                    continue;
+                }
+                'b' | '.' => col = advance(col, count, line_number)?,
+                'o' | 'A'..='X' => {
+                    let end = advance(col, count, line_number)?;
+                    if (u64::from(row) + 1) * u64::from(end) > MAX_CELLS {
+                        return Err(parse_error(line_number, "pattern is too large"));
+                    }
+                    live.extend((col..end).map(|col| (row, col)));
+                    col = end;
+                }
+                '$' => {
+                    row = advance(row, count, line_number)?;
+                    col = 0;
+                }
+                '!' => break 'lines,
+                symbol if symbol.is_whitespace() => continue,
+                other => {
+                    return Err(parse_error(line_number, &format!("unexpected symbol {:?}", other)));
+                }
+            }
+            width = width.max(col);
+            run = None;
+        }
+    }
+
+    for &(row, col) in live.iter() {
+        width = width.max(col + 1);
+        height = height.max(row + 1);
+    }
+    let mut pattern = Pattern::new(width, height).map_err(|err| parse_error(line_number, &err.to_string()))?;
+    for (row, col) in live {
+        pattern.set(row, col, true);
+    }
+    pattern.set_metadata(metadata);
+    Ok(pattern)
+}
+
+/// The row or column `count` cells on from `coordinate`, unless that is
+/// past the last one.
+fn advance(coordinate: u32, count: u32, line_number: usize) -> Result<u32, Error> {
+    coordinate
+        .checked_add(count)
+        .ok_or_else(|| parse_error(line_number, "pattern is too large"))
+}
+
+/// Record a `#` line, given without its `#`, into `metadata`. Other kinds of
+/// comment lines are ignored.
+fn parse_comment(line: &str, metadata: &mut Metadata) {
+    let mut chars = line.chars();
+    let kind = chars.next();
+    let text = chars.as_str().trim().to_string();
+    match kind {
+        Some('N') => metadata.name = Some(text),
+        Some('O') => metadata.author = Some(text),
+        Some('C') | Some('c') => metadata.comments.push(text),
+        _ => {}
+    }
+}
+
+/// Lines of encoded cells are wrapped before reaching this length.
+const MAX_LINE_LENGTH: usize = 70;
+
+/// Encode `pattern` with an `x = .., y = .., rule = ..` header, preceded by
+/// the `#N`, `#O` and `#C` lines of its metadata.
+pub(crate) fn encode(pattern: &Pattern, rule: &str) -> String {
+    let mut tokens = Vec::new();
+    // Row ends owed before the next row with live cells.
+    let mut newlines = 0;
+    for row in 0..pattern.height() {
+        let mut runs: Vec<(u32, char)> = Vec::new();
+        for col in 0..pattern.width() {
+            let tag = if pattern.get(row, col) { 'o' } else { 'b' };
+            match runs.last_mut() {
+                Some((count, last)) if *last == tag => *count += 1,
+                _ => runs.push((1, tag)),
+            }
+        }
+        // Dead cells at the end of a row are implied.
+        if let Some((_, 'b')) = runs.last() {
+            runs.pop();
+        }
+        if runs.is_empty() {
+            newlines += 1;
+            continue;
+        }
+        if newlines > 0 {
+            tokens.push(run_token(newlines, '$'));
+        }
+        tokens.extend(runs.into_iter().map(|(count, tag)| run_token(count, tag)));
+        newlines = 1;
+    }
+    tokens.push("!".to_string());
+
+    let metadata = pattern.metadata();
+    let mut out = String::new();
+    if let Some(name) = &metadata.name {
+        out.push_str(&format!("#N {}\n", name));
+    }
+    if let Some(author) = &metadata.author {
+        out.push_str(&format!("#O {}\n", author));
+    }
+    for comment in metadata.comments.iter() {
+        out.push_str(&format!("#C {}\n", comment));
+    }
+    out.push_str(&format!("x = {}, y = {}, rule = {}\n", pattern.width(), pattern.height(), rule));
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length + token.len() > MAX_LINE_LENGTH {
+            out.push('\n');
+            line_length = 0;
+        }
+        line_length += token.len();
+        out.push_str(&token);
+    }
+    out.push('\n');
+    out
+}
+
+fn run_token(count: u32, tag: char) -> String {
+    if count == 1 {
+        tag.to_string()
+    } else {
+        format!("{}{}", count, tag)
+    }
+}
+
+/// Parse the `x = 3, y = 3, rule = B3/S23` header line into its size and
+/// optional rule.
+fn parse_header(line: &str, line_number: usize) -> Result<(u32, u32, Option<String>), Error> {
+    let (mut x, mut y, mut rule) = (None, None, None);
+    for field in line.split(',') {
+        let mut parts = field.splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+        let value = parts.next().map(str::trim);
+        let parse_size = |value: Option<&str>| {
+            value
+                .and_then(|value| value.parse::<u32>().ok())
+                .ok_or_else(|| parse_error(line_number, &format!("invalid {} in header", key)))
+        };
+        match key {
+            "x" => x = Some(parse_size(value)?),
+            "y" => y = Some(parse_size(value)?),
+            "rule" => rule = value.filter(|value| !value.is_empty()).map(str::to_string),
+            _ => {}
+        }
+    }
+    match (x, y) {
+        (Some(x), Some(y)) => Ok((x, y, rule)),
+        _ => Err(parse_error(line_number, "header must contain both x and y")),
+    }
+}
+
+fn parse_error(line: usize, message: &str) -> Error {
+    Error::Parse {
+        line,
+        message: message.to_string(),
+    }
+}
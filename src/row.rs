@@ -0,0 +1,68 @@
+use fixedbitset::FixedBitSet;
+
+/// A borrowed view of one row of a universe.
+#[derive(Clone, Copy, Debug)]
+pub struct Row<'a> {
+    cells: &'a FixedBitSet,
+    start: usize,
+    width: usize,
+}
+
+impl<'a> Row<'a> {
+    pub(crate) fn new(cells: &'a FixedBitSet, start: usize, width: usize) -> Row<'a> {
+        Row { cells, start, width }
+    }
+
+    pub fn len(&self) -> usize {
+        self.width
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0
+    }
+
+    /// Returns whether the cell in `col` is alive; out of range columns are dead.
+    pub fn get(&self, col: u32) -> bool {
+        (col as usize) < self.width && self.cells[self.start + col as usize]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + 'a {
+        let (cells, start) = (self.cells, self.start);
+        (start..start + self.width).map(move |idx| cells[idx])
+    }
+
+    /// The row packed into 32 bit words, column 0 being the lowest bit of the
+    /// first word. Bits past the end of the row are zero.
+    pub fn words(&self) -> impl Iterator<Item = u32> + 'a {
+        let blocks = self.cells.as_slice();
+        let (start, width) = (self.start, self.width);
+        (0..width.div_ceil(32)).map(move |word| {
+            let offset = start + word * 32;
+            let (block, shift) = (offset / 32, offset % 32);
+            let mut bits = blocks[block] >> shift;
+            if shift != 0 && block + 1 < blocks.len() {
+                bits |= blocks[block + 1] << (32 - shift);
+            }
+            let remaining = width - word * 32;
+            if remaining < 32 {
+                bits &= (1 << remaining) - 1;
+            }
+            bits
+        })
+    }
+
+    /// Columns of the live cells in the row, in increasing order.
+    pub fn live_columns(&self) -> impl Iterator<Item = u32> + 'a {
+        self.words().enumerate().flat_map(|(word, bits)| {
+            let mut bits = bits;
+            std::iter::from_fn(move || {
+                if bits == 0 {
+                    return None;
+                }
+                let bit = bits.trailing_zeros();
+                bits &= bits - 1;
+                Some(word as u32 * 32 + bit)
+            })
+        })
+    }
+}
@@ -0,0 +1,1016 @@
+//! Life-like rules written in B/S notation, such as Conway's `B3/S23`,
+//! their Generations extension where dying cells fade through extra states,
+//! weighted rules where some neighbors count more than others, Immigration
+//! and QuadLife with colored live cells, Wireworld, cyclic rules, Wolfram's one-dimensional elementary rules,
+//! alternating rules switching between others from one generation to the
+//! next, the Drossel-Schwabl forest-fire model, an SIR epidemic, block rules
+//! on the Margolus neighborhood, the abelian sandpile, and Lenia and
+//! SmoothLife.
+//!
+//! See <https://conwaylife.com/wiki/Rulestring>,
+//! <https://en.wikipedia.org/wiki/Abelian_sandpile_model>,
+//! <https://en.wikipedia.org/wiki/Block_cellular_automaton>,
+//! <https://en.wikipedia.org/wiki/Forest-fire_model>,
+//! <https://en.wikipedia.org/wiki/Compartmental_models_in_epidemiology>,
+//! <https://conwaylife.com/wiki/Generations>,
+//! <https://conwaylife.com/wiki/Colourised_Life>,
+//! <https://en.wikipedia.org/wiki/Cyclic_cellular_automaton> and
+//! <https://mathworld.wolfram.com/ElementaryCellularAutomaton.html>.
+
+use crate::continuous::Continuous;
+use crate::table::{self, RuleTable, TableNeighborhood};
+use crate::Error;
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// How cells change state from one generation to the next.
+///
+/// Cell state 1 is the live state counted as a neighbor, 0 is empty, and
+/// rules with more than two states give the others their own meaning.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rule(Kind);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Kind {
+    /// Which neighbor counts bring a dead cell to life and which keep a live
+    /// one alive, as bitmasks where bit `n` stands for `n` live neighbors.
+    /// Under Generations rules, with more than two `states`, a live cell that
+    /// does not survive goes through the dying states 2 up to `states - 1`,
+    /// one per generation, before being dead again.
+    Totalistic {
+        birth: u16,
+        survival: u16,
+        states: u8,
+        neighborhood: Neighborhood,
+    },
+    /// A weighted rule: like a Generations rule, but every live cell at one
+    /// of the offsets in `weights` adds its weight to the count, and birth
+    /// and survival are lists of counts.
+    Weighted {
+        weights: Vec<(i32, i32, u32)>,
+        birth: Vec<u32>,
+        survival: Vec<u32>,
+        states: u8,
+    },
+    /// Conway's Life with `colors` colors of live cells, states 1 up to
+    /// `colors`: cells are born in the color most of their parents have, or
+    /// with three parents all different the one color none of them has, and
+    /// survivors keep theirs.
+    Colored(u8),
+    /// Wireworld: electron heads (1) become tails (2), tails become
+    /// conductors (3), and conductors next to one or two heads become heads.
+    Wireworld,
+    /// A one-dimensional elementary rule by its Wolfram number: bit `n` is the
+    /// next state of a cell whose left neighbor, itself and right neighbor
+    /// read `n` as three binary digits.
+    Elementary(u8),
+    /// A cyclic rule: a cell in state `k` advances to `k + 1`, wrapping back
+    /// to 0 after `states - 1`, once at least `threshold` of its neighbors
+    /// are in that next state.
+    Cyclic {
+        threshold: u8,
+        states: u8,
+        neighborhood: Neighborhood,
+    },
+    /// A Larger than Life rule: like a Generations rule, but counting the
+    /// live cells in the square reaching `range` cells out, the cell itself
+    /// included when `middle` is set, and giving birth and survival as
+    /// ranges of counts.
+    LargerThanLife {
+        range: u16,
+        middle: bool,
+        birth: (u32, u32),
+        survival: (u32, u32),
+        states: u8,
+    },
+    /// A rule loaded from a Golly rule table.
+    Table(Rc<RuleTable>),
+    /// The Drossel-Schwabl forest-fire model: trees (1) grow on empty cells
+    /// with chance `growth`, catch fire (2) from a burning von Neumann
+    /// neighbor or else from lightning with chance `lightning`, and burn
+    /// down to empty cells.
+    ForestFire { growth: Chance, lightning: Chance },
+    /// An SIR epidemic: susceptible cells (0) catch the infection (1) from
+    /// each infected Moore neighbor with chance `infection`, infected cells
+    /// recover with chance `recovery`, and recovered cells (2 and up) stay
+    /// immune for `immunity` generations, a state each, before being
+    /// susceptible again. An `immunity` of 0 lasts for ever.
+    Sir {
+        infection: Chance,
+        recovery: Chance,
+        immunity: u8,
+    },
+    /// A block rule: the grid is split into 2x2 blocks, shifted one cell
+    /// down and right every other generation, and a block whose cells read
+    /// `n` as four binary digits, the top left cell being the lowest one
+    /// followed by top right, bottom left and bottom right, becomes block
+    /// `blocks[n]`.
+    Margolus([u8; 16]),
+    /// The abelian sandpile: cell states 0 to 3 are grains of sand, and a
+    /// cell with 4 or more, all in state 4, topples by handing one grain to
+    /// each of its von Neumann neighbors.
+    Sandpile,
+    /// Lenia or SmoothLife, whose cells hold continuous levels.
+    Continuous(Continuous),
+    /// Rules taking turns, generation `n` being computed by rule `n` modulo
+    /// their number. They all have the same number of states and none is
+    /// elementary, a block rule, the sandpile or continuous.
+    Alternating(Vec<Rule>),
+}
+
+/// A probability, compared and hashed by its bits so that rules holding one
+/// can be too.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Chance(pub f64);
+
+impl PartialEq for Chance {
+    fn eq(&self, other: &Chance) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for Chance {}
+
+impl Hash for Chance {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl FromStr for Chance {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Chance, ()> {
+        match text.parse() {
+            Ok(chance) if (0.0..=1.0).contains(&chance) => Ok(Chance(chance)),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Wireworld cell states besides empty, 0.
+pub(crate) const WIREWORLD_HEAD: u8 = 1;
+pub(crate) const WIREWORLD_TAIL: u8 = 2;
+pub(crate) const WIREWORLD_CONDUCTOR: u8 = 3;
+
+/// The sandpile state of cells with enough grains to topple.
+pub(crate) const SAND_TOPPLING: u8 = 4;
+
+/// Forest-fire cell states besides empty, 0.
+pub(crate) const FOREST_TREE: u8 = 1;
+pub(crate) const FOREST_FIRE: u8 = 2;
+
+/// Chances of a tree growing and of lightning per cell and generation in
+/// the `ForestFire` preset.
+const FOREST_GROWTH: Chance = Chance(0.05);
+const FOREST_LIGHTNING: Chance = Chance(0.0001);
+
+/// SIR cell states besides susceptible, 0.
+pub(crate) const SIR_INFECTED: u8 = 1;
+pub(crate) const SIR_RECOVERED: u8 = 2;
+
+/// Chances of catching the infection from a neighbor and of recovering per
+/// generation in the `SIR` preset.
+const SIR_INFECTION: Chance = Chance(0.3);
+const SIR_RECOVERY: Chance = Chance(0.1);
+
+/// The cells whose states a cell's next state depends on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Neighborhood {
+    /// The 8 cells around a cell.
+    Moore,
+    /// The 4 cells sharing an edge with a cell.
+    VonNeumann,
+    /// The 6 cells around a cell of a hexagonal grid laid out with odd rows
+    /// shifted half a cell right.
+    Hexagonal,
+}
+
+const MOORE: [(i32, i32); 8] = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+const VON_NEUMANN: [(i32, i32); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+const HEXAGONAL_EVEN_ROW: [(i32, i32); 6] = [(-1, -1), (-1, 0), (0, -1), (0, 1), (1, -1), (1, 0)];
+const HEXAGONAL_ODD_ROW: [(i32, i32); 6] = [(-1, 0), (-1, 1), (0, -1), (0, 1), (1, 0), (1, 1)];
+
+impl Neighborhood {
+    /// The row and column offsets of the neighbors of a cell on `row`.
+    pub fn offsets(self, row: u32) -> &'static [(i32, i32)] {
+        match self {
+            Neighborhood::Moore => &MOORE,
+            Neighborhood::VonNeumann => &VON_NEUMANN,
+            Neighborhood::Hexagonal if row.is_multiple_of(2) => &HEXAGONAL_EVEN_ROW,
+            Neighborhood::Hexagonal => &HEXAGONAL_ODD_ROW,
+        }
+    }
+
+    /// Number of neighbors of every cell.
+    pub fn size(self) -> u8 {
+        self.offsets(0).len() as u8
+    }
+}
+
+impl Rule {
+    /// Conway's Game of Life, `B3/S23`.
+    pub const CONWAY: Rule = Rule(Kind::Totalistic {
+        birth: 1 << 3,
+        survival: 1 << 2 | 1 << 3,
+        states: 2,
+        neighborhood: Neighborhood::Moore,
+    });
+
+    /// Brian's Brain, `B2/S/C3`: every firing cell goes refractory for one
+    /// generation, and cells fire with exactly two firing neighbors.
+    pub const BRIANS_BRAIN: Rule = Rule(Kind::Totalistic {
+        birth: 1 << 2,
+        survival: 0,
+        states: 3,
+        neighborhood: Neighborhood::Moore,
+    });
+
+    /// Brian Silverman's Wireworld, for building digital circuits.
+    pub const WIREWORLD: Rule = Rule(Kind::Wireworld);
+
+    /// Immigration, Life with live cells in two colors.
+    pub const IMMIGRATION: Rule = Rule(Kind::Colored(2));
+
+    /// QuadLife, Life with live cells in four colors.
+    pub const QUADLIFE: Rule = Rule(Kind::Colored(4));
+
+    /// The abelian sandpile.
+    pub const SANDPILE: Rule = Rule(Kind::Sandpile);
+
+    /// The forest-fire model with chances suited to a look on screen.
+    pub const FOREST_FIRE: Rule = Rule(Kind::ForestFire {
+        growth: FOREST_GROWTH,
+        lightning: FOREST_LIGHTNING,
+    });
+
+    /// An SIR epidemic with lasting immunity.
+    pub const SIR: Rule = Rule(Kind::Sir {
+        infection: SIR_INFECTION,
+        recovery: SIR_RECOVERY,
+        immunity: 0,
+    });
+
+    /// Number of cell states, 2 for Life-like rules.
+    pub fn states(&self) -> u8 {
+        match self.0 {
+            Kind::Totalistic { states, .. } | Kind::Weighted { states, .. } => states,
+            Kind::Colored(colors) => colors + 1,
+            Kind::Wireworld => 4,
+            Kind::Sandpile => SAND_TOPPLING + 1,
+            Kind::ForestFire { .. } => 3,
+            Kind::Sir { immunity, .. } => SIR_RECOVERED + immunity.max(1),
+            Kind::Elementary(_) | Kind::Margolus(_) | Kind::Continuous(_) => 2,
+            Kind::Cyclic { states, .. } | Kind::LargerThanLife { states, .. } => states,
+            Kind::Table(ref table) => table.states,
+            Kind::Alternating(ref rules) => rules[0].states(),
+        }
+    }
+
+    /// The rule computing the generation after `generation`, which is the
+    /// rule itself unless it is alternating.
+    pub fn at(&self, generation: u64) -> &Rule {
+        match self.0 {
+            Kind::Alternating(ref rules) => &rules[(generation % rules.len() as u64) as usize],
+            _ => self,
+        }
+    }
+
+    /// A rule from the text of a Golly `.rule` file with a `@TABLE` section,
+    /// or of a `.table` file.
+    pub fn from_table(text: &str) -> Result<Rule, Error> {
+        Ok(Rule(Kind::Table(Rc::new(table::parse(text)?))))
+    }
+
+    /// The rule table of a rule loaded from one, which needs the state of
+    /// every neighbor rather than a count.
+    pub(crate) fn table(&self) -> Option<&RuleTable> {
+        match self.0 {
+            Kind::Table(ref table) => Some(table),
+            _ => None,
+        }
+    }
+
+    /// How far the square neighborhood of a Larger than Life rule reaches
+    /// from its middle and whether it counts the middle cell.
+    pub(crate) fn square_range(&self) -> Option<(u32, bool)> {
+        match self.0 {
+            Kind::LargerThanLife { range, middle, .. } => Some((u32::from(range), middle)),
+            _ => None,
+        }
+    }
+
+    /// Whether cells can follow either this rule or `other` within the same
+    /// generation, both counting the same neighbors in the same way and
+    /// having the same states.
+    pub(crate) fn blends_with(&self, other: &Rule) -> bool {
+        let kind = |rule: &Rule| match rule.0 {
+            Kind::Totalistic { .. } | Kind::LargerThanLife { .. } | Kind::Wireworld => Some(false),
+            Kind::Cyclic { .. } => Some(true),
+            _ => None,
+        };
+        kind(self).is_some()
+            && kind(self) == kind(other)
+            && self.states() == other.states()
+            && self.neighborhood() == other.neighborhood()
+            && self.square_range() == other.square_range()
+    }
+
+    /// Whether this is the forest-fire model, whose states are empty ground,
+    /// trees and fire.
+    pub fn is_forest_fire(&self) -> bool {
+        matches!(self.0, Kind::ForestFire { .. })
+    }
+
+    /// The continuous rule this is, whose cells hold levels from 0 to 1,
+    /// state 1 standing for levels from one half up.
+    pub(crate) fn continuous(&self) -> Option<Continuous> {
+        match self.0 {
+            Kind::Continuous(continuous) => Some(continuous),
+            _ => None,
+        }
+    }
+
+    /// Number of colors of a rule with colored live cells, whose dynamics
+    /// are Life's whatever their colors.
+    pub(crate) fn colors(&self) -> Option<u8> {
+        match self.0 {
+            Kind::Colored(colors) => Some(colors),
+            _ => None,
+        }
+    }
+
+    /// Whether this is a two state B/S rule on the Moore neighborhood
+    /// without B0, which keeps empty space empty and so can run on an
+    /// unbounded grid.
+    pub(crate) fn is_unbounded(&self) -> bool {
+        match self.0 {
+            Kind::Totalistic {
+                birth,
+                states,
+                neighborhood,
+                ..
+            } => states == 2 && neighborhood == Neighborhood::Moore && !has_count(birth, 0),
+            _ => false,
+        }
+    }
+
+    /// Whether this is a two state B/S rule counting the 8 Moore neighbors.
+    pub(crate) fn is_life_like(&self) -> bool {
+        self.life_masks().is_some()
+    }
+
+    /// The birth and survival bitmasks of a two state B/S rule counting the
+    /// 8 Moore neighbors.
+    pub(crate) fn life_masks(&self) -> Option<(u16, u16)> {
+        match self.0 {
+            Kind::Totalistic {
+                birth,
+                survival,
+                states: 2,
+                neighborhood: Neighborhood::Moore,
+            } => Some((birth, survival)),
+            _ => None,
+        }
+    }
+
+    /// Whether this is the sandpile, whose cells hold grains of sand rather
+    /// than change by their neighbors' states.
+    pub fn is_sandpile(&self) -> bool {
+        matches!(self.0, Kind::Sandpile)
+    }
+
+    /// Whether this is an SIR epidemic, whose states are susceptible,
+    /// infected and recovered cells.
+    pub fn is_sir(&self) -> bool {
+        matches!(self.0, Kind::Sir { .. })
+    }
+
+    /// Whether this is a cyclic rule, whose states are all alike.
+    pub fn is_cyclic(&self) -> bool {
+        matches!(self.0, Kind::Cyclic { .. })
+    }
+
+    /// Which cells around a cell are its neighbors.
+    pub(crate) fn neighborhood(&self) -> Neighborhood {
+        match self.0 {
+            Kind::Totalistic { neighborhood, .. } | Kind::Cyclic { neighborhood, .. } => neighborhood,
+            Kind::ForestFire { .. } | Kind::Sandpile => Neighborhood::VonNeumann,
+            Kind::Table(ref table) if table.neighborhood == TableNeighborhood::VonNeumann => Neighborhood::VonNeumann,
+            _ => Neighborhood::Moore,
+        }
+    }
+
+    /// The state of the neighbors counted for a cell in `state`: the live
+    /// state 1, or under cyclic rules the state the cell would advance to.
+    pub fn counted_state(&self, state: u8) -> u8 {
+        match self.0 {
+            Kind::Cyclic { states, .. } => ((u16::from(state) + 1) % u16::from(states)) as u8,
+            Kind::ForestFire { .. } => FOREST_FIRE,
+            _ => 1,
+        }
+    }
+
+    /// The Wolfram number of a one-dimensional elementary rule, which grows
+    /// a row at a time instead of updating the whole grid.
+    pub fn elementary(&self) -> Option<u8> {
+        match self.0 {
+            Kind::Elementary(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    /// Offsets of the neighbors of a weighted rule, the cell itself being
+    /// (0, 0), with the weight each adds to the count when alive.
+    pub(crate) fn weights(&self) -> Option<&[(i32, i32, u32)]> {
+        match self.0 {
+            Kind::Weighted { ref weights, .. } => Some(weights),
+            _ => None,
+        }
+    }
+
+    /// What every block becomes under a block rule, which updates 2x2
+    /// blocks at once rather than single cells.
+    pub(crate) fn blocks(&self) -> Option<[u8; 16]> {
+        match self.0 {
+            Kind::Margolus(blocks) => Some(blocks),
+            _ => None,
+        }
+    }
+
+    /// The state of a cell in `state` after one generation with `neighbors`
+    /// neighbors in its `counted_state`. For elementary rules `neighbors` is
+    /// instead the row around the cell read as three binary digits.
+    pub fn next(&self, state: u8, neighbors: u32) -> u8 {
+        let (born, survives, states) = match self.0 {
+            Kind::Totalistic { birth, survival, states, .. } => {
+                (has_count(birth, neighbors), has_count(survival, neighbors), states)
+            }
+            Kind::Weighted {
+                ref birth,
+                ref survival,
+                states,
+                ..
+            } => (birth.contains(&neighbors), survival.contains(&neighbors), states),
+            Kind::LargerThanLife {
+                birth: (birth_min, birth_max),
+                survival: (survival_min, survival_max),
+                states,
+                ..
+            } => (
+                (birth_min..=birth_max).contains(&neighbors),
+                (survival_min..=survival_max).contains(&neighbors),
+                states,
+            ),
+            _ => return self.next_other(state, neighbors),
+        };
+        match state {
+            0 => born as u8,
+            1 if survives => 1,
+            dying if dying + 1 < states => dying + 1,
+            _ => 0,
+        }
+    }
+
+    /// For a cell that `next` leaves in `state` with `neighbors` neighbors
+    /// in its `counted_state`, the chance that it goes to another state
+    /// anyway and that state, under probabilistic rules.
+    pub(crate) fn chance(&self, state: u8, neighbors: u32) -> Option<(f64, u8)> {
+        match self.0 {
+            Kind::Sir { infection, .. } if state == 0 && neighbors > 0 => {
+                let escapes = (1.0 - infection.0).powi(neighbors as i32);
+                Some((1.0 - escapes, SIR_INFECTED))
+            }
+            Kind::Sir { recovery, .. } if state == SIR_INFECTED => Some((recovery.0, SIR_RECOVERED)),
+            Kind::ForestFire { growth, .. } if state == 0 => Some((growth.0, FOREST_TREE)),
+            Kind::ForestFire { lightning, .. } if state == FOREST_TREE => Some((lightning.0, FOREST_FIRE)),
+            _ => None,
+        }
+    }
+
+    /// `next` for rules other than the birth and survival ones.
+    fn next_other(&self, state: u8, neighbors: u32) -> u8 {
+        match self.0 {
+            Kind::Wireworld => match state {
+                0 => 0,
+                WIREWORLD_HEAD => WIREWORLD_TAIL,
+                WIREWORLD_TAIL => WIREWORLD_CONDUCTOR,
+                _ if neighbors == 1 || neighbors == 2 => WIREWORLD_HEAD,
+                _ => WIREWORLD_CONDUCTOR,
+            },
+            Kind::Elementary(number) => (number >> neighbors) & 1,
+            Kind::ForestFire { .. } => match state {
+                FOREST_TREE if neighbors > 0 => FOREST_FIRE,
+                FOREST_FIRE => 0,
+                _ => state,
+            },
+            Kind::Sir { immunity, .. } => match state {
+                0 | SIR_INFECTED => state,
+                _ if immunity == 0 => state,
+                immune if immune + 1 < SIR_RECOVERED + immunity => immune + 1,
+                _ => 0,
+            },
+            Kind::Cyclic { threshold, .. } if neighbors >= u32::from(threshold) => self.counted_state(state),
+            _ => state,
+        }
+    }
+}
+
+/// Whether the bitmask of neighbor counts `mask` has `count`, counts beyond
+/// the mask never being in it.
+fn has_count(mask: u16, count: u32) -> bool {
+    1u16.checked_shl(count).is_some_and(|bit| mask & bit != 0)
+}
+
+/// Famous rules by name, in the order offered to users.
+pub(crate) const PRESETS: &[(&str, &str)] = &[
+    ("Conway", "B3/S23"),
+    ("HighLife", "B36/S23"),
+    ("Seeds", "B2/S"),
+    ("DayAndNight", "B3678/S34678"),
+    ("LifeWithoutDeath", "B3/S012345678"),
+    ("Maze", "B3/S12345"),
+    ("Mazectric", "B3/S1234"),
+    ("2x2", "B36/S125"),
+    ("Morley", "B368/S245"),
+    ("Replicator", "B1357/S1357"),
+    ("Diamoeba", "B35678/S5678"),
+    ("Amoeba", "B357/S1358"),
+    ("Anneal", "B4678/S35678"),
+    ("Coral", "B3/S45678"),
+    ("Gnarl", "B1/S1"),
+    ("LongLife", "B345/S5"),
+    ("Serviettes", "B234/S"),
+    ("StarWars", "B2/S345/C4"),
+    ("BriansBrain", "B2/S/C3"),
+    ("Wireworld", "Wireworld"),
+    ("Immigration", "Immigration"),
+    ("QuadLife", "QuadLife"),
+    ("ForestFire", "ForestFire"),
+    ("Sandpile", "Sandpile"),
+    ("Lenia", "Lenia"),
+    ("SmoothLife", "SmoothLife"),
+    ("SIR", "SIR"),
+    ("Rule30", "W30"),
+    ("Rule90", "W90"),
+    ("Rule110", "W110"),
+    ("Critters", "MS,D15;14;13;3;11;5;6;1;7;9;10;2;12;4;8;0"),
+    ("Tron", "MS,D15;1;2;3;4;5;6;7;8;9;10;11;12;13;14;0"),
+    ("Cyclic", "R1/T1/C14/NN"),
+    ("CyclicSpirals", "R1/T3/C3/NM"),
+    ("Bosco", "R5,C0,M1,S34..58,B34..45,NM"),
+    ("Majority", "R4,C0,M1,S41..81,B41..81,NM"),
+    ("Waffle", "R7,C0,M1,S100..200,B75..170,NM"),
+];
+
+impl Rule {
+    /// Name of the preset with this rule, if any.
+    pub fn preset_name(&self) -> Option<&'static str> {
+        PRESETS
+            .iter()
+            .find(|(_, rulestring)| rulestring.parse().as_ref() == Ok(self))
+            .map(|&(name, _)| name)
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Rule {
+        Rule::CONWAY
+    }
+}
+
+impl FromStr for Rule {
+    type Err = Error;
+
+    /// Parse `B3/S23` in any case, with the slash optional and the parts in
+    /// either order, the older survival/birth form `23/3`, or the name of a
+    /// preset such as `HighLife`, ignoring case. Generations rules add their
+    /// number of states as in `B2/S345/C4` or `345/2/4`. Elementary rules are
+    /// written as in Golly, `W` and their Wolfram number, like `W110`, and
+    /// cyclic rules as in MCell, like `R1/T3/C3/NM` for a range 1 Moore
+    /// neighborhood, a threshold of 3 and 3 states, `NN` standing for the von
+    /// Neumann neighborhood. A trailing `H` makes a B/S rule run on a
+    /// hexagonal grid, as in `B2/S34H`, and a trailing `V` makes it count
+    /// only the von Neumann neighbors, as in `B2/S013V`. Larger than Life
+    /// rules are written as in Golly, like `R5,C0,M1,S34..58,B34..45,NM` for
+    /// Bosco's Rule. The forest-fire model is `ForestFire`, optionally with
+    /// the chances of growth and lightning as in `ForestFire/G0.01/L0.00001`,
+    /// and an SIR epidemic is `SIR`, optionally with the chances of infection
+    /// and recovery and the generations of immunity as in `SIR/I0.2/R0.05/D30`.
+    /// Life with two and four colors of live cells is `Immigration` and
+    /// `QuadLife`. The sandpile is `Sandpile`. Lenia is `Lenia` for the rule of Orbium,
+    /// or with its kernel radius, growth center and width and time steps as
+    /// in `Lenia/R13/M0.15/S0.015/T10`, and SmoothLife is `SmoothLife`, or
+    /// with its outer radius and birth and death intervals as in
+    /// `SmoothLife/R21/B0.278-0.365/D0.267-0.445`. Block rules are written as
+    /// in MCell, `MS,D` and what each of the 16 blocks becomes, like
+    /// `MS,D15;14;13;3;11;5;6;1;7;9;10;2;12;4;8;0` for Critters. Weighted
+    /// rules are written as in MCell too, with the weights of the neighbors
+    /// from the top left one, `NW`, through the cell itself, `ME`, to the
+    /// bottom right one, `SE`, the number of states as `HI`, 0 standing for
+    /// 2, and each count for survival and birth, like
+    /// `NW1,NN1,NE1,WW1,ME0,EE1,SW1,SS1,SE1,HI0,RS2,RS3,RB3` for Conway's
+    /// Life. Alternating rules are separated by `|` as in Golly, like
+    /// `B3/S23|B36/S23`.
+    fn from_str(rulestring: &str) -> Result<Rule, Error> {
+        let invalid = || Error::InvalidRule(rulestring.to_string());
+        if rulestring.contains('|') {
+            let rules = rulestring.split('|').map(str::parse).collect::<Result<Vec<Rule>, Error>>()?;
+            let states = rules[0].states();
+            let steps_cells = |rule: &Rule| {
+                rule.elementary().is_none()
+                    && rule.blocks().is_none()
+                    && rule.colors().is_none()
+                    && !rule.is_sandpile()
+                    && rule.continuous().is_none()
+            };
+            if rules.iter().any(|rule| rule.states() != states || !steps_cells(rule)) {
+                return Err(invalid());
+            }
+            return Ok(Rule(Kind::Alternating(rules)));
+        }
+        let text = rulestring.trim().to_ascii_uppercase();
+        if text == "WIREWORLD" {
+            return Ok(Rule::WIREWORLD);
+        }
+        if text == "IMMIGRATION" {
+            return Ok(Rule::IMMIGRATION);
+        }
+        if text == "QUADLIFE" {
+            return Ok(Rule::QUADLIFE);
+        }
+        if text == "SANDPILE" {
+            return Ok(Rule::SANDPILE);
+        }
+        if text.starts_with("LENIA") || text.starts_with("SMOOTHLIFE") {
+            return Continuous::parse(&text).map(|continuous| Rule(Kind::Continuous(continuous))).ok_or_else(invalid);
+        }
+        if let Some(chances) = text.strip_prefix("FORESTFIRE") {
+            return parse_forest_fire(chances).ok_or_else(invalid);
+        }
+        if let Some(parameters) = text.strip_prefix("SIR") {
+            return parse_sir(parameters).ok_or_else(invalid);
+        }
+        if let Some((_, preset)) = PRESETS.iter().find(|(name, _)| name.eq_ignore_ascii_case(rulestring.trim())) {
+            return preset.parse();
+        }
+        if text.starts_with("NW") && text.contains(',') {
+            return parse_weighted(&text).ok_or_else(invalid);
+        }
+        if let Some(blocks) = text.strip_prefix("MS,D") {
+            return parse_margolus(blocks).ok_or_else(invalid);
+        }
+        if let Some(number) = text.strip_prefix('W') {
+            return number.parse().map(|number| Rule(Kind::Elementary(number))).map_err(|_| invalid());
+        }
+        if text.starts_with('R') && text.contains(',') {
+            return parse_larger_than_life(&text).ok_or_else(invalid);
+        }
+        if text.starts_with('R') {
+            return parse_cyclic(&text).ok_or_else(invalid);
+        }
+        let (text, neighborhood) = if let Some(text) = text.strip_suffix('H') {
+            (text, Neighborhood::Hexagonal)
+        } else if let Some(text) = text.strip_suffix('V') {
+            (text, Neighborhood::VonNeumann)
+        } else {
+            (text.as_str(), Neighborhood::Moore)
+        };
+        let (birth, survival, states) = parse_totalistic(text).ok_or_else(invalid)?;
+        if (birth | survival) >> (neighborhood.size() + 1) != 0 {
+            return Err(invalid());
+        }
+        Ok(Rule(Kind::Totalistic {
+            birth,
+            survival,
+            states,
+            neighborhood,
+        }))
+    }
+}
+
+/// Parse the `/G` growth and `/L` lightning chances following `ForestFire`,
+/// either of which may be left out.
+fn parse_forest_fire(text: &str) -> Option<Rule> {
+    let (mut growth, mut lightning) = (FOREST_GROWTH, FOREST_LIGHTNING);
+    for part in text.split('/').skip(1) {
+        match part.split_at_checked(1)? {
+            ("G", chance) => growth = chance.parse().ok()?,
+            ("L", chance) => lightning = chance.parse().ok()?,
+            _ => return None,
+        }
+    }
+    (text.is_empty() || text.starts_with('/')).then_some(Rule(Kind::ForestFire { growth, lightning }))
+}
+
+/// Parse the `/I` infection and `/R` recovery chances and `/D` immunity
+/// duration following `SIR`, any of which may be left out.
+fn parse_sir(text: &str) -> Option<Rule> {
+    let (mut infection, mut recovery, mut immunity) = (SIR_INFECTION, SIR_RECOVERY, 0);
+    for part in text.split('/').skip(1) {
+        match part.split_at_checked(1)? {
+            ("I", chance) => infection = chance.parse().ok()?,
+            ("R", chance) => recovery = chance.parse().ok()?,
+            ("D", generations) => immunity = generations.parse().ok().filter(|&generations| generations <= u8::MAX - SIR_RECOVERED)?,
+            _ => return None,
+        }
+    }
+    (text.is_empty() || text.starts_with('/')).then_some(Rule(Kind::Sir {
+        infection,
+        recovery,
+        immunity,
+    }))
+}
+
+/// Labels of the cells of a weighted rule in MCell notation, and their
+/// offsets.
+const WEIGHT_LABELS: [(&str, (i32, i32)); 9] = [
+    ("NW", (-1, -1)),
+    ("NN", (-1, 0)),
+    ("NE", (-1, 1)),
+    ("WW", (0, -1)),
+    ("ME", (0, 0)),
+    ("EE", (0, 1)),
+    ("SW", (1, -1)),
+    ("SS", (1, 0)),
+    ("SE", (1, 1)),
+];
+
+/// Parse the MCell notation of a weighted rule, weights left out being 0.
+fn parse_weighted(text: &str) -> Option<Rule> {
+    let (mut weights, mut birth, mut survival, mut states) = (Vec::new(), Vec::new(), Vec::new(), 2);
+    for part in text.split(',') {
+        let (label, value) = part.trim().split_at_checked(2)?;
+        let value: u32 = value.parse().ok()?;
+        match label {
+            "RB" => birth.push(value),
+            "RS" => survival.push(value),
+            "HI" => states = if value == 0 { 2 } else { u8::try_from(value).ok().filter(|&states| states > 2)? },
+            _ => {
+                let &(_, offset) = WEIGHT_LABELS.iter().find(|&&(name, _)| name == label)?;
+                if value > 0 {
+                    weights.push((offset.0, offset.1, value));
+                }
+            }
+        }
+    }
+    birth.sort_unstable();
+    birth.dedup();
+    survival.sort_unstable();
+    survival.dedup();
+    Some(Rule(Kind::Weighted {
+        weights,
+        birth,
+        survival,
+        states,
+    }))
+}
+
+/// Parse the 16 blocks of a block rule, separated by semicolons.
+fn parse_margolus(text: &str) -> Option<Rule> {
+    let blocks: Vec<u8> = text
+        .split(';')
+        .map(|block| block.trim().parse().ok().filter(|&block| block < 16))
+        .collect::<Option<_>>()?;
+    let mut table = [0; 16];
+    if blocks.len() != table.len() {
+        return None;
+    }
+    table.copy_from_slice(&blocks);
+    Some(Rule(Kind::Margolus(table)))
+}
+
+/// Parse the birth and survival masks and number of states of a B/S or S/B
+/// rulestring, without any neighborhood suffix.
+fn parse_totalistic(text: &str) -> Option<(u16, u16, u8)> {
+    if !text.contains(['B', 'S', 'C']) {
+        let parts: Vec<&str> = text.split('/').collect();
+        let (survival, birth, states) = match parts.as_slice() {
+            [survival, birth] => (*survival, *birth, None),
+            [survival, birth, states] => (*survival, *birth, Some(*states)),
+            _ => return None,
+        };
+        return Some((counts(birth)?, counts(survival)?, states.map_or(Some(2), state_count)?));
+    }
+
+    let (mut birth, mut survival, mut states) = (None, None, None);
+    for part in text.split('/') {
+        // A part holds one letter and its value, or several as in `B3S23`.
+        let mut rest = part;
+        while let Some(letter) = rest.chars().next() {
+            let body = &rest[letter.len_utf8()..];
+            let end = body.find(['B', 'S', 'C']).unwrap_or(body.len());
+            let value = &body[..end];
+            match letter {
+                'B' if birth.is_none() => birth = Some(counts(value)?),
+                'S' if survival.is_none() => survival = Some(counts(value)?),
+                'C' if states.is_none() => states = Some(state_count(value)?),
+                // A bare number after B and S, as in `B2/S345/4`.
+                _ if states.is_none() && birth.is_some() && survival.is_some() => {
+                    states = Some(state_count(rest)?);
+                    break;
+                }
+                _ => return None,
+            }
+            rest = &body[end..];
+        }
+    }
+    Some((birth?, survival?, states.unwrap_or(2)))
+}
+
+/// Parse the `R1/T3/C3/NM` MCell notation of a cyclic rule, ranges other
+/// than 1 being unsupported.
+fn parse_cyclic(text: &str) -> Option<Rule> {
+    let (mut range, mut threshold, mut states, mut neighborhood) = (None, None, None, None);
+    for part in text.split('/') {
+        let mut chars = part.chars();
+        let letter = chars.next()?;
+        let value = chars.as_str();
+        let slot = match letter {
+            'R' => &mut range,
+            'T' => &mut threshold,
+            'C' => &mut states,
+            'N' => &mut neighborhood,
+            _ => return None,
+        };
+        if slot.replace(value).is_some() {
+            return None;
+        }
+    }
+    if range? != "1" {
+        return None;
+    }
+    let neighborhood = match neighborhood.unwrap_or("M") {
+        "M" => Neighborhood::Moore,
+        "N" => Neighborhood::VonNeumann,
+        _ => return None,
+    };
+    let threshold = threshold?.parse().ok().filter(|threshold| (1..=neighborhood.size()).contains(threshold))?;
+    Some(Rule(Kind::Cyclic {
+        threshold,
+        states: state_count(states?)?,
+        neighborhood,
+    }))
+}
+
+/// Parse the `R5,C0,M1,S34..58,B34..45,NM` notation of a Larger than Life
+/// rule: its range from 1 to 500, number of states, whether the middle cell
+/// counts, survival and birth ranges and the Moore neighborhood.
+fn parse_larger_than_life(text: &str) -> Option<Rule> {
+    let (mut range, mut states, mut middle, mut survival, mut birth, mut neighborhood) = (None, None, None, None, None, None);
+    for part in text.split(',') {
+        let mut chars = part.trim().chars();
+        let letter = chars.next()?;
+        let value = chars.as_str();
+        let slot = match letter {
+            'R' => &mut range,
+            'C' => &mut states,
+            'M' => &mut middle,
+            'S' => &mut survival,
+            'B' => &mut birth,
+            'N' => &mut neighborhood,
+            _ => return None,
+        };
+        if slot.replace(value).is_some() {
+            return None;
+        }
+    }
+    let range: u16 = range?.parse().ok().filter(|range| (1..=500).contains(range))?;
+    let middle = match middle.unwrap_or("0") {
+        "0" => false,
+        "1" => true,
+        _ => return None,
+    };
+    if neighborhood.unwrap_or("M") != "M" {
+        return None;
+    }
+    // Golly writes two state rules as C0 or C2.
+    let states = match states.unwrap_or("0") {
+        "0" => 2,
+        states => state_count(states)?,
+    };
+    let side = 2 * u32::from(range) + 1;
+    let cells = side * side - !middle as u32;
+    let count_range = |text: &str| -> Option<(u32, u32)> {
+        let (min, max) = text.split_once("..")?;
+        let (min, max) = (min.parse().ok()?, max.parse().ok()?);
+        (min <= max && max <= cells).then_some((min, max))
+    };
+    Some(Rule(Kind::LargerThanLife {
+        range,
+        middle,
+        birth: count_range(birth?)?,
+        survival: count_range(survival?)?,
+        states,
+    }))
+}
+
+/// Number of states of a Generations rule, from 2 to 255.
+fn state_count(digits: &str) -> Option<u8> {
+    digits.parse::<u8>().ok().filter(|&states| states >= 2)
+}
+
+/// Bitmask of a string of neighbor counts from 0 to 8.
+fn counts(digits: &str) -> Option<u16> {
+    let mut mask = 0;
+    for digit in digits.chars() {
+        let count = digit.to_digit(10).filter(|&count| count <= 8)?;
+        mask |= 1 << count;
+    }
+    Some(mask)
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = |mask: u16| -> String {
+            (0..=8)
+                .filter(|count| mask & (1 << count) != 0)
+                .map(|count| count.to_string())
+                .collect()
+        };
+        match self.0 {
+            Kind::Totalistic {
+                birth,
+                survival,
+                states,
+                neighborhood,
+            } => {
+                write!(f, "B{}/S{}", digits(birth), digits(survival))?;
+                if states > 2 {
+                    write!(f, "/C{}", states)?;
+                }
+                match neighborhood {
+                    Neighborhood::Moore => Ok(()),
+                    Neighborhood::VonNeumann => write!(f, "V"),
+                    Neighborhood::Hexagonal => write!(f, "H"),
+                }
+            }
+            Kind::Weighted {
+                ref weights,
+                ref birth,
+                ref survival,
+                states,
+            } => {
+                let mut parts: Vec<String> = WEIGHT_LABELS
+                    .iter()
+                    .map(|&(label, offset)| {
+                        let weight = weights.iter().find(|&&(row, col, _)| (row, col) == offset).map_or(0, |&(_, _, weight)| weight);
+                        format!("{}{}", label, weight)
+                    })
+                    .collect();
+                parts.push(format!("HI{}", if states == 2 { 0 } else { states }));
+                parts.extend(survival.iter().map(|count| format!("RS{}", count)));
+                parts.extend(birth.iter().map(|count| format!("RB{}", count)));
+                write!(f, "{}", parts.join(","))
+            }
+            Kind::Colored(2) => write!(f, "Immigration"),
+            Kind::Colored(_) => write!(f, "QuadLife"),
+            Kind::Wireworld => write!(f, "Wireworld"),
+            Kind::Sandpile => write!(f, "Sandpile"),
+            Kind::Continuous(continuous) => write!(f, "{}", continuous),
+            Kind::Elementary(number) => write!(f, "W{}", number),
+            Kind::Cyclic {
+                threshold,
+                states,
+                neighborhood,
+            } => {
+                let letter = if neighborhood == Neighborhood::VonNeumann { 'N' } else { 'M' };
+                write!(f, "R1/T{}/C{}/N{}", threshold, states, letter)
+            }
+            Kind::Table(ref table) => write!(f, "{}", table.name),
+            Kind::LargerThanLife {
+                range,
+                middle,
+                birth,
+                survival,
+                states,
+            } => {
+                let states = if states == 2 { 0 } else { states };
+                write!(
+                    f,
+                    "R{},C{},M{},S{}..{},B{}..{},NM",
+                    range, states, middle as u8, survival.0, survival.1, birth.0, birth.1
+                )
+            }
+            Kind::ForestFire { .. } if *self == Rule::FOREST_FIRE => write!(f, "ForestFire"),
+            Kind::ForestFire { growth, lightning } => write!(f, "ForestFire/G{}/L{}", growth.0, lightning.0),
+            Kind::Sir { .. } if *self == Rule::SIR => write!(f, "SIR"),
+            Kind::Sir {
+                infection,
+                recovery,
+                immunity,
+            } => write!(f, "SIR/I{}/R{}/D{}", infection.0, recovery.0, immunity),
+            Kind::Margolus(blocks) => {
+                let blocks: Vec<String> = blocks.iter().map(u8::to_string).collect();
+                write!(f, "MS,D{}", blocks.join(";"))
+            }
+            Kind::Alternating(ref rules) => {
+                let rulestrings: Vec<String> = rules.iter().map(Rule::to_string).collect();
+                write!(f, "{}", rulestrings.join("|"))
+            }
+        }
+    }
+}
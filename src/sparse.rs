@@ -0,0 +1,56 @@
+//! Stepping universes with few live cells from those cells alone: only the
+//! live cells and their neighbors can change, so a glider on a board of a
+//! hundred million cells takes a few dozen lookups a generation rather than
+//! a pass over the whole grid.
+//!
+//! This saves time more than memory: the grid of cells stays, one bit a
+//! cell, as everything else reads it, alongside the live cells. Only the
+//! copy of the grid ticks step into goes, halving what a big board with a
+//! few gliders takes, so that 10,000x10,000 cells take 12.5 MB rather than
+//! 25 MB.
+
+use std::collections::{HashMap, HashSet};
+
+/// The live cells by row and column.
+pub(crate) type LiveCells = HashSet<(u32, u32)>;
+
+/// Universes step their live cells alone once fewer than one cell in this
+/// many lives.
+pub(crate) const SPARSE_BELOW: usize = 1024;
+
+/// Universes step the whole grid again once more than one cell in this many
+/// lives, so that a population near the mark does not switch every tick.
+pub(crate) const DENSE_ABOVE: usize = 256;
+
+/// The cells that change in the next generation of `live` under the B/S
+/// rule with the `birth` and `survival` bitmasks, with whether they come to
+/// life, where `neighbor` finds the cell at an offset from another, if any.
+/// The cells a cell counts must be those counting it.
+pub(crate) fn changes(
+    live: &LiveCells,
+    (birth, survival): (u16, u16),
+    offsets: &[(i32, i32)],
+    neighbor: impl Fn((u32, u32), (i32, i32)) -> Option<(u32, u32)>,
+) -> Vec<((u32, u32), bool)> {
+    let mut counts: HashMap<(u32, u32), u32> = HashMap::with_capacity(live.len() * offsets.len());
+    for &cell in live {
+        for &offset in offsets {
+            if let Some(around) = neighbor(cell, offset) {
+                *counts.entry(around).or_insert(0) += 1;
+            }
+        }
+    }
+    let mut changes: Vec<((u32, u32), bool)> = counts
+        .iter()
+        .filter_map(|(&cell, &count)| {
+            let alive = live.contains(&cell);
+            let next = if alive { survival } else { birth } >> count & 1 == 1;
+            (next != alive).then_some((cell, next))
+        })
+        .collect();
+    // Live cells without live neighbors are left out of the counts.
+    if survival & 1 == 0 {
+        changes.extend(live.iter().filter(|cell| !counts.contains_key(cell)).map(|&cell| (cell, false)));
+    }
+    changes
+}
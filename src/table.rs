@@ -0,0 +1,315 @@
+//! Golly's rule table format, from the `@TABLE` section of a `.rule` file or
+//! an older `.table` file, for rules given as explicit transitions.
+//!
+//! See <https://golly.sourceforge.io/Help/formats.html#rule>.
+
+use crate::Error;
+use std::collections::HashMap;
+
+/// A rule table: which state a cell goes to given its own state and the
+/// states of its neighbors, with the first matching transition winning and
+/// cells matching none keeping their state.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct RuleTable {
+    /// The name from the `@RULE` line, or `table` without one.
+    pub(crate) name: String,
+    pub(crate) states: u8,
+    pub(crate) neighborhood: TableNeighborhood,
+    /// Every symmetric variant of the table's transitions, in order.
+    transitions: Vec<Transition>,
+    /// Whether the neighbors of a transition may come in any order.
+    permute: bool,
+    /// RGB colors of the states listed in a `@COLORS` section.
+    pub(crate) colors: Vec<(u8, [u8; 3])>,
+}
+
+/// The neighborhoods rule tables can use. Neighbors are listed clockwise
+/// from north, as in Golly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum TableNeighborhood {
+    Moore,
+    VonNeumann,
+}
+
+/// The row and column offsets of Moore neighbors, clockwise from north.
+const MOORE: [(i32, i32); 8] = [(-1, 0), (-1, 1), (0, 1), (1, 1), (1, 0), (1, -1), (0, -1), (-1, -1)];
+const VON_NEUMANN: [(i32, i32); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+impl TableNeighborhood {
+    pub fn offsets(self) -> &'static [(i32, i32)] {
+        match self {
+            TableNeighborhood::Moore => &MOORE,
+            TableNeighborhood::VonNeumann => &VON_NEUMANN,
+        }
+    }
+}
+
+/// An input or output of a transition.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Slot {
+    State(u8),
+    /// A variable by index, standing for one of a set of states. Every use of
+    /// a variable within a transition is the same state.
+    Variable(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Transition {
+    /// The cell's state, then its neighbors'.
+    inputs: Vec<Slot>,
+    output: Slot,
+    /// `variables[n]` is the set of states variable `n` stands for.
+    variables: Vec<Vec<u8>>,
+}
+
+impl RuleTable {
+    /// The next state of a cell in `state` with neighbors in `neighbors`,
+    /// listed in the order of `offsets`.
+    pub fn next(&self, state: u8, neighbors: &[u8]) -> u8 {
+        for transition in self.transitions.iter() {
+            let mut bound = vec![None; transition.variables.len()];
+            if !transition.matches(transition.inputs[0], state, &mut bound) {
+                continue;
+            }
+            let matched = if self.permute {
+                transition.matches_any_order(&transition.inputs[1..], neighbors, &mut bound)
+            } else {
+                transition.inputs[1..]
+                    .iter()
+                    .zip(neighbors)
+                    .all(|(&slot, &neighbor)| transition.matches(slot, neighbor, &mut bound))
+            };
+            if matched {
+                return match transition.output {
+                    Slot::State(state) => state,
+                    Slot::Variable(variable) => bound[variable].unwrap_or(state),
+                };
+            }
+        }
+        state
+    }
+
+    /// Color of `state` from the `@COLORS` section, if listed.
+    pub fn color(&self, state: u8) -> Option<[u8; 3]> {
+        self.colors.iter().find(|(listed, _)| *listed == state).map(|&(_, color)| color)
+    }
+}
+
+impl Transition {
+    /// Whether `slot` can be `state`, binding variables on first use.
+    fn matches(&self, slot: Slot, state: u8, bound: &mut [Option<u8>]) -> bool {
+        match slot {
+            Slot::State(expected) => expected == state,
+            Slot::Variable(variable) => match bound[variable] {
+                Some(value) => value == state,
+                None if self.variables[variable].contains(&state) => {
+                    bound[variable] = Some(state);
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+
+    /// Whether the `slots` match the `states` in some order.
+    fn matches_any_order(&self, slots: &[Slot], states: &[u8], bound: &mut Vec<Option<u8>>) -> bool {
+        let (slot, rest) = match slots.split_first() {
+            Some(split) => split,
+            None => return true,
+        };
+        for (idx, &state) in states.iter().enumerate() {
+            let mut attempt = bound.clone();
+            if self.matches(*slot, state, &mut attempt) {
+                let others: Vec<u8> = states.iter().enumerate().filter(|&(other, _)| other != idx).map(|(_, &state)| state).collect();
+                if self.matches_any_order(rest, &others, &mut attempt) {
+                    *bound = attempt;
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Parse the text of a `.rule` file with a `@TABLE` section, or of a bare
+/// `.table` file. Other sections than `@RULE`, `@TABLE` and `@COLORS` are
+/// ignored.
+pub(crate) fn parse(text: &str) -> Result<RuleTable, Error> {
+    let has_sections = text.lines().any(|line| line.trim().starts_with('@'));
+    let mut name = "table".to_string();
+    let mut section = if has_sections { "" } else { "@TABLE" };
+    let mut table_lines = Vec::new();
+    let mut color_lines = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line_number = idx + 1;
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('@') {
+            let mut words = line.split_whitespace();
+            section = words.next().unwrap_or("");
+            if section == "@RULE" {
+                name = words.next().ok_or_else(|| parse_error(line_number, "@RULE needs a name"))?.to_string();
+            }
+            continue;
+        }
+        match section {
+            "@TABLE" => table_lines.push((line_number, line)),
+            "@COLORS" => color_lines.push((line_number, line)),
+            _ => {}
+        }
+    }
+    if table_lines.is_empty() {
+        return Err(parse_error(1, "expected a @TABLE section"));
+    }
+
+    let mut table = parse_table(name, table_lines)?;
+    for (line_number, line) in color_lines {
+        let numbers: Vec<u8> = line
+            .split_whitespace()
+            .map(|number| number.parse().map_err(|_| parse_error(line_number, "expected a state and its red, green and blue")))
+            .collect::<Result<_, _>>()?;
+        // Lines of six numbers give a gradient, which is not supported.
+        if let [state, red, green, blue] = numbers[..] {
+            table.colors.push((state, [red, green, blue]));
+        }
+    }
+    Ok(table)
+}
+
+fn parse_table(name: String, lines: Vec<(usize, &str)>) -> Result<RuleTable, Error> {
+    let (mut states, mut neighborhood, mut symmetries) = (None, None, "none");
+    let mut variable_names: HashMap<String, Vec<u8>> = HashMap::new();
+    let mut transitions = Vec::new();
+    for (line_number, line) in lines {
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            match key.trim() {
+                "n_states" => {
+                    let count = value.parse().ok().filter(|&count| count >= 2);
+                    states = Some(count.ok_or_else(|| parse_error(line_number, "n_states must be from 2 to 255"))?);
+                }
+                "neighborhood" => {
+                    neighborhood = Some(match value {
+                        "Moore" => TableNeighborhood::Moore,
+                        "vonNeumann" => TableNeighborhood::VonNeumann,
+                        other => return Err(parse_error(line_number, &format!("unsupported neighborhood {}", other))),
+                    })
+                }
+                "symmetries" => symmetries = value,
+                other => return Err(parse_error(line_number, &format!("unknown setting {}", other))),
+            }
+            continue;
+        }
+        let states = states.ok_or_else(|| parse_error(line_number, "n_states must come first"))?;
+        let neighborhood = neighborhood.ok_or_else(|| parse_error(line_number, "neighborhood must come first"))?;
+        if let Some(declaration) = line.strip_prefix("var ") {
+            let (variable, values) = declaration
+                .split_once('=')
+                .ok_or_else(|| parse_error(line_number, "expected var name={...}"))?;
+            let values = values.trim().trim_start_matches('{').trim_end_matches('}');
+            let mut set = Vec::new();
+            for value in values.split(',').map(str::trim) {
+                match variable_names.get(value) {
+                    Some(states) => set.extend(states.iter().cloned()),
+                    None => set.push(parse_state(value, states, line_number)?),
+                }
+            }
+            variable_names.insert(variable.trim().to_string(), set);
+            continue;
+        }
+        let size = neighborhood.offsets().len() + 2;
+        // States are separated by commas, or by nothing when they are all
+        // single characters.
+        let tokens: Vec<&str> = if line.contains(',') {
+            line.split(',').map(str::trim).collect()
+        } else {
+            line.char_indices().map(|(idx, c)| &line[idx..idx + c.len_utf8()]).collect()
+        };
+        if tokens.len() != size {
+            return Err(parse_error(line_number, &format!("expected {} states in a transition", size)));
+        }
+        // Each variable used in the transition gets its own index.
+        let mut variables: Vec<(&str, Vec<u8>)> = Vec::new();
+        let mut slots = Vec::with_capacity(size);
+        for token in tokens {
+            let slot = match variable_names.get(token) {
+                Some(set) => Slot::Variable(match variables.iter().position(|(name, _)| *name == token) {
+                    Some(idx) => idx,
+                    None => {
+                        variables.push((token, set.clone()));
+                        variables.len() - 1
+                    }
+                }),
+                None => Slot::State(parse_state(token, states, line_number)?),
+            };
+            slots.push(slot);
+        }
+        let output = slots.pop().unwrap();
+        if let Slot::Variable(variable) = output {
+            if !slots.contains(&Slot::Variable(variable)) {
+                return Err(parse_error(line_number, "an output variable must be an input too"));
+            }
+        }
+        let transition = Transition {
+            inputs: slots,
+            output,
+            variables: variables.into_iter().map(|(_, set)| set).collect(),
+        };
+        for permutation in permutations(symmetries, neighborhood, line_number)? {
+            let mut inputs = vec![transition.inputs[0]];
+            inputs.extend(permutation.iter().map(|&position| transition.inputs[1 + position]));
+            transitions.push(Transition {
+                inputs,
+                ..transition.clone()
+            });
+        }
+    }
+    Ok(RuleTable {
+        name,
+        states: states.ok_or_else(|| parse_error(1, "missing n_states"))?,
+        neighborhood: neighborhood.ok_or_else(|| parse_error(1, "missing neighborhood"))?,
+        transitions,
+        permute: symmetries == "permute",
+        colors: Vec::new(),
+    })
+}
+
+/// The reorderings of the neighbors a transition also stands for under
+/// `symmetries`, as the position each neighbor is taken from. For `permute`
+/// only the identity is listed, any order being tried when matching.
+fn permutations(symmetries: &str, neighborhood: TableNeighborhood, line_number: usize) -> Result<Vec<Vec<usize>>, Error> {
+    let size = neighborhood.offsets().len();
+    let rotate = |by: usize| -> Vec<usize> { (0..size).map(|position| (position + by) % size).collect() };
+    let reflect = |permutation: &Vec<usize>| -> Vec<usize> { (0..size).map(|position| permutation[(size - position) % size]).collect() };
+    // A quarter turn moves neighbors two places on the Moore ring.
+    let quarter = size / 4;
+    let rotations = |step: usize| -> Vec<Vec<usize>> { (0..size).step_by(step).map(rotate).collect() };
+    let mut permutations = match (symmetries, neighborhood) {
+        ("none", _) | ("permute", _) => vec![rotate(0)],
+        ("rotate4", _) => rotations(quarter),
+        ("rotate8", TableNeighborhood::Moore) => rotations(1),
+        ("reflect_horizontal", _) => vec![rotate(0), reflect(&rotate(0))],
+        ("rotate4reflect", _) => rotations(quarter).iter().flat_map(|rotation| vec![rotation.clone(), reflect(rotation)]).collect(),
+        ("rotate8reflect", TableNeighborhood::Moore) => rotations(1).iter().flat_map(|rotation| vec![rotation.clone(), reflect(rotation)]).collect(),
+        (other, _) => return Err(parse_error(line_number, &format!("unsupported symmetries {}", other))),
+    };
+    permutations.dedup();
+    Ok(permutations)
+}
+
+fn parse_state(token: &str, states: u8, line_number: usize) -> Result<u8, Error> {
+    token
+        .parse()
+        .ok()
+        .filter(|&state| state < states)
+        .ok_or_else(|| parse_error(line_number, &format!("{} is neither a state nor a variable", token)))
+}
+
+fn parse_error(line: usize, message: &str) -> Error {
+    Error::Parse {
+        line,
+        message: message.to_string(),
+    }
+}
@@ -0,0 +1,48 @@
+//! Stepping big grids on many cores. Builds with the `threads` feature step
+//! the words of the bitwise kernel in parallel on a rayon pool, which in the
+//! browser runs on web workers sharing the module's memory through a
+//! `SharedArrayBuffer`.
+//!
+//! Browsers only hand out shared memory to cross-origin isolated pages, and
+//! the workers take a round trip through JS to start, so wasm32 builds step
+//! on one thread until a page has started the pool. Other builds have threads
+//! of their own and always step in parallel.
+
+use crate::Error;
+use js_sys::Promise;
+
+#[cfg(feature = "threads")]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the pool of web workers has started.
+#[cfg(feature = "threads")]
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether ticks can step in parallel.
+#[cfg(feature = "threads")]
+pub(crate) fn running() -> bool {
+    !cfg!(target_arch = "wasm32") || STARTED.load(Ordering::Acquire)
+}
+
+/// Start a pool of `count` web workers, resolving once they are all up.
+#[cfg(feature = "threads")]
+pub(crate) fn start(count: u32) -> Result<Promise, Error> {
+    use futures::Future;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_futures::{future_to_promise, JsFuture};
+
+    let isolated = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("crossOriginIsolated"));
+    if isolated.ok().and_then(|isolated| isolated.as_bool()) != Some(true) {
+        return Err(Error::NotIsolated);
+    }
+    let pool = wasm_bindgen_rayon::init_thread_pool(count.max(1) as usize);
+    Ok(future_to_promise(JsFuture::from(pool).map(|_| {
+        STARTED.store(true, Ordering::Release);
+        JsValue::UNDEFINED
+    })))
+}
+
+#[cfg(not(feature = "threads"))]
+pub(crate) fn start(_count: u32) -> Result<Promise, Error> {
+    Err(Error::NoThreads)
+}
@@ -0,0 +1,195 @@
+//! Skipping the still parts of a grid: the grid is cut into 64x64 tiles, and
+//! a tile whose cells and those of the 8 tiles around it all stayed the same
+//! through the last generation stays the same through the next one too. A
+//! few gliders on a big board leave almost every tile still, so that the
+//! kernels step a small part of the grid.
+//!
+//! Tiles along the edges of the grid meet whatever lies past them, which can
+//! change on its own, and are always stepped.
+//!
+//! The same tiles tell renderers which parts of the grid to redraw.
+
+use fixedbitset::FixedBitSet;
+
+/// Cells along each side of a tile.
+const TILE: usize = 64;
+
+/// A grid cut into tiles.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Grid {
+    /// The grid's width and height in cells.
+    size: (usize, usize),
+    /// Tiles across and down.
+    columns: usize,
+    rows: usize,
+}
+
+/// Which tiles of a grid changed since they were last stepped, for one two
+/// state B/S rule on the Moore neighborhood.
+#[derive(Clone, Debug)]
+pub(crate) struct Tiles {
+    grid: Grid,
+    /// The birth and survival bitmasks of neighbor counts of the rule.
+    masks: (u16, u16),
+    /// Whether each tile, row by row, had cells change since it was last
+    /// stepped.
+    changed: Vec<bool>,
+    /// Whether each tile has to be stepped this generation.
+    busy: Vec<bool>,
+}
+
+/// Which tiles of a grid changed since they were last asked for.
+#[derive(Clone, Debug)]
+pub(crate) struct DirtyTiles {
+    grid: Grid,
+    /// Whether each tile, row by row, had cells change.
+    dirty: Vec<bool>,
+}
+
+/// The first and last cells of the runs of cells from `first` to `last` on
+/// each row of a grid `width` cells wide. A run of at most 64 cells has its
+/// first and last cells in every tile it lies in.
+fn runs(width: usize, first: usize, last: usize) -> impl Iterator<Item = (usize, usize)> {
+    (first / width..=last / width).map(move |row| (first.max(row * width), last.min(row * width + width - 1)))
+}
+
+impl Grid {
+    fn new((width, height): (usize, usize)) -> Grid {
+        Grid {
+            size: (width, height),
+            columns: width.div_ceil(TILE),
+            rows: height.div_ceil(TILE),
+        }
+    }
+
+    /// The tile of cell `idx`.
+    fn tile(&self, idx: usize) -> usize {
+        let width = self.size.0;
+        idx / width / TILE * self.columns + idx % width / TILE
+    }
+
+    /// Mark the tiles holding the cells that differ between `before` and
+    /// `after` in `marks`, 32 cells at a time.
+    fn mark_changes(&self, marks: &mut [bool], before: &FixedBitSet, after: &FixedBitSet) {
+        let size = self.size.0 * self.size.1;
+        for (block, (&old, &new)) in before.as_slice().iter().zip(after.as_slice()).enumerate() {
+            if old == new {
+                continue;
+            }
+            let (first, last) = (block * 32, (block * 32 + 31).min(size - 1));
+            for (start, end) in runs(self.size.0, first, last) {
+                marks[self.tile(start)] = true;
+                marks[self.tile(end)] = true;
+            }
+        }
+    }
+}
+
+impl Tiles {
+    /// Tiles for a `width` by `height` grid stepping by `masks`, all of them
+    /// changed.
+    pub fn new(size: (usize, usize), masks: (u16, u16)) -> Tiles {
+        let grid = Grid::new(size);
+        let tiles = grid.columns * grid.rows;
+        Tiles {
+            grid,
+            masks,
+            changed: vec![true; tiles],
+            busy: vec![true; tiles],
+        }
+    }
+
+    /// Whether these are the tiles of a grid of `size` stepping by `masks`.
+    pub fn fits(&self, size: (usize, usize), masks: (u16, u16)) -> bool {
+        self.grid.size == size && self.masks == masks
+    }
+
+    /// Mark the tiles holding the cells that differ between `before` and
+    /// `after` as changed.
+    pub fn mark_changes(&mut self, before: &FixedBitSet, after: &FixedBitSet) {
+        self.grid.mark_changes(&mut self.changed, before, after);
+    }
+
+    /// Work out which tiles have to be stepped from those that changed, and
+    /// start over counting changes.
+    pub fn plan(&mut self) {
+        let Grid { columns, rows, .. } = self.grid;
+        for row in 0..rows {
+            for col in 0..columns {
+                let edge = row == 0 || col == 0 || row == rows - 1 || col == columns - 1;
+                self.busy[row * columns + col] = edge
+                    || (row - 1..=row + 1).any(|row| (col - 1..=col + 1).any(|col| self.changed[row * columns + col]));
+            }
+        }
+        self.changed.iter_mut().for_each(|changed| *changed = false);
+    }
+
+    /// About how many bytes the tiles take.
+    pub fn bytes(&self) -> usize {
+        self.changed.capacity() + self.busy.capacity()
+    }
+
+    /// Whether any of the cells from `first` to `last`, at most 64 of them,
+    /// has to be stepped.
+    pub fn busy(&self, first: usize, last: usize) -> bool {
+        let grid = &self.grid;
+        runs(grid.size.0, first, last).any(|(start, end)| self.busy[grid.tile(start)] || self.busy[grid.tile(end)])
+    }
+}
+
+impl DirtyTiles {
+    /// Tiles for a `width` by `height` grid, all of them dirty.
+    pub fn new(size: (usize, usize)) -> DirtyTiles {
+        let grid = Grid::new(size);
+        let dirty = vec![true; grid.columns * grid.rows];
+        DirtyTiles { grid, dirty }
+    }
+
+    /// Whether these are the tiles of a grid of `size`.
+    pub fn fits(&self, size: (usize, usize)) -> bool {
+        self.grid.size == size
+    }
+
+    /// About how many bytes the tiles take.
+    pub fn bytes(&self) -> usize {
+        self.dirty.capacity()
+    }
+
+    /// Mark the tile of cell `idx` as dirty.
+    pub fn mark(&mut self, idx: usize) {
+        let tile = self.grid.tile(idx);
+        self.dirty[tile] = true;
+    }
+
+    /// Mark the tiles holding the cells that differ between `before` and
+    /// `after` as dirty.
+    pub fn mark_changes(&mut self, before: &FixedBitSet, after: &FixedBitSet) {
+        self.grid.mark_changes(&mut self.dirty, before, after);
+    }
+
+    /// The rows, columns, widths and heights of rectangles covering the
+    /// dirty tiles, runs of them along each row of tiles merged into one
+    /// and cut off at the edges of the grid, and clean them all.
+    pub fn take(&mut self) -> Vec<u32> {
+        let Grid { size: (width, height), columns, rows } = self.grid;
+        let mut rects = Vec::new();
+        for row in 0..rows {
+            let mut col = 0;
+            while col < columns {
+                if !self.dirty[row * columns + col] {
+                    col += 1;
+                    continue;
+                }
+                let start = col;
+                while col < columns && self.dirty[row * columns + col] {
+                    col += 1;
+                }
+                let (top, left) = (row * TILE, start * TILE);
+                let (bottom, right) = ((top + TILE).min(height), (col * TILE).min(width));
+                rects.extend_from_slice(&[top as u32, left as u32, (right - left) as u32, (bottom - top) as u32]);
+            }
+        }
+        self.dirty.iter_mut().for_each(|dirty| *dirty = false);
+        rects
+    }
+}
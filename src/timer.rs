@@ -0,0 +1,104 @@
+//! Timing ticks from inside wasm, where a clock read right around the step
+//! leaves out the calls into wasm and whatever else the page does between
+//! frames.
+
+use crate::utils;
+use std::collections::VecDeque;
+use wasm_bindgen::prelude::*;
+
+/// Timers keep at most this many ticks, about 18 minutes of them at 60 ticks
+/// a second.
+pub(crate) const MAX_WINDOW: u32 = 1 << 16;
+
+/**
+ * How long recent ticks took, see `Universe::timing_stats`.
+ */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TimingStats {
+    /// Ticks timed since timing started.
+    pub ticks: u64,
+    /// Ticks the other numbers are over, the most recent ones.
+    pub window: u32,
+    /// Milliseconds the ticks took on average.
+    pub average_ms: f64,
+    /// Milliseconds the fastest tick took.
+    pub min_ms: f64,
+    /// Milliseconds half of the ticks took at most.
+    pub median_ms: f64,
+    /// Milliseconds 9 ticks in 10 took at most.
+    pub p90_ms: f64,
+    /// Milliseconds 99 ticks in 100 took at most.
+    pub p99_ms: f64,
+    /// Milliseconds the slowest tick took.
+    pub max_ms: f64,
+}
+
+/// The durations of the most recent ticks.
+#[derive(Clone, Debug)]
+pub(crate) struct Timer {
+    /// Ticks kept.
+    window: usize,
+    /// What ticks show as in the browser's console timers, if anything.
+    label: Option<String>,
+    /// Milliseconds the most recent ticks took, oldest first.
+    durations: VecDeque<f64>,
+    ticks: u64,
+}
+
+impl Timer {
+    pub fn new(window: usize, label: Option<String>) -> Timer {
+        Timer {
+            window,
+            label,
+            durations: VecDeque::new(),
+            ticks: 0,
+        }
+    }
+
+    /// Time a tick from now on, returning when it started.
+    pub fn start(&self) -> f64 {
+        if let Some(label) = &self.label {
+            crate::time(label);
+        }
+        utils::now_ms()
+    }
+
+    /// Keep how long the tick that `start`ed took.
+    pub fn stop(&mut self, start: f64) {
+        let duration = utils::now_ms() - start;
+        if let Some(label) = &self.label {
+            crate::time_end(label);
+        }
+        if self.durations.len() == self.window {
+            self.durations.pop_front();
+        }
+        self.durations.push_back(duration);
+        self.ticks += 1;
+    }
+
+    pub fn stats(&self) -> TimingStats {
+        let mut durations: Vec<f64> = self.durations.iter().cloned().collect();
+        durations.sort_unstable_by(f64::total_cmp);
+        // The smallest duration at least `fraction` of them are no longer
+        // than.
+        let rank = |fraction: f64| match durations.len() {
+            0 => 0.0,
+            len => durations[((fraction * len as f64).ceil() as usize).clamp(1, len) - 1],
+        };
+        let average = match durations.len() {
+            0 => 0.0,
+            len => durations.iter().sum::<f64>() / len as f64,
+        };
+        TimingStats {
+            ticks: self.ticks,
+            window: durations.len() as u32,
+            average_ms: average,
+            min_ms: rank(0.0),
+            median_ms: rank(0.5),
+            p90_ms: rank(0.9),
+            p99_ms: rank(0.99),
+            max_ms: rank(1.0),
+        }
+    }
+}
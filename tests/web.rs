@@ -5,7 +5,10 @@
 extern crate wasm_bindgen_test;
 extern crate wasm_game_of_life;
 use wasm_bindgen_test::*;
-use wasm_game_of_life::Universe;
+use wasm_game_of_life::{
+    Anchor, Blend, Boundary, Direction, Edge, Engine, InfiniteUniverse, Kernel, LayeredUniverse, MemoryStats, Pattern, Symmetry,
+    TickStats, Transform, Universe, Universe3d, UniverseBuilder,
+};
 
 wasm_bindgen_test_configure!(run_in_browser);
 
@@ -31,3 +34,2687 @@ pub fn test_tick() {
 
     assert_eq!(&input_universe.get_cells(), &expected_universe.get_cells());
 }
+
+#[wasm_bindgen_test]
+pub fn test_new_empty() {
+    let mut universe = Universe::new_empty(6, 6);
+    assert!(universe.is_empty());
+
+    universe.set_cells(&[(1, 2)]);
+    assert!(!universe.is_empty());
+}
+
+#[wasm_bindgen_test]
+pub fn test_new_with_density() {
+    assert!(Universe::new_with_density(6, 6, 0.0).is_empty());
+
+    let mut universe = Universe::new_with_density(6, 6, 1.0);
+    assert_eq!(universe.get_cells().count_ones(..), 36);
+
+    universe.reset_with_density(0.0);
+    assert!(universe.is_empty());
+}
+
+#[wasm_bindgen_test]
+pub fn test_new_with_seed() {
+    let first = Universe::new_with_seed(16, 16, 42);
+    let mut second = Universe::new_with_seed(16, 16, 42);
+    assert_eq!(first.get_cells(), second.get_cells());
+
+    second.set_seed(42);
+    second.reset();
+    assert_eq!(second.seed(), 42);
+    assert_eq!(first.get_cells(), second.get_cells());
+}
+
+#[wasm_bindgen_test]
+pub fn test_builder_dead_boundary() {
+    let mut universe = UniverseBuilder::new()
+        .size(5, 5)
+        .density(0.0)
+        .boundary(Boundary::Dead)
+        .cells(&[0, 0, 0, 1, 0, 2])
+        .build().unwrap();
+    assert_eq!(universe.boundary(), Boundary::Dead);
+    universe.tick();
+
+    let mut expected = Universe::new_empty(5, 5);
+    expected.set_cells(&[(0, 1), (1, 1)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+
+    assert!(UniverseBuilder::new().cells(&[100, 100]).build().is_err());
+    assert!(UniverseBuilder::new().size(5, 5).cells(&[0, 5]).build().is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_get_set_cell() {
+    let mut universe = Universe::new_empty(6, 6);
+    universe.set_cell(2, 3, true).unwrap();
+    universe.set_cell(2, 3, true).unwrap();
+    assert!(universe.get_cell(2, 3).unwrap());
+
+    universe.set_cell(2, 3, false).unwrap();
+    assert!(!universe.get_cell(2, 3).unwrap());
+}
+
+#[wasm_bindgen_test]
+pub fn test_out_of_bounds_cells() {
+    let mut universe = Universe::new_empty(6, 4);
+    assert!(universe.toggle_cell(4, 0).is_err());
+    assert!(universe.set_cell(0, 6, true).is_err());
+    assert!(universe.put_glider(2, 9, None).is_err());
+    assert!(universe.is_empty());
+}
+
+#[wasm_bindgen_test]
+pub fn test_resize() {
+    let mut universe = Universe::new_empty(4, 4);
+    universe.set_cells(&[(0, 0), (1, 2), (3, 3)]);
+    universe.resize(6, 5, Anchor::BottomRight);
+    assert_eq!((universe.width(), universe.height()), (6, 5));
+
+    let mut expected = Universe::new_empty(6, 5);
+    expected.set_cells(&[(1, 2), (2, 4), (4, 5)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+
+    universe.resize(2, 2, Anchor::Center);
+    let mut expected = Universe::new_empty(2, 2);
+    expected.set_cells(&[(0, 0)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+
+    // States the rule keeps come back when a grid of no cells grows.
+    let mut universe = Universe::new_with_seed(28, 28, 1);
+    universe.resize(0, 28, Anchor::TopLeft);
+    universe.set_ant_rule("LLRR").unwrap();
+    universe.resize(28, 7, Anchor::TopLeft);
+    universe.set_state(3, 3, 3).unwrap();
+    assert_eq!(universe.get_state(3, 3).unwrap(), 3);
+    for &rule in &["StarWars", "QuadLife", "Sandpile", "Lenia"] {
+        let mut universe = Universe::new_empty(4, 4);
+        universe.set_rule(rule).unwrap();
+        universe.resize(4, 0, Anchor::TopLeft);
+        universe.resize(4, 4, Anchor::TopLeft);
+        if rule == "Lenia" {
+            universe.set_level(1, 1, 0.75).unwrap();
+            assert_eq!(universe.get_level(1, 1).unwrap(), 0.75);
+        } else {
+            universe.set_state(1, 1, 2).unwrap();
+            assert_eq!(universe.get_state(1, 1).unwrap(), 2, "{}", rule);
+        }
+    }
+}
+
+#[wasm_bindgen_test]
+pub fn test_crop_to_bounding_box() {
+    let mut universe = input_spaceship();
+    assert_eq!(universe.bounding_box(), Some((1, 1, 3, 3)));
+    universe.crop_to_bounding_box(1);
+    assert_eq!((universe.width(), universe.height()), (5, 5));
+
+    let mut expected = Universe::new_empty(5, 5);
+    expected.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+
+    // Dying cells outside the live ones are cut off with the rest, unless
+    // they fall within the padding.
+    let mut universe = Universe::new_empty(6, 6);
+    universe.set_rule("StarWars").unwrap();
+    universe.set_cells(&[(3, 3), (4, 4)]);
+    universe.set_state(0, 0, 2).unwrap();
+    universe.set_state(2, 1, 3).unwrap();
+    universe.set_state(5, 5, 2).unwrap();
+    universe.crop_to_bounding_box(0);
+    assert_eq!(universe.states(), vec![1, 0, 0, 1]);
+
+    let mut universe = Universe::new_empty(6, 6);
+    universe.set_rule("StarWars").unwrap();
+    universe.set_cells(&[(3, 3), (4, 4)]);
+    universe.set_state(0, 0, 2).unwrap();
+    universe.set_state(2, 2, 3).unwrap();
+    universe.crop_to_bounding_box(1);
+    assert_eq!((universe.width(), universe.height()), (4, 4));
+    assert_eq!(universe.get_state(0, 0).unwrap(), 3);
+    assert_eq!(universe.states().iter().filter(|&&state| state != 0).count(), 3);
+}
+
+#[wasm_bindgen_test]
+pub fn test_shift() {
+    let mut universe = Universe::new_empty(4, 3);
+    universe.set_cells(&[(0, 0), (2, 3)]);
+    universe.shift(-1, 2);
+
+    let mut expected = Universe::new_empty(4, 3);
+    expected.set_cells(&[(2, 3), (1, 2)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+}
+
+#[wasm_bindgen_test]
+pub fn test_rotate_and_flip() {
+    let mut universe = Universe::new_empty(3, 2);
+    universe.set_cells(&[(0, 0), (0, 1), (1, 2)]);
+
+    universe.rotate_cw();
+    assert_eq!((universe.width(), universe.height()), (2, 3));
+    let mut expected = Universe::new_empty(2, 3);
+    expected.set_cells(&[(0, 1), (1, 1), (2, 0)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+
+    universe.rotate_ccw();
+    universe.flip_horizontal();
+    universe.flip_vertical();
+    let mut expected = Universe::new_empty(3, 2);
+    expected.set_cells(&[(1, 2), (1, 1), (0, 0)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+}
+
+#[wasm_bindgen_test]
+pub fn test_from_string() {
+    let universe = input_spaceship();
+    let parsed = Universe::from_string(&universe.render()).unwrap();
+    assert_eq!(parsed.get_cells(), universe.get_cells());
+
+    let parsed = Universe::from_string("..O\nO\n.OO\n").unwrap();
+    assert_eq!((parsed.width(), parsed.height()), (3, 3));
+    let mut expected = Universe::new_empty(3, 3);
+    expected.set_cells(&[(0, 2), (1, 0), (2, 1), (2, 2)]);
+    assert_eq!(parsed.get_cells(), expected.get_cells());
+
+    assert!(Universe::from_string("..x").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_snapshot_equals() {
+    let mut universe = input_spaceship();
+    let snapshot = universe.snapshot();
+    assert!(universe.equals(&snapshot));
+
+    universe.tick();
+    assert!(!universe.equals(&snapshot));
+    assert!(universe == expected_spaceship());
+}
+
+#[wasm_bindgen_test]
+pub fn test_batch_cells() {
+    let mut universe = Universe::new_empty(4, 4);
+    universe.set_cells_js(&[0, 1, 2, 3], true).unwrap();
+    universe.toggle_cells(&[1, 5, 11]).unwrap();
+
+    let mut expected = Universe::new_empty(4, 4);
+    expected.set_cells(&[(1, 1)]);
+    assert!(universe == expected);
+
+    assert!(universe.toggle_cells(&[3, 16]).is_err());
+    assert!(universe.set_cells_js(&[0, 0, 4, 0], true).is_err());
+    assert!(universe == expected);
+}
+
+#[wasm_bindgen_test]
+pub fn test_fill_random_region() {
+    let mut universe = Universe::new_empty(6, 6);
+    universe.fill_random_region(4, 5, 3, 2, 1.0).unwrap();
+
+    let mut expected = Universe::new_empty(6, 6);
+    expected.set_cells(&[(4, 5), (4, 0), (4, 1), (5, 5), (5, 0), (5, 1)]);
+    assert!(universe == expected);
+    assert!(universe.fill_random_region(6, 0, 1, 1, 1.0).is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_invert() {
+    let mut universe = Universe::new_empty(5, 3);
+    universe.set_cells(&[(0, 0), (2, 4)]);
+    universe.invert();
+    assert_eq!(universe.get_cells().count_ones(..), 13);
+    universe.invert();
+
+    universe.invert_region(2, 4, 2, 2).unwrap();
+    let mut expected = Universe::new_empty(5, 3);
+    expected.set_cells(&[(2, 0), (0, 4)]);
+    assert!(universe == expected);
+
+    // Cells the mask leaves out stay dead.
+    let mut arena = Universe::new_empty(10, 10);
+    arena.set_circular_mask();
+    arena.invert();
+    assert!(!arena.get_cell(0, 0).unwrap() && arena.get_cell(5, 5).unwrap());
+    let inside = (0..10).flat_map(|row| (0..10).map(move |col| (row, col)));
+    let inside = inside.filter(|&(row, col)| arena.cell_exists(row, col).unwrap()).count();
+    assert_eq!(arena.get_cells().count_ones(..), inside);
+}
+
+#[wasm_bindgen_test]
+pub fn test_clear_region() {
+    let mut universe = Universe::new_with_density(6, 6, 1.0);
+    universe.clear_region(5, 5, 2, 6).unwrap();
+    assert_eq!(universe.get_cells().count_ones(..), 24);
+    assert!(!universe.get_cell(0, 0).unwrap());
+    assert!(universe.get_cell(0, 1).unwrap());
+}
+
+#[wasm_bindgen_test]
+pub fn test_render_style() {
+    let mut universe = Universe::new_empty(3, 2);
+    universe.set_cells(&[(0, 0), (1, 2)]);
+    assert_eq!(universe.render(), "\n◻◼◼\n◼◼◻");
+
+    universe.set_render_glyphs('#', '-', None);
+    universe.set_row_separator("|");
+    universe.set_render_newlines(false, true);
+    assert_eq!(universe.render(), "#--|--#\n");
+}
+
+#[wasm_bindgen_test]
+pub fn test_rows() {
+    let mut universe = Universe::new_empty(40, 3);
+    universe.set_cells(&[(1, 0), (1, 31), (1, 39), (2, 5)]);
+
+    let row = universe.row(1).unwrap();
+    assert!(row.get(31) && !row.get(30));
+    assert_eq!(row.live_columns().collect::<Vec<u32>>(), vec![0, 31, 39]);
+    assert_eq!(row.words().collect::<Vec<u32>>(), vec![1 | 1 << 31, 1 << 7]);
+    assert!(universe.row(3).is_none());
+
+    let populations: Vec<usize> = universe.rows().map(|row| row.iter().filter(|&alive| alive).count()).collect();
+    assert_eq!(populations, vec![0, 3, 1]);
+}
+
+#[wasm_bindgen_test]
+pub fn test_from_bytes() {
+    let expected = input_spaceship();
+
+    let bytes: Vec<u8> = (0..36).map(|i| expected.get_cells()[i] as u8).collect();
+    assert!(Universe::from_bytes(6, 6, &bytes, false).unwrap() == expected);
+
+    let mut packed = vec![0u8; 5];
+    for i in expected.get_cells().ones() {
+        packed[i / 8] |= 1 << (i % 8);
+    }
+    assert!(Universe::from_bytes(6, 6, &packed, true).unwrap() == expected);
+    assert!(Universe::from_bytes(6, 6, &packed[..4], true).is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_generation() {
+    let mut universe = input_spaceship();
+    universe.tick();
+    universe.tick();
+    assert_eq!(universe.generation(), 2);
+
+    universe.reset_generation();
+    assert_eq!(universe.generation(), 0);
+    universe.tick();
+    universe.reset();
+    assert_eq!(universe.generation(), 0);
+}
+
+#[wasm_bindgen_test]
+pub fn test_put_rle() {
+    let mut universe = Universe::new_empty(6, 6);
+    let glider = "#N Glider\n#O Richard K. Guy\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!";
+    universe.put_rle(glider, 2, 2, None).unwrap();
+    assert!(universe == input_spaceship());
+
+    assert!(universe.put_rle("x = 3, y = 3\nbo?!", 2, 2, None).is_err());
+    assert!(universe.put_rle(glider, 6, 0, None).is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_to_rle() {
+    let universe = input_spaceship();
+    assert_eq!(universe.to_rle(), "x = 6, y = 6, rule = B3/S23\n$2bo$3bo$b3o!\n");
+    assert_eq!(universe.region_to_rle(1, 1, 3, 3).unwrap(), "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+
+    let mut copy = Universe::new_empty(6, 6);
+    copy.put_rle(&universe.to_rle(), 3, 3, None).unwrap();
+    assert!(copy == universe);
+}
+
+#[wasm_bindgen_test]
+pub fn test_plaintext() {
+    let glider = "!Name: Glider\n!\n.O\n..O\nOOO\n";
+    let mut universe = Universe::new_empty(6, 6);
+    universe.put_plaintext(glider, 2, 2, None).unwrap();
+    assert!(universe == input_spaceship());
+
+    assert_eq!(universe.region_to_plaintext(1, 1, 3, 3).unwrap(), ".O.\n..O\nOOO\n");
+    let mut copy = Universe::new_empty(6, 6);
+    copy.put_plaintext(&universe.to_plaintext(), 3, 3, None).unwrap();
+    assert!(copy == universe);
+
+    assert!(universe.put_plaintext("..X", 0, 0, None).is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_life_formats() {
+    let mut universe = Universe::new_empty(6, 6);
+    universe.put_life("#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n", 2, 2, None).unwrap();
+    assert!(universe == input_spaceship());
+    assert_eq!(universe.to_life_106(), "#Life 1.06\n2 1\n3 2\n1 3\n2 3\n3 3\n");
+
+    let mut copy = Universe::new_empty(6, 6);
+    copy.put_life("#Life 1.05\n#D Glider\n#N\n#P -1 -1\n.*\n..*\n***\n", 2, 2, None).unwrap();
+    assert!(copy == universe);
+
+    let mut spaced = Universe::new_empty(6, 6);
+    spaced.set_cells(&[(0, 0), (2, 1)]);
+    let mut copy = Universe::new_empty(6, 6);
+    copy.put_life(&spaced.to_life_105(), 1, 1, None).unwrap();
+    assert!(copy == spaced);
+
+    assert!(universe.put_life("0 1\n", 2, 2, None).is_err());
+
+    // Cells too far apart to fit in a pattern fail to parse.
+    let far = format!("#Life 1.06\n{} 0\n{} 0\n", i64::MIN, i64::MAX);
+    assert!(Pattern::from_life(&far).is_err());
+    assert!(Pattern::from_life("#Life 1.06\n0 0\n100000 100000\n").is_err());
+    assert!(Pattern::from_life("#Life 1.06\n0 0\n5000000000 0\n").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_put_macrocell() {
+    // A glider in the top left leaf of a 16x16 root.
+    let glider = "[M2] (golly 3.0)\n#R B3/S23\n.*$..*$***$\n4 1 0 0 0\n";
+    let mut universe = Universe::new_empty(6, 6);
+    universe.put_macrocell(glider, 2, 2).unwrap();
+    assert!(universe == input_spaceship());
+
+    // A block at the corners of four leaves, cropped by the universe edge.
+    let block = "[M2]\n$$$$$$$.......*$\n$$$$$$$*$\n.......*$\n*$\n4 1 2 3 4\n";
+    let mut universe = Universe::new_empty(3, 3);
+    universe.put_macrocell(block, 0, 0).unwrap();
+    let mut expected = Universe::new_empty(3, 3);
+    expected.set_cells(&[(0, 0)]);
+    assert!(universe == expected);
+
+    // Levels, colors and sand under the pattern follow its cells.
+    for &rule in &["Lenia", "QuadLife", "Sandpile"] {
+        let mut universe = Universe::new_empty(6, 6);
+        universe.set_rule(rule).unwrap();
+        universe.set_state(2, 2, 2).unwrap();
+        universe.set_state(3, 3, 3).unwrap();
+        universe.set_level(1, 1, 0.3).unwrap();
+        universe.put_macrocell(glider, 2, 2).unwrap();
+        assert!(universe.get_cells() == input_spaceship().get_cells(), "{}", rule);
+        let expected: Vec<u8> = input_spaceship().states();
+        assert_eq!(universe.states(), expected, "{}", rule);
+        let levels: Vec<f32> = expected.iter().map(|&state| f32::from(state)).collect();
+        assert_eq!(universe.levels(), levels, "{}", rule);
+    }
+
+    assert!(universe.put_macrocell("4 1 0 0 0", 0, 0).is_err());
+    assert!(universe.put_macrocell("[M2]\n5 1 0 0 0\n", 0, 0).is_err());
+    assert!(universe.put_macrocell("[M2]\n0 0 0 0 0\n", 0, 0).is_err());
+    assert!(universe.put_macrocell("[M2]\n1 1 0 0 1\n4294967298 1 0 0 0\n", 0, 0).is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_to_macrocell() {
+    let universe = input_spaceship();
+    assert_eq!(
+        universe.to_macrocell(),
+        "[M2] (wasm-game-of-life)\n#R B3/S23\n$..*$...*$.***$\n4 1 0 0 0\n"
+    );
+
+    let mut gliders = Universe::new_empty(40, 20);
+    gliders.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+    gliders.set_cells(&[(17, 34), (18, 35), (19, 33), (19, 34), (19, 35)]);
+    gliders.set_cells(&[(9, 26), (10, 27), (11, 25), (11, 26), (11, 27)]);
+    let mut copy = Universe::new_empty(40, 20);
+    copy.put_macrocell(&gliders.to_macrocell(), 10, 18).unwrap();
+    assert!(copy == gliders);
+
+    let empty = Universe::new_empty(3, 3).to_macrocell();
+    assert!(empty.ends_with("\n4 0 0 0 0\n"));
+}
+
+#[wasm_bindgen_test]
+pub fn test_put_pattern() {
+    let mut universe = Universe::new_empty(6, 6);
+    universe.put_glider(2, 2, None).unwrap();
+    assert!(universe == input_spaceship());
+
+    let mut named = Universe::new_empty(6, 6);
+    named.put_pattern("Glider", 2, 2, None, None).unwrap();
+    assert!(named == universe);
+
+    let names = Universe::list_patterns();
+    assert!(names.iter().any(|name| name == "gosper-glider-gun"));
+    for name in names.iter() {
+        let mut universe = Universe::new_empty(40, 40);
+        universe.put_pattern(name, 20, 20, None, None).unwrap();
+        assert!(!universe.is_empty());
+    }
+    assert!(universe.put_pattern("no-such-pattern", 2, 2, None, None).is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_put_glider_transform() {
+    // Each rotation aims the glider in a different diagonal direction.
+    let mut directions = Vec::new();
+    for &transform in [Transform::Identity, Transform::Rotate90, Transform::Rotate180, Transform::Rotate270].iter() {
+        let mut universe = Universe::new_empty(12, 12);
+        universe.put_glider(6, 6, Some(transform)).unwrap();
+        let before = universe.bounding_box().unwrap();
+        for _ in 0..4 {
+            universe.tick();
+        }
+        let after = universe.bounding_box().unwrap();
+        directions.push((after.0 as i32 - before.0 as i32, after.1 as i32 - before.1 as i32));
+    }
+    assert_eq!(directions, vec![(1, 1), (1, -1), (-1, -1), (-1, 1)]);
+
+    let mut flipped = Universe::new_empty(6, 6);
+    flipped.put_glider(2, 2, Some(Transform::Flip)).unwrap();
+    let mut expected = input_spaceship();
+    expected.flip_horizontal();
+    expected.shift(-1, 0);
+    assert!(flipped == expected);
+}
+
+#[wasm_bindgen_test]
+pub fn test_put_spaceships() {
+    let headings = [
+        (Direction::North, (-2, 0)),
+        (Direction::East, (0, 2)),
+        (Direction::South, (2, 0)),
+        (Direction::West, (0, -2)),
+    ];
+    for &(direction, (delta_row, delta_col)) in headings.iter() {
+        let mut universes = [Universe::new_empty(24, 24), Universe::new_empty(24, 24), Universe::new_empty(24, 24)];
+        universes[0].put_lwss(12, 12, Some(direction)).unwrap();
+        universes[1].put_mwss(12, 12, Some(direction)).unwrap();
+        universes[2].put_hwss(12, 12, Some(direction)).unwrap();
+        for universe in universes.iter_mut() {
+            let mut expected = universe.snapshot();
+            expected.shift(delta_col, delta_row);
+            for _ in 0..4 {
+                universe.tick();
+            }
+            assert!(*universe == expected);
+        }
+    }
+
+    let mut universe = Universe::new_empty(24, 24);
+    assert!(universe.put_lwss(12, 12, Some(Direction::NorthEast)).is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_put_gosper_gun() {
+    // The stream grows the bounding box away from the gun, towards `direction`.
+    let growth = [
+        (Direction::SouthEast, (0, 0, 1, 1)),
+        (Direction::SouthWest, (0, -1, 1, 0)),
+        (Direction::NorthWest, (-1, -1, 0, 0)),
+        (Direction::NorthEast, (-1, 0, 0, 1)),
+    ];
+    for &(direction, expected) in growth.iter() {
+        let mut universe = Universe::new_empty(100, 100);
+        universe.put_gosper_gun(50, 50, Some(direction)).unwrap();
+        let edges = |universe: &Universe| {
+            let (row, col, width, height) = universe.bounding_box().unwrap();
+            [row as i32, col as i32, (row + height) as i32, (col + width) as i32]
+        };
+        let before = edges(&universe);
+        for _ in 0..120 {
+            universe.tick();
+        }
+        let after = edges(&universe);
+        let signs: Vec<i32> = before.iter().zip(after.iter()).map(|(b, a)| (a - b).signum()).collect();
+        assert_eq!(signs, vec![expected.0, expected.1, expected.2, expected.3]);
+    }
+
+    let mut universe = Universe::new_empty(100, 100);
+    assert!(universe.put_gosper_gun(50, 50, Some(Direction::North)).is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_methuselahs() {
+    let mut universe = Universe::new_empty(64, 64);
+    universe.put_pattern("diehard", 32, 32, None, None).unwrap();
+    for _ in 0..129 {
+        universe.tick();
+    }
+    assert!(!universe.is_empty());
+    universe.tick();
+    assert!(universe.is_empty());
+
+    for name in ["r-pentomino", "acorn"].iter() {
+        let mut universe = Universe::new_empty(64, 64);
+        universe.put_pattern(name, 32, 32, None, None).unwrap();
+        assert!(universe.get_cells().count_ones(..) <= 7);
+    }
+}
+
+#[wasm_bindgen_test]
+pub fn test_pattern_object() {
+    let glider = Pattern::from_rle("x = 3, y = 3\nbob$2bo$3o!").unwrap();
+    assert_eq!((glider.width_js(), glider.height_js(), glider.population()), (3, 3, 5));
+    assert!(Pattern::from_plaintext(".O\n..O\nOOO").unwrap() == glider);
+    assert!(Pattern::from_library("glider").unwrap() == glider);
+    assert_eq!(glider.transformed_js(Transform::Rotate180).to_plaintext(), "OOO\nO..\n.O.\n");
+
+    let mut universe = Universe::new_empty(6, 6);
+    universe.stamp(&glider, 2, 2, None).unwrap();
+    assert!(universe == input_spaceship());
+    assert!(universe.stamp(&glider, 6, 2, None).is_err());
+
+    let lwss = Pattern::from_library("lwss").unwrap().transformed_js(Transform::Rotate90);
+    assert_eq!((lwss.width_js(), lwss.height_js()), (4, 5));
+    assert!(Pattern::from_rle("3o?").is_err());
+
+    // Patterns too big to hold fail to parse.
+    assert!(Pattern::from_rle("4294967295b4294967295bo!").is_err());
+    assert!(Pattern::from_rle("4294967295$4294967295$o!").is_err());
+    assert!(Pattern::from_rle("x = 70000, y = 70000
+o!").is_err());
+    assert!(Pattern::from_rle("x = 20000, y = 20000
+o!").is_err());
+    assert!(Pattern::from_rle("300000000o!").is_err());
+    assert!(Pattern::from_apgcode(&format!("xs1_{}{}1", "z".repeat(20000), "yz".repeat(2600))).is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_pattern_from_text() {
+    let glider = Pattern::from_library("glider").unwrap();
+    assert!(Pattern::from_text("x = 3, y = 3, rule = B3/S23\nbob$2bo$3o!").unwrap() == glider);
+    assert!(Pattern::from_text("!Name: Glider\n.O\n..O\nOOO\n").unwrap() == glider);
+    assert!(Pattern::from_text("#Life 1.06\n1 0\n2 1\n0 2\n1 2\n2 2\n").unwrap() == glider);
+    assert!(Pattern::from_text("#Life 1.05\n#P -1 -1\n.*\n..*\n***\n").unwrap() == glider);
+    assert!(Pattern::from_text("bo$2bo$3o?").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_pattern_metadata() {
+    let rle = "#N Glider\n#O Richard K. Guy\n#C The smallest spaceship.\n#C www.conwaylife.com/wiki/Glider\nx = 3, y = 3, rule = B36/S23\nbo$2bo$3o!";
+    let glider = Pattern::from_rle(rle).unwrap();
+    assert_eq!(glider.name(), Some("Glider".to_string()));
+    assert_eq!(glider.author(), Some("Richard K. Guy".to_string()));
+    assert_eq!(glider.comments(), vec!["The smallest spaceship.", "www.conwaylife.com/wiki/Glider"]);
+    assert_eq!(glider.rule(), Some("B36/S23".to_string()));
+    assert_eq!(glider.to_rle(), format!("{}\n", rle));
+    assert!(glider == Pattern::from_library("glider").unwrap());
+
+    let bare = Pattern::from_rle("3o!").unwrap();
+    assert_eq!((bare.name(), bare.author(), bare.rule()), (None, None, None));
+    assert!(bare.comments().is_empty());
+}
+
+#[wasm_bindgen_test]
+pub fn test_export_region() {
+    let mut universe = Universe::new_empty(10, 10);
+    universe.put_glider(4, 4, None).unwrap();
+    universe.set_cell(0, 9, true).unwrap();
+
+    let glider = universe.export_region(1, 1, 7, 7).unwrap();
+    assert_eq!((glider.width_js(), glider.height_js()), (3, 3));
+    assert_eq!(glider.to_rle(), "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+
+    // The selection wraps, picking up the cell in the top right corner.
+    let wrapped = universe.export_region(9, 8, 10, 7).unwrap();
+    assert_eq!((wrapped.width_js(), wrapped.height_js()), (7, 6));
+    assert_eq!(wrapped.population(), 6);
+
+    let empty = universe.export_region(7, 0, 2, 2).unwrap();
+    assert_eq!((empty.width_js(), empty.height_js(), empty.population()), (0, 0, 0));
+    assert!(universe.export_region(10, 0, 2, 2).is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_apgcode() {
+    let codes = [
+        ("block", "xs4_33"),
+        ("beehive", "xs6_696"),
+        ("blinker", "xp2_7"),
+        ("glider", "xq4_153"),
+        ("lwss", "xq4_6frc"),
+        ("pulsar", "xp3_co9nas0san9oczgoldlo0oldlogz1047210127401"),
+    ];
+    for &(name, code) in codes.iter() {
+        let pattern = Pattern::from_library(name).unwrap();
+        assert_eq!(pattern.to_apgcode().unwrap(), code, "{}", name);
+        assert_eq!(Pattern::from_apgcode(code).unwrap().to_apgcode().unwrap(), code, "{}", name);
+    }
+    assert_eq!(Pattern::new(0, 0).unwrap().to_apgcode().unwrap(), "xs0_0");
+    assert_eq!(Pattern::from_apgcode("xs4_33").unwrap().to_plaintext(), "OO\nOO\n");
+    assert_eq!(Pattern::from_apgcode("xs4_11z11").unwrap().to_plaintext(), "OO\n..\n..\n..\n..\nOO\n");
+    assert!(Pattern::from_library("r-pentomino").unwrap().to_apgcode().is_err());
+    assert!(Pattern::from_text("#C c\n1230A").unwrap().to_apgcode().is_err());
+    assert!(Pattern::from_rle("300o!").unwrap().to_apgcode().is_err());
+    assert!(Pattern::from_apgcode("yl144_1_16_afb5f3db909e60548f086e22ee3353ac").is_err());
+    assert!(Pattern::from_apgcode("xs4_3!").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_search_patterns() {
+    assert_eq!(
+        Universe::search_patterns("GLIDER"),
+        "[{\"name\":\"glider\",\"size\":{\"width\":3,\"height\":3},\"period\":4,\"category\":\"spaceship\"},\
+         {\"name\":\"gosper-glider-gun\",\"size\":{\"width\":36,\"height\":9},\"period\":30,\"category\":\"gun\"}]"
+    );
+    assert_eq!(
+        Universe::search_patterns("methuselah"),
+        "[{\"name\":\"r-pentomino\",\"size\":{\"width\":3,\"height\":3},\"period\":null,\"category\":\"methuselah\"},\
+         {\"name\":\"acorn\",\"size\":{\"width\":7,\"height\":3},\"period\":null,\"category\":\"methuselah\"},\
+         {\"name\":\"diehard\",\"size\":{\"width\":8,\"height\":3},\"period\":null,\"category\":\"methuselah\"}]"
+    );
+    assert_eq!(Universe::search_patterns("").matches("\"name\"").count(), Universe::list_patterns().len());
+    assert_eq!(Universe::search_patterns("no such pattern"), "[]");
+}
+
+#[wasm_bindgen_test]
+pub fn test_blend_modes() {
+    let block = Pattern::from_rle("3o$obo$3o!").unwrap();
+    let mut base = Universe::new_empty(5, 5);
+    base.set_cell(2, 2, true).unwrap();
+    base.set_cell(0, 0, true).unwrap();
+
+    let mut overwrite = base.snapshot();
+    overwrite.stamp(&block, 2, 2, None).unwrap();
+    let mut explicit = base.snapshot();
+    explicit.stamp(&block, 2, 2, Some(Blend::Overwrite)).unwrap();
+    assert!(overwrite == explicit);
+    assert!(!overwrite.get_cell(2, 2).unwrap());
+    assert!(overwrite.get_cell(0, 0).unwrap());
+
+    let mut or = base.snapshot();
+    or.put_rle("3o$obo$3o!", 2, 2, Some(Blend::Or)).unwrap();
+    assert!(or.get_cell(2, 2).unwrap());
+    assert_eq!(or.to_plaintext(), "O....\n.OOO.\n.OOO.\n.OOO.\n.....\n");
+
+    let mut xor = base.snapshot();
+    xor.stamp(&block, 1, 1, Some(Blend::Xor)).unwrap();
+    assert_eq!(xor.to_plaintext(), ".OO..\nO.O..\nOO...\n.....\n.....\n");
+
+    let mut library = base.snapshot();
+    library.put_pattern("blinker", 2, 2, None, Some(Blend::Or)).unwrap();
+    assert!(library.get_cell(0, 0).unwrap());
+    assert_eq!(library.to_plaintext(), "O....\n.....\n.OOO.\n.....\n.....\n");
+}
+
+#[wasm_bindgen_test]
+pub fn test_put_random_soup() {
+    let soup = |size: u32, symmetry: Option<Symmetry>| {
+        let mut universe = Universe::new_with_seed(size, size, 7);
+        universe.put_random_soup(size / 2, size / 2, size, 0.5, symmetry).unwrap();
+        universe
+    };
+
+    let c1 = soup(16, None);
+    assert!(c1 == soup(16, Some(Symmetry::C1)));
+    assert!(!c1.is_empty());
+
+    let checks: [(Symmetry, &[Transform]); 5] = [
+        (Symmetry::C2, &[Transform::Rotate180]),
+        (Symmetry::C4, &[Transform::Rotate90, Transform::Rotate180]),
+        (Symmetry::D2, &[Transform::Flip]),
+        (Symmetry::D4, &[Transform::Flip, Transform::FlipRotate180, Transform::Rotate180]),
+        (Symmetry::D8, &[Transform::Rotate90, Transform::Flip, Transform::FlipRotate90]),
+    ];
+    for &(symmetry, transforms) in checks.iter() {
+        for size in [15, 16].iter() {
+            let universe = soup(*size, Some(symmetry));
+            let whole = Pattern::from_plaintext(&universe.to_plaintext()).unwrap();
+            assert!(whole.population() > 0, "{:?}", symmetry);
+            for &transform in transforms {
+                assert!(whole.transformed_js(transform) == whole, "{:?} {:?}", symmetry, transform);
+            }
+        }
+    }
+    let c2 = Pattern::from_plaintext(&soup(16, Some(Symmetry::C2)).to_plaintext()).unwrap();
+    assert!(c2.transformed_js(Transform::Flip) != c2);
+    assert!(Universe::new_empty(8, 8).put_random_soup(8, 0, 4, 0.5, None).is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_pattern_thumbnail() {
+    let alive = [0x00, 0x00, 0x00, 0xff];
+    let dead = [0xff, 0xff, 0xff, 0xff];
+    // Rows of a thumbnail as strings of '#' for live and '.' for dead pixels.
+    let rows = |pixels: Vec<u8>, width: usize| -> Vec<String> {
+        pixels
+            .chunks(4)
+            .map(|pixel| if pixel == alive { '#' } else { assert_eq!(pixel, dead); '.' })
+            .collect::<Vec<char>>()
+            .chunks(width)
+            .map(|row| row.iter().collect())
+            .collect()
+    };
+
+    let glider = Pattern::from_library("glider").unwrap();
+    assert_eq!(
+        rows(glider.thumbnail(8, 7), 8),
+        vec!["...##...", "...##...", ".....##.", ".....##.", ".######.", ".######.", "........"]
+    );
+
+    let blinker = Pattern::from_library("blinker").unwrap();
+    assert_eq!(rows(blinker.thumbnail(3, 2), 3), vec!["###", "..."]);
+
+    let gun = Pattern::from_library("gosper-glider-gun").unwrap();
+    let icon = gun.thumbnail(12, 12);
+    assert_eq!(icon.len(), 12 * 12 * 4);
+    // The 36x9 gun shrinks to 12x3 pixels, centered vertically.
+    assert_eq!(rows(icon, 12)[3..8], ["............", "....#.###..#", "#..######..#", "...###..#...", "............"]);
+
+    assert_eq!(Pattern::new(0, 0).unwrap().thumbnail(2, 2), dead.repeat(4));
+}
+
+#[wasm_bindgen_test]
+pub fn test_rules() {
+    let mut universe = Universe::new_empty(8, 8);
+    assert_eq!(universe.rule(), "B3/S23");
+
+    // HighLife's B6 turns this into a birth on the empty center.
+    universe.set_rule("b36/s23").unwrap();
+    assert_eq!(universe.rule(), "B36/S23");
+    universe.set_cells(&[(2, 2), (2, 3), (2, 4), (4, 2), (4, 3), (4, 4)]);
+    universe.tick();
+    assert!(universe.get_cell(3, 3).unwrap());
+
+    for &(rulestring, normalized) in [
+        ("B3/S23", "B3/S23"),
+        ("B3S23", "B3/S23"),
+        ("S23/B3", "B3/S23"),
+        ("23/3", "B3/S23"),
+        ("/2", "B2/S"),
+        ("B1357/S1357", "B1357/S1357"),
+        (" b2/s ", "B2/S"),
+    ]
+    .iter()
+    {
+        universe.set_rule(rulestring).unwrap();
+        assert_eq!(universe.rule(), normalized, "{}", rulestring);
+    }
+    for rulestring in ["B9/S23", "B3/S23/S4", "B3", "NotARule", "3/2/1", "B3/Sx", "B3/S2é"].iter() {
+        assert!(universe.set_rule(rulestring).is_err(), "{}", rulestring);
+    }
+    assert_eq!(universe.rule(), "B2/S");
+
+    // Seeds: every live cell dies, and cells with two neighbors are born.
+    let mut seeds = Universe::new_empty(6, 6);
+    seeds.set_rule("B2/S").unwrap();
+    seeds.set_cells(&[(2, 2), (2, 3)]);
+    seeds.tick();
+    assert_eq!(seeds.to_rle(), "x = 6, y = 6, rule = B2/S\n$2b2o2$2b2o!\n");
+}
+
+#[wasm_bindgen_test]
+pub fn test_rule_presets() {
+    let mut universe = Universe::new_empty(4, 4);
+    assert_eq!(universe.rule_name(), Some("Conway".to_string()));
+    let names = Universe::list_rules();
+    assert!(names.len() >= 10);
+    for name in names.iter() {
+        universe.set_rule(name).unwrap();
+        assert_eq!(universe.rule_name().as_ref(), Some(name));
+    }
+
+    universe.set_rule("dayandnight").unwrap();
+    assert_eq!(universe.rule(), "B3678/S34678");
+    universe.set_rule("2x2").unwrap();
+    assert_eq!(universe.rule(), "B36/S125");
+    universe.set_rule("B35678/S5678").unwrap();
+    assert_eq!(universe.rule_name(), Some("Diamoeba".to_string()));
+    universe.set_rule("B37/S23").unwrap();
+    assert_eq!(universe.rule_name(), None);
+    assert!(universe.set_rule("NoSuchRule").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_b0_rules() {
+    // Without S8 empty space strobes, and a dead boundary follows it so the
+    // grid matches a window into an infinite plane of the same rule.
+    let mut bounded = UniverseBuilder::new().size(7, 7).density(0.0).boundary(Boundary::Dead).build().unwrap();
+    bounded.set_rule("B0/S").unwrap();
+    bounded.set_cell(3, 3, true).unwrap();
+    bounded.tick();
+    assert!(bounded.background());
+    assert_eq!(bounded.to_plaintext(), "OOOOOOO\nOOOOOOO\nOO...OO\nOO...OO\nOO...OO\nOOOOOOO\nOOOOOOO\n");
+    bounded.tick();
+    assert!(!bounded.background());
+    assert_eq!(bounded.to_plaintext(), ".......\n.......\n.......\n...O...\n.......\n.......\n.......\n");
+
+    // With S8 empty space turns on once and stays on.
+    let mut torus = Universe::new_empty(5, 5);
+    torus.set_rule("B0/S8").unwrap();
+    torus.tick();
+    assert!(torus.background());
+    assert_eq!(torus.export_region(0, 0, 5, 5).unwrap().population(), 25);
+    torus.tick();
+    assert!(torus.background());
+    assert_eq!(torus.export_region(0, 0, 5, 5).unwrap().population(), 25);
+
+    torus.reset_with_density(0.0);
+    assert!(!torus.background());
+}
+
+#[wasm_bindgen_test]
+pub fn test_generations_rules() {
+    let mut universe = Universe::new_empty(5, 5);
+    universe.set_rule("345/2/4").unwrap();
+    assert_eq!(universe.rule(), "B2/S345/C4");
+    assert_eq!(universe.rule_name(), Some("StarWars".to_string()));
+    assert_eq!(universe.state_count(), 4);
+    for &(rulestring, normalized) in [("B2/S345/4", "B2/S345/C4"), ("b2s345c4", "B2/S345/C4"), ("23/3/2", "B3/S23")].iter() {
+        universe.set_rule(rulestring).unwrap();
+        assert_eq!(universe.rule(), normalized);
+    }
+    assert!(universe.set_rule("345/2/1").is_err());
+    assert!(universe.set_rule("B2/S345/C4/C5").is_err());
+
+    // A lone live cell dies of isolation and fades through states 2 and 3.
+    universe.set_rule("StarWars").unwrap();
+    universe.set_cell(2, 2, true).unwrap();
+    let states = |universe: &Universe| universe.states()[12];
+    assert_eq!(states(&universe), 1);
+    universe.tick();
+    assert_eq!((universe.get_state(2, 2).unwrap(), universe.get_cell(2, 2).unwrap()), (2, false));
+    universe.tick();
+    assert_eq!(states(&universe), 3);
+    universe.tick();
+    assert_eq!(states(&universe), 0);
+    assert!(universe.states().iter().all(|&state| state == 0));
+
+    // Dying cells block births: two live cells would give birth to their
+    // neighbors, except where a dying cell sits.
+    universe.set_cells(&[(1, 1), (1, 3)]);
+    universe.set_state(2, 2, 2).unwrap();
+    universe.set_render_glyphs('O', '.', Some('x'));
+    universe.set_render_newlines(false, true);
+    assert_eq!(universe.render(), ".....\n.O.O.\n..x..\n.....\n.....\n");
+    universe.tick();
+    assert_eq!(universe.render(), "..O..\n.xOx.\n..x..\n.....\n.....\n");
+
+    universe.set_state(0, 0, 9).unwrap();
+    assert_eq!(universe.get_state(0, 0).unwrap(), 3);
+    universe.rotate_cw();
+    assert_eq!(universe.get_state(0, 4).unwrap(), 3);
+    universe.set_cell(0, 4, false).unwrap();
+    assert_eq!(universe.get_state(0, 4).unwrap(), 0);
+
+    universe.set_rule("B3/S23").unwrap();
+    assert_eq!(universe.state_count(), 2);
+    assert!(universe.states().iter().all(|&state| state < 2));
+}
+
+#[wasm_bindgen_test]
+pub fn test_brians_brain() {
+    let mut universe = Universe::new_empty(4, 4);
+    universe.set_rule("BriansBrain").unwrap();
+    assert_eq!((universe.rule(), universe.state_count()), ("B2/S/C3".to_string(), 3));
+
+    // Two firing cells light up the cells next to both, then go refractory.
+    universe.set_cells(&[(1, 1), (1, 2)]);
+    universe.tick();
+    assert_eq!(universe.states(), vec![0, 1, 1, 0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 0, 0, 0]);
+
+    let pixels = universe.pixels();
+    assert_eq!(pixels.len(), 4 * 4 * 4);
+    assert_eq!(pixels[0..4], [0x00, 0x00, 0x00, 0xff]);
+    assert_eq!(pixels[4..8], [0xff, 0xff, 0xff, 0xff]);
+    assert_eq!(pixels[20..24], [0x30, 0x60, 0xff, 0xff]);
+
+    // Other rules draw black on white, dying cells fading to white.
+    let mut star_wars = Universe::new_empty(4, 1);
+    star_wars.set_rule("StarWars").unwrap();
+    for (col, state) in [1u8, 2, 3].iter().enumerate() {
+        star_wars.set_state(0, col as u32 + 1, *state).unwrap();
+    }
+    assert_eq!(
+        star_wars.pixels(),
+        vec![0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0xff, 0x55, 0x55, 0x55, 0xff, 0xaa, 0xaa, 0xaa, 0xff]
+    );
+
+    // Life-like rules fill their pixels straight from the live cells.
+    let mut life = Universe::new_empty(5, 1);
+    life.set_cells(&[(0, 0), (0, 4)]);
+    let (alive, dead) = ([0x00, 0x00, 0x00, 0xff], [0xff, 0xff, 0xff, 0xff]);
+    assert_eq!(life.pixels(), [alive, dead, dead, dead, alive].concat());
+}
+
+#[wasm_bindgen_test]
+pub fn test_wireworld() {
+    let mut universe = Universe::new_empty(7, 3);
+    assert!(universe.draw_wire(1, 1, 1, 5).is_err());
+    universe.set_rule("Wireworld").unwrap();
+    assert_eq!((universe.rule(), universe.state_count()), ("Wireworld".to_string(), 4));
+    assert!(universe.draw_wire(1, 1, 1, 7).is_err());
+
+    // An electron runs down the wire away from its tail.
+    universe.draw_wire(1, 1, 1, 5).unwrap();
+    universe.inject_electron(1, 2, 1, 1).unwrap();
+    let wire = |universe: &Universe| universe.states()[7..14].to_vec();
+    assert_eq!(wire(&universe), vec![0, 2, 1, 3, 3, 3, 0]);
+    universe.tick();
+    assert_eq!(wire(&universe), vec![0, 3, 2, 1, 3, 3, 0]);
+    universe.tick();
+    universe.tick();
+    assert_eq!(wire(&universe), vec![0, 3, 3, 3, 2, 1, 0]);
+    universe.tick();
+    assert_eq!(wire(&universe), vec![0, 3, 3, 3, 3, 2, 0]);
+
+    let pixels = universe.pixels();
+    assert_eq!(pixels[0..4], [0x00, 0x00, 0x00, 0xff]);
+    assert_eq!(pixels[32..36], [0xff, 0xc8, 0x00, 0xff]);
+    assert_eq!(pixels[48..52], [0xff, 0x30, 0x20, 0xff]);
+
+    // Diagonal wires are drawn a cell per row.
+    let mut diagonal = Universe::new_empty(3, 3);
+    diagonal.set_rule("Wireworld").unwrap();
+    diagonal.draw_wire(2, 0, 0, 2).unwrap();
+    assert_eq!(diagonal.states(), vec![0, 0, 3, 0, 3, 0, 3, 0, 0]);
+
+    // Steeper and shallower wires end on their last cell.
+    let mut steep = Universe::new_empty(3, 3);
+    steep.set_rule("Wireworld").unwrap();
+    steep.draw_wire(0, 0, 2, 1).unwrap();
+    assert_eq!(steep.states(), vec![3, 0, 0, 0, 3, 0, 0, 3, 0]);
+    let mut shallow = Universe::new_empty(6, 2);
+    shallow.set_rule("Wireworld").unwrap();
+    shallow.draw_wire(1, 5, 0, 0).unwrap();
+    assert_eq!(shallow.states().iter().filter(|&&state| state == 3).count(), 6);
+    assert_eq!((shallow.get_state(0, 0).unwrap(), shallow.get_state(1, 5).unwrap()), (3, 3));
+}
+
+#[wasm_bindgen_test]
+pub fn test_langtons_ant() {
+    let mut universe = Universe::new_empty(5, 5);
+    universe.set_rule("B/S012345678").unwrap();
+    universe.add_ant(2, 2, None).unwrap();
+    assert!(universe.add_ant(5, 0, None).is_err());
+
+    // The ant turns right on dead cells, leaving them alive, so it walks a
+    // square before turning left onto the first cell and clearing it.
+    for _ in 0..4 {
+        universe.tick();
+    }
+    let mut expected = Universe::new_empty(5, 5);
+    expected.set_cells(&[(2, 2), (2, 3), (3, 2), (3, 3)]);
+    assert!(universe == expected);
+    assert_eq!(universe.ants(), vec![2, 2, Direction::North as u32, 0]);
+    universe.tick();
+    assert!(!universe.get_cell(2, 2).unwrap());
+    assert_eq!(universe.ants(), vec![2, 1, Direction::West as u32, 0]);
+
+    universe.rotate_cw();
+    assert_eq!(universe.ants(), vec![1, 2, Direction::North as u32, 0]);
+    assert_eq!(universe.remove_ants(1, 2).unwrap(), 1);
+    assert!(universe.ants().is_empty());
+
+    // Longer rules color cells in extra states.
+    assert!(universe.set_ant_rule("LRX").is_err());
+    universe.set_ant_rule("llrr").unwrap();
+    assert_eq!((universe.ant_rule(), universe.state_count()), ("LLRR".to_string(), 4));
+    universe.set_state(0, 0, 2).unwrap();
+    universe.add_ant(0, 0, Some(Direction::North)).unwrap();
+    universe.tick();
+    assert_eq!(universe.get_state(0, 0).unwrap(), 3);
+    assert_eq!(universe.ants(), vec![0, 1, Direction::East as u32, 0]);
+    universe.tick();
+    assert_eq!(universe.get_state(0, 0).unwrap(), 3);
+}
+
+#[wasm_bindgen_test]
+pub fn test_turmites() {
+    let mut universe = Universe::new_empty(8, 8);
+    universe.set_rule("B/S012345678").unwrap();
+    // Langton's ant as a transition table, and in Golly's notation.
+    universe.add_turmite(1, 1, "[[[1, 2, 0], [0, 8, 0]]]", None).unwrap();
+    universe.add_ant(5, 5, None).unwrap();
+    universe.add_turmite(1, 5, "{{{1, 2, 0}, {0, 8, 0}}}", None).unwrap();
+    for _ in 0..5 {
+        universe.tick();
+    }
+    assert_eq!(universe.get_cells().count_ones(..), 9);
+    assert_eq!(universe.ants()[0..4], [1, 0, Direction::West as u32, 0]);
+    assert_eq!(universe.ants()[4..8], [5, 4, Direction::West as u32, 0]);
+
+    // A turmite switching states: it turns right from state 0, then keeps
+    // going from state 1 while leaving color 1 behind.
+    let mut universe = Universe::new_empty(8, 8);
+    universe.set_rule("B/S012345678").unwrap();
+    universe.add_turmite(4, 4, "[[[1, 2, 1], [1, 2, 1]], [[1, 1, 0], [0, 1, 0]]]", None).unwrap();
+    universe.tick();
+    assert_eq!(universe.ants(), vec![4, 5, Direction::East as u32, 1]);
+    universe.tick();
+    assert_eq!(universe.ants(), vec![4, 6, Direction::East as u32, 0]);
+    assert_eq!(universe.states()[36..39], [1, 1, 0]);
+
+    assert!(universe.add_turmite(0, 0, "[[[2, 2, 0], [0, 8, 0]]]", None).is_err());
+    assert!(universe.add_turmite(0, 0, "[[[1, 2, 1]]]", None).is_err());
+    assert!(universe.add_turmite(0, 0, "[[[1, 3, 0]]]", None).is_err());
+    assert!(universe.add_turmite(0, 0, "[[[1, 2, 0]]", None).is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_elementary_rules() {
+    let mut universe = Universe::new_empty(7, 4);
+    universe.set_rule("Rule90").unwrap();
+    assert_eq!(universe.rule(), "W90");
+    assert!(universe.set_rule("W256").is_err());
+
+    // A single cell on the bottom row grows a Sierpinski triangle upwards.
+    universe.set_cell(3, 3, true).unwrap();
+    for _ in 0..3 {
+        universe.tick();
+    }
+    let expected = Universe::from_string("...O...\n..O.O..\n.O...O.\nO.O.O.O").unwrap();
+    assert!(universe == expected);
+
+    universe.set_rule("w110").unwrap();
+    assert_eq!(universe.rule_name(), Some("Rule110".to_string()));
+}
+
+#[wasm_bindgen_test]
+pub fn test_cyclic_rules() {
+    let mut universe = Universe::new_empty(4, 1);
+    universe.set_rule("r1/t1/c3/nm").unwrap();
+    assert_eq!((universe.rule(), universe.state_count()), ("R1/T1/C3/NM".to_string(), 3));
+    assert!(universe.set_rule("R2/T1/C3/NM").is_err());
+    assert!(universe.set_rule("R1/T9/C3/NM").is_err());
+
+    // Each cell advances when a neighbor is already in its next state, the
+    // last state giving way to 0.
+    let mut universe = Universe::new_empty(5, 5);
+    universe.set_rule("R1/T1/C3/NM").unwrap();
+    for (col, state) in [0u8, 1, 2].iter().enumerate() {
+        universe.set_state(2, col as u32 + 1, *state).unwrap();
+    }
+    universe.set_state(0, 0, 1).unwrap();
+    universe.tick();
+    assert_eq!(universe.states()[10..15], [0, 1, 2, 0, 0]);
+    assert_eq!(universe.states()[0..2], [1, 1]);
+
+    // States are spread around the color wheel.
+    let pixels = universe.pixels();
+    assert_eq!(pixels[0..4], [0x00, 0xff, 0x00, 0xff]);
+    assert_eq!(pixels[8..12], [0xff, 0x00, 0x00, 0xff]);
+    assert_eq!(pixels[48..52], [0x00, 0x00, 0xff, 0xff]);
+
+    universe.reset_with_states();
+    assert!(universe.states().iter().all(|&state| state < 3));
+    assert!(universe.states().contains(&2));
+}
+
+#[wasm_bindgen_test]
+pub fn test_hexagonal_rules() {
+    let mut universe = Universe::new_empty(6, 6);
+    universe.set_rule("b2/s34h").unwrap();
+    assert_eq!(universe.rule(), "B2/S34H");
+    assert!(universe.set_rule("B7/S34H").is_err());
+
+    // On even rows the neighbors above and below lean left, so the pair only
+    // shares (1, 2) and (3, 2), which are born while the pair dies out.
+    universe.set_cells(&[(2, 2), (2, 3)]);
+    universe.tick();
+    let mut expected = Universe::new_empty(6, 6);
+    expected.set_cells(&[(1, 2), (3, 2)]);
+    assert!(universe == expected);
+
+    assert_eq!(universe.cell_center(1, 0)[0], 1.0);
+    assert_eq!(universe.cell_center(2, 0)[0], 0.5);
+    universe.set_rule("B3/S23").unwrap();
+    assert_eq!(universe.cell_center(1, 0), vec![0.5, 1.5]);
+}
+
+#[wasm_bindgen_test]
+pub fn test_von_neumann_rules() {
+    let mut universe = Universe::new_empty(5, 5);
+    universe.set_rule("B1/SV").unwrap();
+    assert_eq!(universe.rule(), "B1/SV");
+    assert!(universe.set_rule("B5/SV").is_err());
+
+    // Only the four orthogonal neighbors count.
+    universe.set_cell(2, 2, true).unwrap();
+    universe.tick();
+    let mut expected = Universe::new_empty(5, 5);
+    expected.set_cells(&[(1, 2), (2, 1), (2, 3), (3, 2)]);
+    assert!(universe == expected);
+
+    universe.set_rule("R1/T1/C3/NN").unwrap();
+    assert_eq!(universe.rule(), "R1/T1/C3/NN");
+    assert!(universe.set_rule("R1/T5/C3/NN").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_custom_neighborhood() {
+    let mut universe = Universe::new_empty(7, 7);
+    universe.set_rule("B1/S").unwrap();
+    universe.set_neighborhood(&[-2, -1, -2, 1, -1, -2, -1, 2, 1, -2, 1, 2, 2, -1, 2, 1]).unwrap();
+
+    // A lone cell gives birth a knight's move away in every direction.
+    universe.set_cell(3, 3, true).unwrap();
+    universe.tick();
+    let mut expected = Universe::new_empty(7, 7);
+    expected.set_cells(&[(1, 2), (1, 4), (2, 1), (2, 5), (4, 1), (4, 5), (5, 2), (5, 4)]);
+    assert!(universe == expected);
+
+    // Back to the rule's own Moore neighborhood.
+    universe.set_neighborhood(&[]).unwrap();
+    universe.empty_cells();
+    universe.set_cell(3, 3, true).unwrap();
+    universe.tick();
+    assert_eq!(universe.get_cells().count_ones(..), 8);
+    assert!(universe.get_cell(2, 2).unwrap());
+}
+
+#[wasm_bindgen_test]
+pub fn test_larger_than_life() {
+    let mut universe = Universe::new_empty(8, 8);
+    universe.set_rule("Bosco").unwrap();
+    assert_eq!(universe.rule(), "R5,C0,M1,S34..58,B34..45,NM");
+    assert!(universe.set_rule("R1,C0,M0,S2..9,B3..3,NM").is_err());
+    assert!(universe.set_rule("R0,C0,M0,S2..3,B3..3,NM").is_err());
+
+    // At range 1 Larger than Life runs Life, with or without counting the
+    // middle cell, on any boundary.
+    for &boundary in [Boundary::Torus, Boundary::Dead].iter() {
+        let mut life = UniverseBuilder::new().size(12, 10).seed(7).density(0.4).boundary(boundary).build().unwrap();
+        let mut without_middle = life.snapshot();
+        without_middle.set_rule("R1,C0,M0,S2..3,B3..3,NM").unwrap();
+        let mut with_middle = life.snapshot();
+        with_middle.set_rule("r1,c2,m1,s3..4,b3..3").unwrap();
+        for _ in 0..8 {
+            life.tick();
+            without_middle.tick();
+            with_middle.tick();
+        }
+        assert!(without_middle == life);
+        assert!(with_middle == life);
+    }
+
+    // Grids of no cells step to nothing.
+    for &(width, height) in [(3, 0), (0, 3)].iter() {
+        let mut empty = Universe::new_empty(width, height);
+        empty.set_rule("Bosco").unwrap();
+        empty.tick();
+        assert_eq!(empty.get_cells().len(), 0);
+    }
+}
+
+#[wasm_bindgen_test]
+pub fn test_rule_tables() {
+    // Life as a rule table with bound and permuted variables.
+    let life = "@RULE TableLife
+@TABLE
+n_states:2
+neighborhood:Moore
+symmetries:permute
+var a={0,1}
+var b=a
+var c=a
+var d=a
+var e=a
+var f=a
+# Birth with three neighbors, survival with two or three.
+0,1,1,1,0,0,0,0,0,1
+1,1,1,0,0,0,0,0,0,1
+1,1,1,1,0,0,0,0,0,1
+1,a,b,c,d,e,f,0,0,0
+1,1,1,1,1,a,b,c,d,0
+@COLORS
+1 255 0 0
+";
+    let mut universe = UniverseBuilder::new().size(10, 10).seed(3).density(0.4).build().unwrap();
+    let mut expected = universe.snapshot();
+    universe.set_rule_table(life).unwrap();
+    assert_eq!((universe.rule(), universe.state_count()), ("TableLife".to_string(), 2));
+    for _ in 0..6 {
+        universe.tick();
+        expected.tick();
+    }
+    assert!(universe == expected);
+    let live = (0..100).find(|&idx| universe.states()[idx] == 1).unwrap();
+    assert_eq!(universe.pixels()[live * 4..live * 4 + 4], [0xff, 0x00, 0x00, 0xff]);
+
+    // An old style table without sections, rotated, with states written
+    // without commas: a signal moving along a wire of state 2.
+    let table = "n_states:3
+neighborhood:vonNeumann
+symmetries:rotate4
+var a={0,1,2}
+var b=a
+var c=a
+var d=a
+1abcd2
+2abc11
+";
+    let mut universe = Universe::new_empty(5, 3);
+    universe.set_rule_table(table).unwrap();
+    assert_eq!(universe.rule(), "table");
+    for col in 0..4 {
+        universe.set_state(1, col, 2).unwrap();
+    }
+    universe.set_state(1, 0, 1).unwrap();
+    universe.tick();
+    assert_eq!(universe.states()[5..10], [2, 1, 2, 2, 0]);
+
+    assert!(universe.set_rule_table("@RULE Broken\n@TREE\nnum_states=2\n").is_err());
+    assert!(universe.set_rule_table("n_states:2\nneighborhood:Moore\n0,1,1,1,0,0,0,0,1\n").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_rule_callback() {
+    // Conway's Life written in JavaScript runs like the built-in rule.
+    let life = js_sys::Function::new_with_args("alive, neighbors", "return neighbors === 3 || (alive === 1 && neighbors === 2);");
+    let mut universe = UniverseBuilder::new().size(12, 12).seed(4).density(0.35).build().unwrap();
+    let mut expected = universe.snapshot();
+    universe.set_rule_callback(Some(life));
+    for _ in 0..5 {
+        universe.tick();
+        expected.tick();
+    }
+    assert!(universe == expected);
+
+    // A callback that throws leaves the cells alone.
+    let broken = js_sys::Function::new_with_args("", "throw new Error('no rule');");
+    universe.set_rule_callback(Some(broken));
+    universe.tick();
+    assert!(universe == expected);
+
+    universe.set_rule_callback(None);
+    universe.tick();
+    expected.tick();
+    assert!(universe == expected);
+}
+
+#[wasm_bindgen_test]
+pub fn test_alternating_rules() {
+    let mut universe = UniverseBuilder::new().size(16, 16).seed(9).density(0.4).build().unwrap();
+    let mut expected = universe.snapshot();
+    universe.set_rule("b3/s23 | B36/S23").unwrap();
+    assert_eq!(universe.rule(), "B3/S23|B36/S23");
+    for generation in 0..6 {
+        expected.set_rule(if generation % 2 == 0 { "B3/S23" } else { "B36/S23" }).unwrap();
+        universe.tick();
+        expected.tick();
+    }
+    assert!(universe == expected);
+
+    // The rules must agree on the number of states and grow whole grids.
+    assert!(universe.set_rule("B3/S23|B2/S/C3").is_err());
+    assert!(universe.set_rule("W30|W90").is_err());
+    assert!(universe.set_rule("B3/S23|").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_morph_rule() {
+    let mut universe = UniverseBuilder::new().size(16, 16).seed(12).density(0.3).build().unwrap();
+    let mut twin = universe.clone();
+    universe.morph_rule("Seeds", "Conway", 10).unwrap();
+    twin.morph_rule("B2/S", "B3/S23", 10).unwrap();
+    assert_eq!(universe.rule(), "B3/S23");
+    for _ in 0..10 {
+        universe.tick();
+        twin.tick();
+    }
+    // Morphs with the same seed go the same way, and end in the new rule.
+    assert!(universe == twin);
+    let mut expected = universe.snapshot();
+    expected.set_rule("Conway").unwrap();
+    universe.tick();
+    expected.tick();
+    assert!(universe == expected);
+
+    assert!(universe.morph_rule("Conway", "BriansBrain", 10).is_err());
+    assert!(universe.morph_rule("Conway", "B2/S34H", 10).is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_region_rules() {
+    // Isolated halves of a dead bounded grid run like two universes.
+    let mut left = UniverseBuilder::new().size(10, 10).seed(5).density(0.4).boundary(Boundary::Dead).build().unwrap();
+    let mut right = UniverseBuilder::new().size(10, 10).seed(6).density(0.4).boundary(Boundary::Dead).build().unwrap();
+    right.set_rule("HighLife").unwrap();
+    let mut universe = UniverseBuilder::new().size(20, 10).density(0.0).boundary(Boundary::Dead).build().unwrap();
+    for row in 0..10 {
+        for col in 0..10 {
+            universe.set_cell(row, col, left.get_cell(row, col).unwrap()).unwrap();
+            universe.set_cell(row, col + 10, right.get_cell(row, col).unwrap()).unwrap();
+        }
+    }
+    universe.set_region_rule(0, 10, 10, 100, "B36/S23").unwrap();
+    universe.set_isolated_regions(true);
+    assert_eq!(universe.region_rule(3, 4).unwrap(), "B3/S23");
+    assert_eq!(universe.region_rule(3, 14).unwrap(), "B36/S23");
+    for _ in 0..8 {
+        universe.tick();
+        left.tick();
+        right.tick();
+    }
+    for row in 0..10 {
+        for col in 0..10 {
+            assert_eq!(universe.get_cell(row, col).unwrap(), left.get_cell(row, col).unwrap());
+            assert_eq!(universe.get_cell(row, col + 10).unwrap(), right.get_cell(row, col).unwrap());
+        }
+    }
+
+    // Open edges let a glider fly from one region into the other.
+    universe.empty_cells();
+    universe.set_isolated_regions(false);
+    universe.set_cells(&[(1, 6), (2, 7), (3, 5), (3, 6), (3, 7)]);
+    for _ in 0..16 {
+        universe.tick();
+    }
+    assert_eq!(universe.get_cells().count_ones(..), 5);
+    assert!(universe.get_cell(7, 11).unwrap());
+
+    assert!(universe.set_region_rule(0, 0, 5, 5, "BriansBrain").is_err());
+    assert!(universe.set_region_rule(10, 0, 5, 5, "HighLife").is_err());
+    universe.set_rule("BriansBrain").unwrap();
+    assert_eq!(universe.region_rule(3, 14).unwrap(), "B2/S/C3");
+}
+
+#[wasm_bindgen_test]
+pub fn test_noise() {
+    // Nothing is born or dies, so only the noise changes cells.
+    let mut universe = UniverseBuilder::new().size(100, 100).seed(8).density(0.0).build().unwrap();
+    universe.set_rule("B/S012345678").unwrap();
+    let mut twin = universe.clone();
+    universe.set_noise_rate(0.1);
+    twin.set_noise_rate(0.1);
+    assert_eq!(universe.noise_rate(), 0.1);
+    universe.tick();
+    twin.tick();
+    assert!(universe == twin);
+    let flipped = universe.get_cells().count_ones(..);
+    assert!((850..1150).contains(&flipped), "{} cells flipped", flipped);
+
+    let before = universe.snapshot();
+    universe.set_noise_rate(0.0);
+    universe.tick();
+    assert!(universe == before);
+
+    universe.set_noise_rate(2.0);
+    assert_eq!(universe.noise_rate(), 1.0);
+    universe.tick();
+    assert_eq!(universe.get_cells().count_ones(..), 10000 - flipped);
+}
+
+#[wasm_bindgen_test]
+pub fn test_forest_fire() {
+    let mut universe = UniverseBuilder::new().size(9, 9).seed(2).density(0.0).boundary(Boundary::Dead).build().unwrap();
+    universe.set_rule("ForestFire/G1/L0").unwrap();
+    assert_eq!((universe.rule(), universe.state_count()), ("ForestFire/G1/L0".to_string(), 3));
+    universe.tick();
+    assert!(universe.states().iter().all(|&state| state == 1));
+
+    // Fire spreads to the von Neumann neighbors and leaves empty ground.
+    universe.set_rule("forestfire/g0/l0").unwrap();
+    universe.set_state(4, 4, 2).unwrap();
+    universe.tick();
+    let states = universe.states();
+    assert_eq!(states[4 * 9 + 4], 0);
+    for &(row, col) in &[(3, 4), (5, 4), (4, 3), (4, 5)] {
+        assert_eq!(states[row * 9 + col], 2);
+    }
+    assert_eq!(states[3 * 9 + 3], 1);
+    assert_eq!(universe.pixels()[(4 * 9 + 4) * 4..(4 * 9 + 5) * 4], [0x3b, 0x2a, 0x1a, 0xff]);
+    for _ in 0..20 {
+        universe.tick();
+    }
+    assert!(universe.states().iter().all(|&state| state == 0));
+
+    // Lightning strikes a grown forest at random, the same way every time
+    // with the same seed.
+    universe.set_rule("ForestFire").unwrap();
+    assert_eq!(universe.rule_name(), Some("ForestFire".to_string()));
+    let mut twin = universe.clone();
+    for _ in 0..50 {
+        universe.tick();
+        twin.tick();
+    }
+    assert_eq!(universe.states(), twin.states());
+    assert!(universe.set_rule("ForestFire/G2").is_err());
+    assert!(universe.set_rule("ForestFire/X0.1").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_sir() {
+    let mut universe = UniverseBuilder::new().size(11, 11).density(0.0).build().unwrap();
+    universe.set_rule("SIR/I1/R0/D0").unwrap();
+    universe.set_state(5, 5, 1).unwrap();
+    universe.tick();
+    assert_eq!(universe.births(), 8);
+    assert_eq!(universe.state_counts(), vec![112, 9, 0]);
+
+    // Everyone recovers at once and stays immune for two generations.
+    universe.set_rule("sir/i0/r1/d2").unwrap();
+    assert_eq!((universe.rule(), universe.state_count()), ("SIR/I0/R1/D2".to_string(), 4));
+    universe.tick();
+    assert_eq!(universe.state_counts(), vec![112, 0, 9, 0]);
+    universe.tick();
+    assert_eq!(universe.state_counts(), vec![112, 0, 0, 9]);
+    universe.tick();
+    assert_eq!(universe.state_counts(), vec![121, 0, 0, 0]);
+    assert_eq!(universe.births(), 0);
+
+    universe.set_rule("SIR").unwrap();
+    assert_eq!(universe.rule_name(), Some("SIR".to_string()));
+    assert_eq!(universe.state_count(), 3);
+    assert!(universe.set_rule("SIR/D254").is_err());
+    assert!(universe.set_rule("SIR/I1.5").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_block_rules() {
+    // Lone particles cross their block diagonally, so with the blocks
+    // shifting every generation a particle keeps going down and right.
+    let mut universe = UniverseBuilder::new().size(8, 8).density(0.0).build().unwrap();
+    universe.set_rule("MS,D0;8;4;3;2;5;6;7;1;9;10;11;12;13;14;15").unwrap();
+    universe.set_cell(0, 0, true).unwrap();
+    for _ in 0..3 {
+        universe.tick();
+    }
+    assert_eq!(universe.get_cells().count_ones(..), 1);
+    assert!(universe.get_cell(3, 3).unwrap());
+    for _ in 0..5 {
+        universe.tick();
+    }
+    assert!(universe.get_cell(0, 0).unwrap());
+
+    // Critters inverts empty space, and inverts it back in the other phase.
+    universe.set_rule("Critters").unwrap();
+    assert_eq!(universe.rule(), "MS,D15;14;13;3;11;5;6;1;7;9;10;2;12;4;8;0");
+    universe.empty_cells();
+    universe.tick();
+    assert_eq!(universe.get_cells().count_ones(..), 64);
+    universe.tick();
+    assert_eq!(universe.get_cells().count_ones(..), 0);
+
+    // Blocks cut off by a dead boundary stay as they are.
+    let mut bounded = UniverseBuilder::new().size(5, 5).density(0.0).boundary(Boundary::Dead).build().unwrap();
+    bounded.set_rule("Critters").unwrap();
+    bounded.tick();
+    assert_eq!(bounded.get_cells().count_ones(..), 16);
+    assert!(!bounded.get_cell(4, 2).unwrap());
+
+    assert!(universe.set_rule("MS,D0;1;2").is_err());
+    assert!(universe.set_rule("MS,D0;1;2;3;4;5;6;7;8;9;10;11;12;13;14;16").is_err());
+    assert!(universe.set_rule("Critters|B3/S23").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_sandpile() {
+    let mut universe = UniverseBuilder::new().size(41, 41).density(0.0).boundary(Boundary::Dead).build().unwrap();
+    assert!(universe.drop_sand(20, 20, 4).is_err());
+    universe.set_rule("Sandpile").unwrap();
+    universe.drop_sand(20, 20, 4).unwrap();
+    assert_eq!(universe.get_state(20, 20).unwrap(), 4);
+    universe.tick();
+    assert_eq!(universe.get_grains(20, 20).unwrap(), 0);
+    assert_eq!(universe.state_counts(), vec![1677, 4, 0, 0, 0]);
+
+    // A thousand grains settle into a pile with the symmetries of the
+    // square, none falling off the grid.
+    universe.drop_sand(20, 20, 1020).unwrap();
+    assert_eq!(universe.get_grains(20, 20).unwrap(), 1020);
+    let mut ticks = 0;
+    while universe.state_counts()[4] > 0 {
+        universe.tick();
+        ticks += 1;
+        assert!(ticks < 10_000);
+    }
+    let states = universe.states();
+    let total: u32 = states.iter().map(|&state| u32::from(state)).sum();
+    assert_eq!(total, 1024);
+    for row in 0..41 {
+        for col in 0..41 {
+            assert_eq!(states[row * 41 + col], states[col * 41 + 40 - row]);
+        }
+    }
+}
+
+#[wasm_bindgen_test]
+pub fn test_lenia() {
+    let mut universe = UniverseBuilder::new().size(32, 32).density(0.0).build().unwrap();
+    universe.set_cell(16, 16, true).unwrap();
+    universe.set_rule("Lenia").unwrap();
+    assert_eq!((universe.rule(), universe.state_count()), ("Lenia".to_string(), 2));
+    assert_eq!(universe.get_level(16, 16).unwrap(), 1.0);
+
+    // A lone cell has nothing around it, so it shrinks by a tenth a step.
+    universe.tick();
+    assert!((universe.get_level(16, 16).unwrap() - 0.9).abs() < 1e-4);
+    assert!(universe.get_cell(16, 16).unwrap());
+    assert_eq!(universe.get_level(0, 0).unwrap(), 0.0);
+
+    // Random levels stay between 0 and 1, live cells at one half and up.
+    universe.reset_with_states();
+    for _ in 0..3 {
+        universe.tick();
+    }
+    let levels = universe.levels();
+    assert!(levels.iter().all(|level| (0.0..=1.0).contains(level)));
+    for (idx, &level) in levels.iter().enumerate() {
+        assert_eq!(universe.get_cells()[idx], level >= 0.5);
+    }
+    let shade = universe.pixels()[0];
+    assert_eq!(shade, (255.0 * (1.0 - levels[0])).round() as u8);
+
+    // Inverting flips the levels along with the cells.
+    universe.invert();
+    let inverted = universe.levels();
+    for (idx, &level) in inverted.iter().enumerate() {
+        assert!((level - (1.0 - levels[idx])).abs() < 1e-6);
+        assert_eq!(universe.get_cells()[idx], levels[idx] < 0.5);
+    }
+
+    universe.set_rule("lenia/r10/m0.2/s0.02/t5").unwrap();
+    assert_eq!(universe.rule(), "Lenia/R10/M0.2/S0.02/T5");
+    assert!(universe.set_rule("Lenia/R0").is_err());
+    assert!(universe.set_rule("Lenia|B3/S23").is_err());
+    universe.set_rule("Conway").unwrap();
+    universe.set_level(0, 0, 0.7).unwrap();
+    assert_eq!(universe.get_level(0, 0).unwrap(), 1.0);
+}
+
+#[wasm_bindgen_test]
+pub fn test_smooth_life() {
+    let mut universe = UniverseBuilder::new().size(30, 30).density(0.0).build().unwrap();
+    universe.set_rule("SmoothLife/R6").unwrap();
+    assert_eq!((universe.rule(), universe.state_count()), ("SmoothLife/R6/B0.278-0.365/D0.267-0.445".to_string(), 2));
+    universe.tick();
+    assert!(universe.levels().iter().all(|&level| level < 1e-3));
+
+    // A filled grid is overcrowded everywhere and dies out.
+    for idx in 0..900 {
+        universe.set_level(idx / 30, idx % 30, 1.0).unwrap();
+    }
+    universe.tick();
+    assert!(universe.levels().iter().all(|&level| level < 1e-3));
+    assert_eq!(universe.get_cells().count_ones(..), 0);
+
+    universe.set_rule("SmoothLife").unwrap();
+    assert_eq!(universe.rule_name(), Some("SmoothLife".to_string()));
+    universe.set_rule("smoothlife/r12/b0.25-0.35/d0.25-0.45").unwrap();
+    assert_eq!(universe.rule(), "SmoothLife/R12/B0.25-0.35/D0.25-0.45");
+    assert!(universe.set_rule("SmoothLife/B0.4-0.3").is_err());
+    assert!(universe.set_rule("SmoothLife/R2").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_weighted_rules() {
+    // With every neighbor weighing 1 the weighted sum is the count, and the
+    // rule is Life.
+    let life = "NW1,NN1,NE1,WW1,ME0,EE1,SW1,SS1,SE1,HI0,RS2,RS3,RB3";
+    let mut conway = Universe::new_with_seed(32, 32, 7);
+    let mut weighted = Universe::new_with_seed(32, 32, 7);
+    weighted.set_rule(life).unwrap();
+    assert_eq!(weighted.rule(), life);
+    for _ in 0..10 {
+        conway.tick();
+        weighted.tick();
+    }
+    assert_eq!(weighted.get_cells(), conway.get_cells());
+
+    // A cell is born under a live one and nothing survives, so a lone cell
+    // falls one row a generation.
+    let mut universe = UniverseBuilder::new().size(8, 8).density(0.0).build().unwrap();
+    universe.set_rule("nw0,nn1,rb1").unwrap();
+    assert_eq!(universe.rule(), "NW0,NN1,NE0,WW0,ME0,EE0,SW0,SS0,SE0,HI0,RB1");
+    universe.set_cell(0, 3, true).unwrap();
+    universe.tick();
+    universe.tick();
+    assert_eq!(universe.get_cells().count_ones(..), 1);
+    assert!(universe.get_cell(2, 3).unwrap());
+
+    assert!(universe.set_rule("NW1,XX1,RB1").is_err());
+    assert!(universe.set_rule("NW1,HI1,RB1").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_colored_life() {
+    // A blinker of mixed colors flips as in Life, its new ends taking the
+    // color most of the row had.
+    let mut universe = UniverseBuilder::new().size(10, 10).density(0.0).build().unwrap();
+    universe.set_rule("immigration").unwrap();
+    assert_eq!((universe.rule(), universe.state_count()), ("Immigration".to_string(), 3));
+    universe.set_state(5, 4, 1).unwrap();
+    universe.set_state(5, 5, 2).unwrap();
+    universe.set_state(5, 6, 2).unwrap();
+    assert_eq!(universe.get_cells().count_ones(..), 3);
+    universe.tick();
+    assert_eq!(universe.get_cells().count_ones(..), 3);
+    assert!(universe.get_cell(4, 5).unwrap());
+    for row in 4..7 {
+        assert_eq!(universe.get_state(row, 5).unwrap(), 2);
+    }
+    assert_ne!(universe.pixels()[(4 * 10 + 5) * 4..][..4], universe.pixels()[0..4]);
+
+    // Under QuadLife three parents all different give the fourth color.
+    universe.set_rule("QuadLife").unwrap();
+    universe.empty_cells();
+    for (col, state) in (4..7).zip(1..4) {
+        universe.set_state(5, col, state).unwrap();
+    }
+    universe.tick();
+    assert_eq!(universe.get_state(4, 5).unwrap(), 4);
+    assert_eq!(universe.get_state(6, 5).unwrap(), 4);
+    assert_eq!(universe.get_state(5, 5).unwrap(), 2);
+
+    // Other rules keep the live cells but not their colors.
+    universe.set_rule("Conway").unwrap();
+    assert_eq!(universe.get_state(4, 5).unwrap(), 1);
+    assert_eq!(universe.get_cells().count_ones(..), 3);
+    assert!(universe.set_rule("QuadLife|B3/S23/C5").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_alive_boundary() {
+    // Every edge cell but the corners has three live neighbors beyond the
+    // edge and is born.
+    let mut universe = UniverseBuilder::new().size(5, 5).density(0.0).boundary(Boundary::Alive).build().unwrap();
+    assert_eq!(universe.boundary(), Boundary::Alive);
+    universe.tick();
+    assert_eq!(universe.get_cells().count_ones(..), 12);
+    assert!(universe.get_cell(0, 2).unwrap());
+    assert!(!universe.get_cell(0, 0).unwrap());
+    assert!(!universe.get_cell(2, 2).unwrap());
+
+    // The boundary can change at any time, keeping the cells.
+    universe.empty_cells();
+    universe.set_boundary(Boundary::Torus);
+    universe.tick();
+    assert_eq!(universe.get_cells().count_ones(..), 0);
+    universe.set_boundary(Boundary::Dead);
+    universe.set_cells(&[(0, 0), (0, 1), (1, 0)]);
+    universe.tick();
+    assert_eq!(universe.get_cells().count_ones(..), 4);
+}
+
+#[wasm_bindgen_test]
+pub fn test_cylinder_boundaries() {
+    // A blinker across the left and right edges keeps blinking on a
+    // horizontal cylinder, while one across the top and bottom edges falls
+    // apart, and the other way around on a vertical cylinder.
+    let across_cols = [(2, 5), (2, 0), (2, 1)];
+    let across_rows = [(5, 2), (0, 2), (1, 2)];
+    for &(boundary, wrapping, cut) in [
+        (Boundary::HorizontalCylinder, across_cols, across_rows),
+        (Boundary::VerticalCylinder, across_rows, across_cols),
+    ]
+    .iter()
+    {
+        let mut universe = UniverseBuilder::new().size(6, 6).density(0.0).boundary(boundary).build().unwrap();
+        universe.set_cells(&wrapping);
+        universe.tick();
+        assert_eq!(universe.get_cells().count_ones(..), 3);
+        universe.tick();
+        let mut expected = Universe::new_empty(6, 6);
+        expected.set_cells(&wrapping);
+        assert_eq!(universe.get_cells(), expected.get_cells());
+
+        universe.empty_cells();
+        universe.set_cells(&cut);
+        universe.tick();
+        assert_eq!(universe.get_cells().count_ones(..), 0);
+    }
+}
+
+#[wasm_bindgen_test]
+pub fn test_twisted_boundaries() {
+    // Below (5, 1) on a 6x6 Klein bottle is (0, 4), on the mirrored column,
+    // so these three cells are a blinker and flip to a row.
+    let mut universe = UniverseBuilder::new().size(6, 6).density(0.0).boundary(Boundary::KleinBottle).build().unwrap();
+    universe.set_cells(&[(5, 1), (0, 4), (1, 4)]);
+    universe.tick();
+    let mut expected = Universe::new_empty(6, 6);
+    expected.set_cells(&[(0, 3), (0, 4), (0, 5)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+
+    // Its left and right edges have no twist.
+    universe.empty_cells();
+    universe.set_cells(&[(2, 5), (2, 0), (2, 1)]);
+    universe.tick();
+    expected.empty_cells();
+    expected.set_cells(&[(1, 0), (2, 0), (3, 0)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+
+    // A cross-surface twists those too, right of (1, 5) being (4, 0).
+    universe.set_boundary(Boundary::CrossSurface);
+    universe.empty_cells();
+    universe.set_cells(&[(1, 5), (4, 0), (4, 1)]);
+    universe.tick();
+    expected.empty_cells();
+    expected.set_cells(&[(3, 0), (4, 0), (5, 0)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+}
+
+#[wasm_bindgen_test]
+pub fn test_torus_shift() {
+    // Right of (2, 5) on a torus shifted by a row is (3, 0), so these three
+    // cells are a blinker.
+    let mut universe = UniverseBuilder::new().size(6, 6).density(0.0).build().unwrap();
+    universe.set_torus_shift(1);
+    assert_eq!(universe.torus_shift(), 1);
+    universe.set_cells(&[(2, 4), (2, 5), (3, 0)]);
+    universe.tick();
+    let mut expected = Universe::new_empty(6, 6);
+    expected.set_cells(&[(1, 5), (2, 5), (3, 5)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+    universe.tick();
+    expected.empty_cells();
+    expected.set_cells(&[(2, 4), (2, 5), (3, 0)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+
+    // Shifting by the whole height is no shift at all.
+    universe.set_torus_shift(6);
+    universe.empty_cells();
+    universe.set_cells(&[(2, 5), (2, 0), (2, 1)]);
+    universe.tick();
+    expected.empty_cells();
+    expected.set_cells(&[(1, 0), (2, 0), (3, 0)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+}
+
+#[wasm_bindgen_test]
+pub fn test_infinite_universe() {
+    // A glider flies on for ever, from negative coordinates across chunks,
+    // without meeting an edge or leaving empty chunks behind.
+    let mut universe = InfiniteUniverse::new();
+    let glider = [(-2, -1), (-1, 0), (0, -2), (0, -1), (0, 0)];
+    for &(row, col) in glider.iter() {
+        universe.set_cell(row, col, true);
+    }
+    assert_eq!((universe.population(), universe.chunk_count()), (5, 4));
+    for _ in 0..400 {
+        universe.tick();
+    }
+    assert_eq!(universe.generation(), 400);
+    assert_eq!(universe.population(), 5);
+    assert_eq!(universe.chunk_count(), 1);
+    for &(row, col) in glider.iter() {
+        assert!(universe.get_cell(row + 100, col + 100));
+    }
+
+    // A blinker across the corner of four chunks blinks as anywhere else.
+    universe.set_cells(&[98, 99, 99, 100, 100, 98, 100, 99, 100, 100], false);
+    assert_eq!(universe.chunk_count(), 0);
+    universe.set_cells(&[63, 64, 64, 64, 65, 64], true);
+    universe.tick();
+    assert!(universe.get_cell(64, 63) && universe.get_cell(64, 64) && universe.get_cell(64, 65));
+    assert_eq!(universe.population(), 3);
+
+    universe.set_rule("HighLife").unwrap();
+    assert_eq!(universe.rule(), "B36/S23");
+    assert!(universe.set_rule("B03/S23").is_err());
+    assert!(universe.set_rule("BriansBrain").is_err());
+    assert!(universe.set_rule("Wireworld").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_cells_in_rect() {
+    let mut universe = Universe::new_empty(40, 10);
+    universe.set_cells(&[(1, 2), (2, 3), (2, 35), (3, 39)]);
+    // Two words a row for a window 40 columns wide, the columns past the
+    // right edge dead.
+    assert_eq!(universe.cells_in_rect(1, 2, 40, 3), vec![1, 0, 1 << 1, 1 << 1, 0, 1 << 5]);
+    assert_eq!(universe.live_cells_in_rect(1, 2, 40, 3), vec![1, 2, 2, 3, 2, 35, 3, 39]);
+    assert_eq!(universe.cells_in_rect(9, 38, 4, 3), vec![0, 0, 0]);
+    assert!(universe.live_cells_in_rect(20, 0, 5, 5).is_empty());
+
+    // Windows of an unbounded universe go anywhere and across chunks.
+    let mut infinite = InfiniteUniverse::new();
+    infinite.set_cells(&[-1, -1, -1, 0, 0, 63, 0, 64], true);
+    assert_eq!(infinite.cells_in_rect(-1, -1, 2, 1), vec![0b11]);
+    assert_eq!(infinite.cells_in_rect(0, 40, 40, 2), vec![1 << 23 | 1 << 24, 0, 0, 0]);
+    assert_eq!(infinite.live_cells_in_rect(-1, -2, 70, 2), vec![-1, -1, -1, 0, 0, 63, 0, 64]);
+}
+
+#[wasm_bindgen_test]
+pub fn test_mirror_boundary() {
+    // A mirrored 6x6 universe runs like a 12x12 torus holding it and its
+    // reflections in both directions.
+    let mut mirrored = UniverseBuilder::new().size(6, 6).seed(3).density(0.4).boundary(Boundary::Mirror).build().unwrap();
+    let mut torus = Universe::new_empty(12, 12);
+    for row in 0..12 {
+        for col in 0..12 {
+            let reflect = |coordinate: u32| if coordinate < 6 { coordinate } else { 11 - coordinate };
+            let alive = mirrored.get_cell(reflect(row), reflect(col)).unwrap();
+            torus.set_cell(row, col, alive).unwrap();
+        }
+    }
+    for _ in 0..10 {
+        mirrored.tick();
+        torus.tick();
+    }
+    assert_eq!(mirrored.cells_in_rect(0, 0, 6, 6), torus.cells_in_rect(0, 0, 6, 6));
+    assert!(mirrored.get_cells().count_ones(..) > 0);
+}
+
+#[wasm_bindgen_test]
+pub fn test_auto_expand() {
+    // A glider flying down and right grows the grid ahead of it instead of
+    // crashing into the edge, the grid growing on no other side.
+    let mut universe = UniverseBuilder::new().size(8, 8).density(0.0).boundary(Boundary::Dead).build().unwrap();
+    universe.set_auto_expand(Some(2));
+    universe.put_glider(3, 3, None).unwrap();
+    for _ in 0..80 {
+        universe.tick();
+    }
+    assert_eq!(universe.get_cells().count_ones(..), 5);
+    assert!(universe.width() >= 25 && universe.height() >= 25);
+    assert_eq!((universe.origin_row(), universe.origin_col()), (0, 0));
+    let (row, col, _, _) = universe.bounding_box().unwrap();
+    assert!(universe.height() - row >= 2 && universe.width() - col >= 2);
+
+    // Growth above and left moves the cells and shifts the origin.
+    let mut universe = UniverseBuilder::new().size(6, 6).density(0.0).boundary(Boundary::Dead).build().unwrap();
+    universe.set_auto_expand(Some(2));
+    universe.set_cells(&[(0, 2), (1, 2), (2, 2)]);
+    universe.tick();
+    assert_eq!((universe.origin_row(), universe.origin_col()), (3, 3));
+    assert_eq!(universe.bounding_box(), Some((4, 4, 3, 1)));
+
+    // A torus never grows.
+    let mut torus = UniverseBuilder::new().size(6, 6).density(0.0).build().unwrap();
+    torus.set_auto_expand(Some(2));
+    torus.set_cells(&[(0, 2), (1, 2), (2, 2)]);
+    torus.tick();
+    assert_eq!((torus.width(), torus.height()), (6, 6));
+}
+
+#[wasm_bindgen_test]
+pub fn test_cube_boundary() {
+    // On a cube with faces of 8 cells the right edge of the top right face
+    // meets the left edge of the bottom left one, so these cells are a
+    // blinker.
+    let mut universe = UniverseBuilder::new().size(24, 16).density(0.0).boundary(Boundary::Cube).build().unwrap();
+    universe.set_cells(&[(3, 22), (3, 23), (11, 0)]);
+    universe.tick();
+    let mut expected = Universe::new_empty(24, 16);
+    expected.set_cells(&[(2, 23), (3, 23), (4, 23)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+
+    // Above the first face is the bottom row of the last one.
+    universe.empty_cells();
+    universe.set_cells(&[(15, 19), (0, 3), (1, 3)]);
+    universe.tick();
+    expected.empty_cells();
+    expected.set_cells(&[(0, 2), (0, 3), (0, 4)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+
+    // Soups of any rule run on the cube.
+    universe.reset_with_density(0.4);
+    universe.set_rule("R2,C0,M1,S6..11,B6..8,NM").unwrap();
+    for _ in 0..5 {
+        universe.tick();
+    }
+
+    // Other sizes have a dead boundary.
+    let mut flat = UniverseBuilder::new().size(20, 16).density(0.0).boundary(Boundary::Cube).build().unwrap();
+    flat.set_cells(&[(3, 18), (3, 19), (11, 0)]);
+    flat.tick();
+    assert_eq!(flat.get_cells().count_ones(..), 0);
+}
+
+#[wasm_bindgen_test]
+pub fn test_universe_3d() {
+    // A 2x2x2 block of cells with 7 neighbors each dies out under 4555 but
+    // is still under 5766, which no cell around it has 6 neighbors for.
+    let mut universe = Universe3d::new(6, 6, 6);
+    assert_eq!(universe.rule(), "4555");
+    let block = [2, 2, 2, 2, 2, 3, 2, 3, 2, 2, 3, 3, 3, 2, 2, 3, 2, 3, 3, 3, 2, 3, 3, 3];
+    universe.set_cells(&block, true).unwrap();
+    universe.tick_3d();
+    assert_eq!(universe.population(), 0);
+
+    universe.set_rule("5766").unwrap();
+    universe.set_cells(&block, true).unwrap();
+    universe.tick_3d();
+    assert_eq!(universe.population(), 8);
+    assert_eq!(universe.generation(), 2);
+    assert!(universe.get_cell(3, 3, 3).unwrap());
+    assert!(!universe.get_cell(4, 3, 3).unwrap());
+
+    // Layers read out one at a time.
+    assert_eq!(universe.layer_cells(2).unwrap(), vec![0, 0, 0b1100, 0b1100, 0, 0]);
+    assert_eq!(universe.layer_cells(0).unwrap(), vec![0; 6]);
+    let pixels = universe.layer_pixels(3).unwrap();
+    assert_eq!(pixels.len(), 6 * 6 * 4);
+    assert_eq!(&pixels[(2 * 6 + 2) * 4..(2 * 6 + 3) * 4], &[0x00, 0x00, 0x00, 0xff]);
+    assert!(universe.layer_cells(6).is_err());
+    assert!(universe.set_cell(0, 6, 0, true).is_err());
+
+    universe.set_rule("9,13,10,13").unwrap();
+    assert_eq!(universe.rule(), "9,13,10,13");
+    assert!(universe.set_rule("5576").is_err());
+    assert!(universe.set_rule("4,5,27,27").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_layered_universe() {
+    let mut universe = LayeredUniverse::new(6, 6);
+    assert_eq!(universe.add_layer("B3/S23").unwrap(), 1);
+    assert_eq!(universe.layer_count(), 2);
+    universe.set_cells(0, &[2, 1, 2, 2, 2, 3], true).unwrap();
+
+    // Uncoupled, the second layer stays empty while the first blinks.
+    universe.tick();
+    assert_eq!(universe.layer_cells(0).unwrap(), vec![0, 0b100, 0b100, 0b100, 0, 0]);
+    assert_eq!(universe.population(1).unwrap(), 0);
+
+    // Coupled, the second layer is born where the first has 3 live cells in
+    // the square around a cell.
+    universe.set_coupling(1, 0, 1).unwrap();
+    assert_eq!(universe.coupling(1, 0), 1);
+    assert_eq!(universe.coupling(0, 1), 0);
+    universe.tick();
+    assert_eq!(universe.layer_cells(0).unwrap(), vec![0, 0, 0b1110, 0, 0, 0]);
+    assert_eq!(universe.layer_cells(1).unwrap(), vec![0, 0, 0b1110, 0, 0, 0]);
+    assert!(universe.get_cell(1, 2, 1).unwrap());
+
+    // Held back by the second layer, the first one dies out.
+    universe.set_coupling(1, 0, 0).unwrap();
+    universe.set_coupling(0, 1, -1).unwrap();
+    universe.tick();
+    assert_eq!(universe.population(0).unwrap(), 0);
+    assert_eq!(universe.layer_cells(1).unwrap(), vec![0, 0b100, 0b100, 0b100, 0, 0]);
+    assert_eq!(universe.generation(), 3);
+
+    assert!(universe.set_coupling(2, 0, 1).is_err());
+    assert!(universe.set_cell(0, 0, 6, true).is_err());
+    assert!(universe.add_layer("Wireworld").is_err());
+    universe.set_layer_rule(1, "HighLife").unwrap();
+    assert_eq!(universe.layer_rule(1).unwrap(), "B36/S23");
+}
+
+#[wasm_bindgen_test]
+pub fn test_infinite_view() {
+    // Absolute coordinates reach far past 32 bits, and a view panned out
+    // there addresses the cells relative to its top left corner.
+    let mut universe = InfiniteUniverse::new();
+    let far = 1 << 40;
+    universe.set_cells(&[far, -far, far + 1, -far + 2], true);
+    universe.set_origin(far - 1, -far - 1);
+    assert_eq!((universe.origin_row(), universe.origin_col()), (far - 1, -far - 1));
+    assert_eq!(universe.view_cells(4, 3), vec![0, 0b10, 0b1000]);
+    assert_eq!(universe.live_view_cells(4, 3), vec![1, 1, 2, 3]);
+    assert_eq!(universe.absolute_row(1), far);
+    assert_eq!(universe.absolute_col(3), -far + 2);
+    assert_eq!((universe.view_row(far + 1), universe.view_col(-far + 2)), (2, 3));
+
+    universe.pan(2, -1);
+    assert_eq!(universe.view_row(far), -1);
+    assert_eq!(universe.live_view_cells(5, 2), vec![0, 4]);
+    assert!(universe.get_cell(universe.absolute_row(0), universe.absolute_col(4)));
+}
+
+#[wasm_bindgen_test]
+pub fn test_cell_mask() {
+    // Without its fourth column a blinker can only lie down on one side.
+    let mut universe = Universe::new_empty(5, 5);
+    let mut mask = vec![1; 25];
+    for row in 0..5 {
+        mask[row * 5 + 3] = 0;
+    }
+    universe.set_mask(&mask).unwrap();
+    universe.set_cells(&[(1, 2), (2, 2), (3, 2), (1, 3)]);
+    assert!(!universe.get_cell(1, 3).unwrap());
+    universe.tick();
+    let mut expected = Universe::new_empty(5, 5);
+    expected.set_cells(&[(2, 1), (2, 2)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+    universe.toggle_cell(0, 3).unwrap();
+    assert!(!universe.get_cell(0, 3).unwrap());
+    assert!(!universe.cell_exists(4, 3).unwrap() && universe.cell_exists(4, 4).unwrap());
+
+    universe.set_render_newlines(false, false);
+    assert_eq!(universe.render().lines().nth(2), Some("◼◻◻ ◼"));
+    assert_eq!(&universe.pixels()[(2 * 5 + 3) * 4..][..4], &[0, 0, 0, 0]);
+
+    // The mask turns with the cells.
+    universe.rotate_cw();
+    assert!(!universe.cell_exists(3, 2).unwrap() && universe.cell_exists(2, 3).unwrap());
+    universe.clear_mask();
+    universe.set_cell(3, 2, true).unwrap();
+    assert!(universe.get_cell(3, 2).unwrap());
+
+    // A circular arena leaves out the corners.
+    let mut arena = UniverseBuilder::new().size(10, 10).seed(5).density(1.0).build().unwrap();
+    arena.set_circular_mask();
+    assert!(!arena.cell_exists(0, 0).unwrap() && !arena.cell_exists(9, 9).unwrap());
+    assert!(arena.cell_exists(0, 5).unwrap() && arena.cell_exists(5, 5).unwrap());
+    assert!(!arena.get_cell(0, 0).unwrap() && arena.get_cell(5, 5).unwrap());
+    for _ in 0..5 {
+        arena.tick();
+        assert!(!arena.get_cell(0, 9).unwrap() && !arena.get_cell(9, 0).unwrap());
+    }
+    assert!(universe.set_mask(&[1; 3]).is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_edge_source() {
+    // The left edge blinks on and off, feeding the cell next to it once.
+    let mut universe = UniverseBuilder::new().size(8, 8).density(0.0).boundary(Boundary::Dead).build().unwrap();
+    let mut frames = vec![0; 16];
+    frames[3..6].copy_from_slice(&[1, 1, 1]);
+    universe.set_edge_source(Edge::Left, &frames).unwrap();
+    assert_eq!(universe.edge_source_period(Edge::Left), 2);
+    let mut expected = Universe::new_empty(8, 8);
+    expected.set_cells(&[(3, 0), (4, 0), (5, 0)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+
+    universe.tick();
+    expected.empty_cells();
+    expected.set_cells(&[(4, 1)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+    universe.tick();
+    expected.empty_cells();
+    expected.set_cells(&[(3, 0), (4, 0), (5, 0)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+
+    universe.clear_edge_source(Edge::Left);
+    universe.tick();
+    assert!(!universe.get_cell(3, 0).unwrap() && universe.get_cell(4, 1).unwrap());
+
+    assert!(universe.set_edge_source(Edge::Top, &[1; 12]).is_err());
+    assert!(universe.set_edge_source(Edge::Top, &[]).is_err());
+    universe.set_edge_source(Edge::Top, &[1; 8]).unwrap();
+    assert!(universe.get_cell(0, 7).unwrap());
+    universe.resize(10, 8, Anchor::TopLeft);
+    assert_eq!(universe.edge_source_period(Edge::Top), 0);
+}
+
+#[wasm_bindgen_test]
+pub fn test_tick_after_resize() {
+    // Ticks step into the grid kept from the last one, which has to follow
+    // the universe through changes of size.
+    let mut universe = Universe::new_empty(5, 5);
+    universe.set_cells(&[(2, 1), (2, 2), (2, 3)]);
+    universe.tick();
+    assert_eq!(universe.births(), 2);
+    universe.resize(9, 7, Anchor::Center);
+    universe.tick();
+    assert_eq!(universe.births(), 2);
+    let mut expected = Universe::new_empty(9, 7);
+    expected.set_cells(&[(3, 3), (3, 4), (3, 5)]);
+    assert_eq!(universe.get_cells(), expected.get_cells());
+    universe.tick();
+    universe.tick();
+    assert_eq!(universe.get_cells(), expected.get_cells());
+}
+
+#[wasm_bindgen_test]
+pub fn test_bitwise_tick() {
+    // Two state Moore rules step 64 cells at a time, which must give what
+    // counting the same 8 neighbors one by one does.
+    let moore = [-1, -1, -1, 0, -1, 1, 0, -1, 0, 1, 1, -1, 1, 0, 1, 1];
+    let boundaries = [
+        Boundary::Torus,
+        Boundary::Dead,
+        Boundary::Alive,
+        Boundary::HorizontalCylinder,
+        Boundary::VerticalCylinder,
+        Boundary::KleinBottle,
+    ];
+    for &(width, height) in &[(1, 1), (3, 2), (64, 3), (65, 5), (70, 33)] {
+        for &boundary in &boundaries {
+            for rule in &["B3/S23", "B36/S23", "B0123478/S34678", "B2/S"] {
+                let mut fast = UniverseBuilder::new().size(width, height).seed(width + height).density(0.4).boundary(boundary).build().unwrap();
+                fast.set_rule(rule).unwrap();
+                let mut slow = fast.clone();
+                slow.set_neighborhood(&moore).unwrap();
+                for _ in 0..6 {
+                    fast.tick();
+                    slow.tick();
+                    assert_eq!(fast.get_cells(), slow.get_cells(), "{}x{} {:?} {}", width, height, boundary, rule);
+                }
+            }
+        }
+    }
+    let mut shifted = UniverseBuilder::new().size(70, 9).seed(2).density(0.4).build().unwrap();
+    shifted.set_torus_shift(3);
+    let mut slow = shifted.clone();
+    slow.set_neighborhood(&moore).unwrap();
+    for _ in 0..6 {
+        shifted.tick();
+        slow.tick();
+        assert_eq!(shifted.get_cells(), slow.get_cells());
+    }
+}
+
+#[wasm_bindgen_test]
+pub fn test_lookup_kernel() {
+    // Stepping 2x2 blocks through the table gives what adding up neighbors
+    // 64 cells at a time does, odd sizes leaving half blocks at the edges.
+    for &(width, height) in &[(1, 1), (3, 2), (5, 7), (65, 5), (70, 33)] {
+        for &boundary in &[Boundary::Torus, Boundary::Dead, Boundary::Alive, Boundary::VerticalCylinder] {
+            for rule in &["B3/S23", "B36/S23", "B0123478/S34678", "B2/S"] {
+                let mut lookup = UniverseBuilder::new().size(width, height).seed(width * height).density(0.4).boundary(boundary).build().unwrap();
+                lookup.set_rule(rule).unwrap();
+                let mut bitwise = lookup.clone();
+                lookup.set_kernel(Kernel::Lookup);
+                assert_eq!(lookup.kernel(), Kernel::Lookup);
+                assert_eq!(bitwise.kernel(), Kernel::Bitwise);
+                for _ in 0..6 {
+                    lookup.tick();
+                    bitwise.tick();
+                    assert_eq!(lookup.get_cells(), bitwise.get_cells(), "{}x{} {:?} {}", width, height, boundary, rule);
+                }
+            }
+        }
+    }
+}
+
+#[wasm_bindgen_test]
+pub fn test_quiet_tiles() {
+    // Tiles whose surroundings stood still are skipped, which must give what
+    // stepping every cell does, also after cells are set by hand.
+    let moore = [-1, -1, -1, 0, -1, 1, 0, -1, 0, 1, 1, -1, 1, 0, 1, 1];
+    for &kernel in &[Kernel::Bitwise, Kernel::Lookup] {
+        let mut tiled = Universe::new_empty(400, 200);
+        tiled.set_kernel(kernel);
+        // A glider crossing into the quiet tiles below and right of it.
+        tiled.set_cells(&[(120, 121), (121, 122), (122, 120), (122, 121), (122, 122)]);
+        tiled.set_cells(&[(150, 250), (150, 251), (150, 252), (30, 30), (30, 31), (31, 30), (31, 31)]);
+        // Enough blocks to keep the universe from stepping its live cells
+        // alone, which would leave the tiles out.
+        for col in (4..196).step_by(8) {
+            tiled.set_cells(&[(190, col), (190, col + 1), (191, col), (191, col + 1)]);
+        }
+        let mut slow = tiled.clone();
+        slow.set_neighborhood(&moore).unwrap();
+        for generation in 0..40 {
+            if generation == 20 {
+                tiled.set_cells(&[(160, 300), (160, 301), (160, 302)]);
+                slow.set_cells(&[(160, 300), (160, 301), (160, 302)]);
+            }
+            if generation == 20 || generation == 21 {
+                // A lone cell dies, to come straight back.
+                tiled.set_cell(100, 330, true).unwrap();
+                slow.set_cell(100, 330, true).unwrap();
+            }
+            tiled.tick();
+            slow.tick();
+            assert!(!tiled.is_sparse());
+            assert_eq!(tiled.get_cells(), slow.get_cells(), "{:?} generation {}", kernel, generation);
+        }
+        tiled.set_rule("B36/S23").unwrap();
+        slow.set_rule("B36/S23").unwrap();
+        for _ in 0..10 {
+            tiled.tick();
+            slow.tick();
+            assert!(!tiled.is_sparse());
+            assert_eq!(tiled.get_cells(), slow.get_cells());
+        }
+    }
+}
+
+#[wasm_bindgen_test]
+pub fn test_hashlife() {
+    // HashLife steps as chunks do, one generation or a power of two at once.
+    let r_pentomino = Pattern::from_library("r-pentomino").unwrap();
+    let mut coords = Vec::new();
+    for row in 0..r_pentomino.height() {
+        for col in 0..r_pentomino.width() {
+            if r_pentomino.get(row, col) {
+                coords.extend_from_slice(&[i64::from(row) - 1, i64::from(col) - 1]);
+            }
+        }
+    }
+    let mut chunks = InfiniteUniverse::new();
+    let mut tree = InfiniteUniverse::with_engine(Engine::HashLife);
+    assert_eq!((chunks.engine(), tree.engine()), (Engine::Chunks, Engine::HashLife));
+    chunks.set_cells(&coords, true);
+    tree.set_cells(&coords, true);
+    assert_eq!(tree.cells_in_rect(-1, -1, 3, 3), chunks.cells_in_rect(-1, -1, 3, 3));
+    for _ in 0..10 {
+        chunks.tick();
+        tree.tick();
+    }
+    chunks.tick_superstep(8).unwrap();
+    tree.tick_superstep(8).unwrap();
+    assert_eq!(tree.generation(), 266);
+    assert_eq!(tree.population(), chunks.population());
+    assert_eq!(tree.live_cells_in_rect(-150, -150, 300, 300), chunks.live_cells_in_rect(-150, -150, 300, 300));
+
+    // The R-pentomino settles into 116 cells by generation 1103, 6 of them
+    // in gliders flying off.
+    chunks.tick_superstep(11).unwrap();
+    tree.tick_superstep(11).unwrap();
+    assert_eq!((tree.generation(), tree.population()), (266 + 2048, 116));
+    assert!(tree.tick_superstep(62).is_err());
+    let generation = chunks.generation();
+    assert!(chunks.tick_superstep(61).is_err());
+    assert_eq!(chunks.generation(), generation);
+    assert_eq!(tree.generation(), 266 + 2048);
+
+    // A new rule steps by itself rather than what the last one gave.
+    chunks.set_rule("HighLife").unwrap();
+    tree.set_rule("HighLife").unwrap();
+    chunks.set_cells(&[-3, 1, -3, 2, -3, 3], true);
+    tree.set_cells(&[-3, 1, -3, 2, -3, 3], true);
+    chunks.tick_superstep(3).unwrap();
+    tree.tick_superstep(3).unwrap();
+    assert_eq!(tree.live_cells_in_rect(-20, -20, 40, 40), chunks.live_cells_in_rect(-20, -20, 40, 40));
+}
+
+#[wasm_bindgen_test]
+pub fn test_hashlife_cache() {
+    // Compacting drops what the current cells do not need, and stepping
+    // goes on as if it had not.
+    let gun = Pattern::from_library("gosper-glider-gun").unwrap();
+    let mut coords = Vec::new();
+    for row in 0..gun.height() {
+        for col in 0..gun.width() {
+            if gun.get(row, col) {
+                coords.extend_from_slice(&[i64::from(row), i64::from(col)]);
+            }
+        }
+    }
+    let mut kept = InfiniteUniverse::with_engine(Engine::HashLife);
+    kept.set_cells(&coords, true);
+    let mut compacted = kept.clone();
+    assert_eq!(InfiniteUniverse::new().cache_stats(), None);
+    assert_eq!(kept.cache_budget(), Some(256 << 20));
+    kept.set_cache_budget(None);
+    compacted.set_cache_budget(Some(1));
+    for _ in 0..6 {
+        kept.tick_superstep(6).unwrap();
+        compacted.tick_superstep(6).unwrap();
+    }
+    // Once for the budget, then after every step.
+    let stats = compacted.cache_stats().unwrap();
+    assert_eq!(stats.compactions, 7);
+    assert!(stats.hits > 0 && stats.misses > 0);
+    assert!(stats.nodes < kept.cache_stats().unwrap().nodes);
+    assert!(stats.bytes < kept.cache_stats().unwrap().bytes);
+    assert_eq!(compacted.population(), kept.population());
+    assert_eq!(compacted.live_cells_in_rect(-10, -10, 500, 500), kept.live_cells_in_rect(-10, -10, 500, 500));
+
+    let before = kept.cache_stats().unwrap();
+    kept.compact_cache();
+    let after = kept.cache_stats().unwrap();
+    assert!(after.nodes < before.nodes && after.results < before.results);
+    assert_eq!(after.compactions, 1);
+    kept.tick_superstep(10).unwrap();
+    compacted.tick_superstep(10).unwrap();
+    assert_eq!(compacted.live_cells_in_rect(-10, -10, 800, 800), kept.live_cells_in_rect(-10, -10, 800, 800));
+}
+
+#[wasm_bindgen_test]
+pub fn test_sparse_tick() {
+    // A few gliders on a big board step from their live cells alone, across
+    // the edges as anywhere else, until there are too many of them.
+    let moore = [-1, -1, -1, 0, -1, 1, 0, -1, 0, 1, 1, -1, 1, 0, 1, 1];
+    let glider = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+    let boundaries = [
+        Boundary::Torus,
+        Boundary::Dead,
+        Boundary::HorizontalCylinder,
+        Boundary::VerticalCylinder,
+        Boundary::KleinBottle,
+        Boundary::CrossSurface,
+    ];
+    for &boundary in &boundaries {
+        for rule in &["B3/S23", "B36/S23", "B3/S012345678"] {
+            let mut sparse = UniverseBuilder::new().size(240, 90).density(0.0).boundary(boundary).build().unwrap();
+            sparse.set_rule(rule).unwrap();
+            sparse.set_torus_shift(5);
+            for &(row, col) in &glider {
+                sparse.set_cells(&[(row + 84, col + 234), (row + 40, col + 60)]);
+            }
+            let mut dense = sparse.clone();
+            dense.set_neighborhood(&moore).unwrap();
+            for generation in 0..40 {
+                sparse.tick();
+                dense.tick();
+                assert!(sparse.is_sparse() || generation > 0);
+                assert!(!dense.is_sparse());
+                assert_eq!(sparse.get_cells(), dense.get_cells(), "{:?} {} generation {}", boundary, rule, generation);
+                assert_eq!(sparse.births(), dense.births());
+                assert_eq!(sparse.deaths(), dense.deaths());
+            }
+            sparse.toggle_cell(10, 10).unwrap();
+            dense.toggle_cell(10, 10).unwrap();
+            sparse.tick();
+            dense.tick();
+            assert_eq!(sparse.get_cells(), dense.get_cells());
+        }
+    }
+
+    let mut universe = Universe::new_empty(120, 90);
+    universe.tick();
+    assert!(universe.is_sparse());
+    universe.set_cells(&[(30, 30), (30, 31), (31, 30), (31, 31), (40, 40), (40, 41), (41, 40), (41, 41)]);
+    universe.set_cells(&[(50, 50), (50, 51), (51, 50), (51, 51), (60, 60), (60, 61), (61, 60), (61, 61)]);
+    universe.set_cells(&[(70, 70), (70, 71), (71, 70), (71, 71), (80, 80), (80, 81), (81, 80), (81, 81)]);
+    universe.tick();
+    assert!(universe.is_sparse());
+    universe.set_cells(&[(10, 10), (10, 11), (11, 10), (11, 11), (20, 20), (20, 21), (21, 20), (21, 21)]);
+    universe.set_cells(&[(10, 70), (10, 71), (11, 70), (11, 71), (20, 80), (20, 81), (21, 80), (21, 81)]);
+    universe.set_cells(&[(70, 10), (70, 11), (71, 10), (71, 11), (80, 20), (80, 21), (81, 20), (81, 21)]);
+    universe.tick();
+    assert!(!universe.is_sparse());
+    assert_eq!(universe.get_cells().count_ones(..), 48);
+    universe.set_rule("BriansBrain").unwrap();
+    universe.tick();
+    assert!(!universe.is_sparse());
+}
+
+#[wasm_bindgen_test]
+pub fn test_start_threads() {
+    // Builds without the threads feature tick on one thread, and say so
+    // when asked for more.
+    assert!(Universe::start_threads(4).is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_tick_n() {
+    // A blinker turns two cells on and two off every generation.
+    let mut blinker = Universe::new_empty(8, 8);
+    blinker.set_cells(&[(3, 2), (3, 3), (3, 4)]);
+    let stats = blinker.tick_n(5);
+    assert_eq!(stats, TickStats { generations: 5, births: 10, deaths: 10 });
+    assert_eq!((blinker.births(), blinker.deaths()), (2, 2));
+    assert_eq!(blinker.get_cells().count_ones(..), 3);
+    assert!(blinker.get_cell(2, 3).unwrap());
+
+    let mut batched = UniverseBuilder::new().size(48, 32).seed(7).build().unwrap();
+    let mut stepped = batched.clone();
+    let (mut births, mut deaths) = (0, 0);
+    for _ in 0..30 {
+        stepped.tick();
+        births += u64::from(stepped.births());
+        deaths += u64::from(stepped.deaths());
+    }
+    assert_eq!(batched.tick_n(30), TickStats { generations: 30, births, deaths });
+    assert_eq!(batched.get_cells(), stepped.get_cells());
+    assert_eq!(batched.tick_n(0).generations, 0);
+}
+
+#[wasm_bindgen_test]
+pub fn test_tick_for_budget() {
+    let mut universe = UniverseBuilder::new().size(64, 64).seed(3).build().unwrap();
+    assert_eq!(universe.tick_for_budget(0.0), 0);
+    assert_eq!(universe.generation(), 0);
+    let generations = universe.tick_for_budget(5.0);
+    assert!(generations > 0);
+    assert_eq!(universe.generation(), u64::from(generations));
+}
+
+#[wasm_bindgen_test]
+pub fn test_deltas() {
+    let mut blinker = Universe::new_empty(8, 8);
+    blinker.tick();
+    assert!(blinker.take_deltas().is_empty());
+    blinker.set_record_deltas(true);
+    blinker.set_cells(&[(3, 2), (3, 3), (3, 4)]);
+    assert!(blinker.take_deltas().is_empty());
+    blinker.tick();
+    assert_eq!(blinker.take_deltas(), vec![2 * 8 + 3, 3 * 8 + 2, 3 * 8 + 4, 4 * 8 + 3]);
+    assert!(blinker.take_deltas().is_empty());
+    // Cells changing back and forth are taken once.
+    blinker.tick();
+    blinker.tick();
+    assert_eq!(blinker.take_deltas().len(), 4);
+
+    // Deltas match the cells whose states differ, under every way of
+    // stepping: the whole grid, dying states and the live cells alone.
+    for &(width, height, rule) in &[(40, 30, "B3/S23"), (40, 30, "BriansBrain"), (200, 150, "B3/S23")] {
+        let mut universe = UniverseBuilder::new().size(width, height).density(0.0).build().unwrap();
+        universe.set_rule(rule).unwrap();
+        universe.set_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2), (20, 20), (20, 21), (21, 20), (21, 22)]);
+        universe.set_record_deltas(true);
+        for _ in 0..12 {
+            let before = universe.states();
+            universe.tick();
+            let after = universe.states();
+            let changed: Vec<u32> = (0..before.len() as u32).filter(|&idx| before[idx as usize] != after[idx as usize]).collect();
+            assert_eq!(universe.take_deltas(), changed, "{} {}x{}", rule, width, height);
+        }
+        assert_eq!(universe.is_sparse(), width == 200);
+    }
+
+    // Every cell has to be redrawn once the grid grows.
+    let mut growing = Universe::new_empty(10, 10);
+    growing.set_boundary(Boundary::Dead);
+    growing.set_auto_expand(Some(2));
+    growing.set_record_deltas(true);
+    growing.set_cells(&[(1, 1), (1, 2), (2, 1), (2, 2)]);
+    growing.tick();
+    assert!(growing.width() > 10);
+    assert_eq!(growing.take_deltas().len() as u32, growing.width() * growing.height());
+    growing.set_record_deltas(false);
+    assert!(!growing.records_deltas());
+}
+
+#[wasm_bindgen_test]
+pub fn test_dirty_rects() {
+    let mut universe = Universe::new_empty(200, 150);
+    assert!(universe.take_dirty_rects().is_empty());
+    universe.set_record_dirty_rects(true);
+    assert_eq!(universe.take_dirty_rects(), vec![0, 0, 200, 64, 64, 0, 200, 64, 128, 0, 200, 22]);
+    universe.tick();
+    assert!(universe.take_dirty_rects().is_empty());
+
+    // A blinker across the corner of three tiles dirties them all, the two
+    // side by side as one rectangle, whether the whole grid is stepped or
+    // the live cells alone.
+    universe.set_cells(&[(63, 127), (63, 128), (63, 129)]);
+    universe.tick();
+    assert!(universe.is_sparse());
+    assert_eq!(universe.take_dirty_rects(), vec![0, 64, 128, 64, 64, 128, 64, 64]);
+    universe.tick();
+    assert_eq!(universe.take_dirty_rects(), vec![0, 64, 128, 64, 64, 128, 64, 64]);
+
+    // Cells changing states alone dirty their tiles too.
+    let mut brain = UniverseBuilder::new().size(130, 70).density(0.0).build().unwrap();
+    brain.set_rule("BriansBrain").unwrap();
+    brain.set_cells(&[(10, 10), (10, 11)]);
+    brain.set_record_dirty_rects(true);
+    brain.set_record_deltas(true);
+    brain.take_dirty_rects();
+    for _ in 0..20 {
+        brain.tick();
+        let rects = brain.take_dirty_rects();
+        for idx in brain.take_deltas() {
+            let (row, col) = (idx / 130, idx % 130);
+            assert!(rects
+                .chunks(4)
+                .any(|rect| (rect[0]..rect[0] + rect[3]).contains(&row) && (rect[1]..rect[1] + rect[2]).contains(&col)));
+        }
+    }
+    brain.set_record_dirty_rects(false);
+    assert!(!brain.records_dirty_rects());
+}
+
+#[wasm_bindgen_test]
+pub fn test_live_cells() {
+    let mut universe = Universe::new_empty(200, 150);
+    assert!(universe.live_cells().is_empty());
+    universe.set_cells(&[(100, 7), (3, 190), (3, 4), (3, 5), (100, 8), (4, 4), (4, 5)]);
+    assert_eq!(universe.live_cells(), vec![3, 4, 3, 5, 3, 190, 4, 4, 4, 5, 100, 7, 100, 8]);
+    universe.tick();
+    assert!(universe.is_sparse());
+    assert_eq!(universe.live_cells(), vec![3, 4, 3, 5, 4, 4, 4, 5]);
+
+    let mut dense = UniverseBuilder::new().size(30, 20).seed(5).build().unwrap();
+    let live = dense.live_cells();
+    assert_eq!(live.len(), 2 * dense.get_cells().count_ones(..));
+    assert!(live.chunks(2).all(|pair| dense.get_cell(pair[0], pair[1]).unwrap()));
+    dense.tick();
+    assert_eq!(dense.live_cells().len(), 2 * dense.get_cells().count_ones(..));
+}
+
+#[wasm_bindgen_test]
+pub fn test_incremental_kernel() {
+    // Neighbor counts kept from tick to tick give the same generations as
+    // stepping every cell, through edits, rule changes and new boundaries.
+    let boundaries = [Boundary::Torus, Boundary::Dead, Boundary::KleinBottle, Boundary::CrossSurface];
+    for &boundary in &boundaries {
+        let mut incremental = UniverseBuilder::new().size(50, 40).seed(11).boundary(boundary).build().unwrap();
+        incremental.set_torus_shift(3);
+        incremental.set_kernel(Kernel::Incremental);
+        let mut bitwise = incremental.clone();
+        bitwise.set_kernel(Kernel::Bitwise);
+        for generation in 0..60 {
+            match generation {
+                10 => {
+                    incremental.set_cells(&[(0, 0), (0, 1), (1, 0), (39, 49)]);
+                    bitwise.set_cells(&[(0, 0), (0, 1), (1, 0), (39, 49)]);
+                    incremental.toggle_cell(20, 20).unwrap();
+                    bitwise.toggle_cell(20, 20).unwrap();
+                }
+                20 => {
+                    incremental.set_rule("B36/S23").unwrap();
+                    bitwise.set_rule("B36/S23").unwrap();
+                }
+                30 => {
+                    incremental.set_boundary(Boundary::VerticalCylinder);
+                    bitwise.set_boundary(Boundary::VerticalCylinder);
+                }
+                40 => {
+                    incremental.set_rule("B2/S").unwrap();
+                    bitwise.set_rule("B2/S").unwrap();
+                }
+                _ => {}
+            }
+            incremental.tick();
+            bitwise.tick();
+            assert_eq!(incremental.get_cells(), bitwise.get_cells(), "{:?} generation {}", boundary, generation);
+            assert_eq!((incremental.births(), incremental.deaths()), (bitwise.births(), bitwise.deaths()));
+        }
+        assert_eq!(incremental.generation(), 60);
+    }
+
+    // Edits count into the cells around them: a block missing a cell grows
+    // it back.
+    let mut blocks = Universe::new_empty(64, 64);
+    blocks.set_kernel(Kernel::Incremental);
+    blocks.set_record_deltas(true);
+    blocks.set_cells(&[(10, 10), (10, 11), (11, 10), (11, 11), (30, 40), (30, 41), (31, 40), (31, 41)]);
+    blocks.tick();
+    blocks.tick();
+    assert!(blocks.take_deltas().is_empty());
+    blocks.toggle_cell(11, 11).unwrap();
+    blocks.tick();
+    assert_eq!(blocks.take_deltas(), vec![11 * 64 + 11]);
+    assert_eq!(blocks.get_cells().count_ones(..), 8);
+}
+
+#[wasm_bindgen_test]
+pub fn test_tick_band() {
+    // Three copies of a universe each stepping a band of it, swapping halos
+    // every generation, step it as a whole does.
+    let bands = [(0, 15), (15, 30), (30, 45)];
+    let words = 2;
+    for &(boundary, wrap_rows) in &[
+        (Boundary::Torus, true),
+        (Boundary::Dead, false),
+        (Boundary::HorizontalCylinder, false),
+        (Boundary::VerticalCylinder, true),
+    ] {
+        let mut whole = UniverseBuilder::new().size(60, 45).seed(21).boundary(boundary).build().unwrap();
+        let mut workers = [whole.clone(), whole.clone(), whole.clone()];
+        for generation in 0..30 {
+            whole.tick();
+            let halos: Vec<Vec<u32>> =
+                workers.iter().zip(&bands).map(|(worker, &(start, end))| worker.band_halos(start, end).unwrap()).collect();
+            for (idx, worker) in workers.iter_mut().enumerate() {
+                let dead = vec![0; words];
+                let above = match idx {
+                    0 if !wrap_rows => &dead[..],
+                    _ => &halos[(idx + 2) % 3][words..],
+                };
+                let below = match idx {
+                    2 if !wrap_rows => &dead[..],
+                    _ => &halos[(idx + 1) % 3][..words],
+                };
+                worker.tick_band(bands[idx].0, bands[idx].1, above, below).unwrap();
+            }
+            for (worker, &(start, end)) in workers.iter().zip(&bands) {
+                let rows = end - start;
+                assert_eq!(
+                    worker.cells_in_rect(start, 0, 60, rows),
+                    whole.cells_in_rect(start, 0, 60, rows),
+                    "{:?} generation {}",
+                    boundary,
+                    generation
+                );
+            }
+        }
+        assert_eq!(workers[1].generation(), 30);
+    }
+
+    let mut universe = Universe::new_empty(40, 30);
+    let halo = [0, 0];
+    assert!(universe.tick_band(0, 10, &halo, &halo).is_ok());
+    assert!(universe.tick_band(5, 5, &halo, &halo).is_err());
+    assert!(universe.tick_band(20, 31, &halo, &halo).is_err());
+    assert!(universe.band_halos(20, 31).is_err());
+    assert!(universe.tick_band(0, 10, &halo[..1], &halo).is_err());
+    universe.set_torus_shift(2);
+    assert!(universe.tick_band(0, 10, &halo, &halo).is_err());
+    universe.set_torus_shift(0);
+    universe.set_rule("BriansBrain").unwrap();
+    assert!(universe.tick_band(0, 10, &halo, &halo).is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_memory_stats() {
+    // 128x64 cells take 1 KiB, and so does the copy ticks step into.
+    let mut universe = UniverseBuilder::new().size(128, 64).seed(4).build().unwrap();
+    let stats = universe.memory_stats();
+    assert_eq!(stats, MemoryStats { cells: 2048, states: 0, caches: 0, recording: 0, total: 2048 });
+    universe.set_kernel(Kernel::Lookup);
+    universe.set_record_deltas(true);
+    universe.tick();
+    let stats = universe.memory_stats();
+    assert!(stats.caches >= 1 << 16);
+    assert_eq!(stats.recording, 1024);
+    assert_eq!(stats.total, stats.cells + stats.states + stats.caches + stats.recording);
+
+    let mut unshrunk = universe.clone();
+    universe.shrink_to_fit();
+    let stats = universe.memory_stats();
+    assert_eq!((stats.cells, stats.caches, stats.recording), (1024, 0, 1024));
+    for _ in 0..5 {
+        universe.tick();
+        unshrunk.tick();
+    }
+    assert_eq!(universe.get_cells(), unshrunk.get_cells());
+    assert_eq!(universe.take_deltas(), unshrunk.take_deltas());
+
+    universe.set_rule("BriansBrain").unwrap();
+    universe.tick();
+    assert!(universe.memory_stats().states >= 128 * 64);
+
+    // Sparse universes keep the cells but not the copy, and the live cells
+    // on top.
+    let mut sparse = Universe::new_empty(512, 512);
+    sparse.set_cells(&[(10, 11), (11, 12), (12, 10), (12, 11), (12, 12)]);
+    sparse.tick();
+    assert!(sparse.is_sparse());
+    let stats = sparse.memory_stats();
+    assert_eq!(stats.cells, 512 * 512 / 8);
+    assert!(stats.caches > 0 && stats.caches < 1024);
+}
+
+#[wasm_bindgen_test]
+pub fn test_timing_stats() {
+    let mut universe = UniverseBuilder::new().size(64, 64).seed(9).build().unwrap();
+    universe.tick();
+    assert!(universe.timing_stats().is_none());
+    universe.set_timing(4, None);
+    assert_eq!(universe.timing_stats().unwrap().window, 0);
+    for _ in 0..10 {
+        universe.tick();
+    }
+    universe.tick_n(3);
+    let stats = universe.timing_stats().unwrap();
+    assert_eq!((stats.ticks, stats.window), (13, 4));
+    assert!(0.0 <= stats.min_ms && stats.min_ms <= stats.median_ms && stats.median_ms <= stats.p90_ms);
+    assert!(stats.p90_ms <= stats.p99_ms && stats.p99_ms <= stats.max_ms);
+    assert!(stats.min_ms <= stats.average_ms && stats.average_ms <= stats.max_ms);
+    universe.set_timing(0, None);
+    assert!(universe.timing_stats().is_none());
+
+    // Huge windows are cut down to size rather than set aside up front.
+    let mut universe = Universe::new_empty(8, 8);
+    universe.set_timing(u32::MAX, None);
+    universe.tick_n(70000);
+    let stats = universe.timing_stats().unwrap();
+    assert_eq!((stats.ticks, stats.window), (70000, 65536));
+}